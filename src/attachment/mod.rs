@@ -0,0 +1,378 @@
+use crate::auth::is_authenticated_as_author;
+use crate::block::{BlockKind, InvitationBlock};
+use crate::db::attachments::{self, Attachment};
+use crate::db::{Invitation, Party};
+use actix_files::NamedFile;
+use actix_multipart::Multipart;
+use actix_web::http::header::{self, ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{HttpRequest, HttpResponse, Responder, Scope, get, post, web};
+use futures_util::StreamExt;
+use image::{ImageFormat, ImageReader, Limits};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::Connection;
+use serde_json::{Map, json};
+use std::fs;
+use std::io::Cursor;
+use uuid::Uuid;
+
+/// Directory uploaded files and their thumbnails are stored in
+pub const UPLOAD_DIR: &str = "uploads";
+
+/// Largest file an organizer can upload
+const MAX_FILE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Longest edge of generated thumbnails; smaller images are shown as they are
+const THUMBNAIL_SIZE: u32 = 1024;
+
+/// Images with a larger edge are rejected before they are decoded
+const MAX_IMAGE_DIMENSION: u32 = 12_000;
+
+fn file_path(attachment_id: &str) -> String {
+    format!("{}/{}", UPLOAD_DIR, attachment_id)
+}
+
+fn thumbnail_path(attachment_id: &str) -> String {
+    format!("{}/{}.thumb.png", UPLOAD_DIR, attachment_id)
+}
+
+/// Determines the type from the content, since the browser's claim can't be trusted
+fn detect_content_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"%PDF-") {
+        return Some("application/pdf");
+    }
+    match image::guess_format(data).ok()? {
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Gif => Some("image/gif"),
+        ImageFormat::WebP => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Strips directories and control characters from the name the browser sent
+fn clean_file_name(raw: &str) -> String {
+    let name: String = raw
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or("")
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(200)
+        .collect();
+    let name = name.trim();
+    if name.is_empty() {
+        "attachment".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+enum StoreError {
+    UnreadableImage,
+    Io(std::io::Error),
+}
+
+/// Writes an upload to disk, together with a thumbnail for large images.
+/// Returns whether a thumbnail was created.
+fn store(attachment_id: &str, data: &[u8], is_image: bool) -> Result<bool, StoreError> {
+    let mut has_thumbnail = false;
+    fs::create_dir_all(UPLOAD_DIR).map_err(StoreError::Io)?;
+
+    if is_image {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+        limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+        let mut reader = ImageReader::new(Cursor::new(data))
+            .with_guessed_format()
+            .map_err(StoreError::Io)?;
+        reader.limits(limits);
+        let image = reader.decode().map_err(|_| StoreError::UnreadableImage)?;
+
+        if image.width() > THUMBNAIL_SIZE || image.height() > THUMBNAIL_SIZE {
+            image
+                .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+                .save_with_format(thumbnail_path(attachment_id), ImageFormat::Png)
+                .map_err(|e| StoreError::Io(std::io::Error::other(e)))?;
+            has_thumbnail = true;
+        }
+    }
+
+    fs::write(file_path(attachment_id), data).map_err(StoreError::Io)?;
+    Ok(has_thumbnail)
+}
+
+#[post("/upload")]
+async fn upload(
+    mut payload: Multipart,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let Some(author_id) = is_authenticated_as_author(&req, &pool) else {
+        return HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}));
+    };
+
+    // Only the field named "file" is read; it is rejected as soon as it grows too large
+    let mut upload = None;
+    while let Some(item) = payload.next().await {
+        let mut field = match item {
+            Ok(field) => field,
+            Err(e) => {
+                eprintln!("Error reading upload: {}", e);
+                return HttpResponse::BadRequest().json(json!({"error": "Invalid upload"}));
+            }
+        };
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let file_name = clean_file_name(
+            field
+                .content_disposition()
+                .and_then(|cd| cd.get_filename())
+                .unwrap_or(""),
+        );
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    eprintln!("Error reading upload: {}", e);
+                    return HttpResponse::BadRequest().json(json!({"error": "Invalid upload"}));
+                }
+            };
+            if data.len() + chunk.len() > MAX_FILE_SIZE {
+                return HttpResponse::PayloadTooLarge().json(json!({
+                    "error": format!("Files can be at most {} MB", MAX_FILE_SIZE / 1024 / 1024)
+                }));
+            }
+            data.extend_from_slice(&chunk);
+        }
+        upload = Some((file_name, data));
+        break;
+    }
+
+    let Some((file_name, data)) = upload else {
+        return HttpResponse::BadRequest().json(json!({"error": "No file uploaded"}));
+    };
+    let Some(content_type) = detect_content_type(&data) else {
+        return HttpResponse::UnsupportedMediaType().json(json!({
+            "error": "Only PNG, JPEG, GIF and WebP images and PDF files can be uploaded"
+        }));
+    };
+
+    // Decoding and scaling images is CPU work, so it runs off the async workers
+    let attachment_id = Uuid::new_v4().to_string();
+    let size = data.len() as i64;
+    let is_image = content_type.starts_with("image/");
+    let stored = {
+        let attachment_id = attachment_id.clone();
+        web::block(move || store(&attachment_id, &data, is_image)).await
+    };
+    let has_thumbnail = match stored {
+        Ok(Ok(has_thumbnail)) => has_thumbnail,
+        Ok(Err(StoreError::UnreadableImage)) => {
+            return HttpResponse::BadRequest()
+                .json(json!({"error": "The image could not be read"}));
+        }
+        Ok(Err(StoreError::Io(e))) => {
+            eprintln!("Error storing upload: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(json!({"error": "Failed to store file"}));
+        }
+        Err(e) => {
+            eprintln!("Error storing upload: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(json!({"error": "Failed to store file"}));
+        }
+    };
+
+    let attachment = Attachment {
+        id: attachment_id,
+        author: author_id,
+        file_name,
+        content_type: content_type.to_string(),
+        size,
+        has_thumbnail,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let conn = pool.get().unwrap();
+    match attachments::insert(&conn, &attachment) {
+        Ok(()) => HttpResponse::Ok().json(attachment.to_json()),
+        Err(e) => {
+            eprintln!("Database error saving attachment: {}", e);
+            let _ = fs::remove_file(file_path(&attachment.id));
+            let _ = fs::remove_file(thumbnail_path(&attachment.id));
+            HttpResponse::InternalServerError().json(json!({"error": "Failed to store file"}))
+        }
+    }
+}
+
+#[get("/list")]
+async fn list(pool: web::Data<Pool<SqliteConnectionManager>>, req: HttpRequest) -> impl Responder {
+    let Some(author_id) = is_authenticated_as_author(&req, &pool) else {
+        return HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}));
+    };
+
+    let conn = pool.get().unwrap();
+    match attachments::list_for_author(&conn, &author_id) {
+        Ok(list) => {
+            HttpResponse::Ok().json(list.iter().map(Attachment::to_json).collect::<Vec<_>>())
+        }
+        Err(e) => {
+            eprintln!("Database error fetching attachments: {}", e);
+            HttpResponse::InternalServerError()
+                .json(json!({"error": "Failed to fetch attachments"}))
+        }
+    }
+}
+
+/// Whether whoever holds `access_id` may see the attachment: the organizer who uploaded it,
+/// or anyone with an invitation to (or the link of a public) party whose blocks show it
+fn may_access(
+    conn: &Connection,
+    pool: &Pool<SqliteConnectionManager>,
+    req: &HttpRequest,
+    access_id: &str,
+    attachment: &Attachment,
+) -> bool {
+    if is_authenticated_as_author(req, pool).as_deref() == Some(attachment.author.as_str()) {
+        return true;
+    }
+
+    let party = match Invitation::load(conn, access_id) {
+        Ok(invitation) => Party::load(conn, &invitation.party_id).ok(),
+        Err(_) => Party::load(conn, access_id)
+            .ok()
+            .filter(|party| party.public),
+    };
+    party.is_some_and(|party| {
+        party
+            .invitation_blocks
+            .iter()
+            .any(|block| block.attachment_id() == Some(attachment.id.as_str()))
+    })
+}
+
+async fn serve(
+    path: web::Path<(String, String)>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+    thumbnail: bool,
+) -> HttpResponse {
+    let (access_id, attachment_id) = path.into_inner();
+    let conn = pool.get().unwrap();
+
+    let attachment = match attachments::load(&conn, &attachment_id) {
+        Ok(Some(attachment)) if may_access(&conn, &pool, &req, &access_id, &attachment) => {
+            attachment
+        }
+        Ok(_) => return HttpResponse::NotFound().json(json!({"error": "Attachment not found"})),
+        Err(e) => {
+            eprintln!("Database error loading attachment: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(json!({"error": "Failed to load attachment"}));
+        }
+    };
+
+    // Images too small for a thumbnail are their own thumbnail
+    let (path, content_type) = if thumbnail && attachment.has_thumbnail {
+        (thumbnail_path(&attachment.id), "image/png")
+    } else {
+        (file_path(&attachment.id), attachment.content_type.as_str())
+    };
+    let file = match NamedFile::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Error opening attachment {}: {}", path, e);
+            return HttpResponse::NotFound().json(json!({"error": "Attachment not found"}));
+        }
+    };
+
+    let mut response = file
+        .set_content_type(
+            content_type
+                .parse()
+                .unwrap_or(actix_web::mime::APPLICATION_OCTET_STREAM),
+        )
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Inline,
+            parameters: vec![DispositionParam::Filename(attachment.file_name.clone())],
+        })
+        .into_response(&req);
+    let headers = response.headers_mut();
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        header::HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static("private, max-age=3600"),
+    );
+    response
+}
+
+#[get("/{access_id}/{attachment_id}")]
+async fn download(
+    path: web::Path<(String, String)>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    serve(path, pool, req, false).await
+}
+
+#[get("/{access_id}/{attachment_id}/thumbnail")]
+async fn download_thumbnail(
+    path: web::Path<(String, String)>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    serve(path, pool, req, true).await
+}
+
+/// Checks that image and file blocks show uploads of `author_id`, and image blocks an image
+pub fn check_blocks(
+    conn: &Connection,
+    author_id: &str,
+    blocks: &[InvitationBlock],
+) -> Result<(), String> {
+    for block in blocks {
+        let Some(attachment_id) = block.attachment_id() else {
+            continue;
+        };
+        let attachment = match attachments::load(conn, attachment_id) {
+            Ok(Some(attachment)) if attachment.author == author_id => attachment,
+            Ok(_) => return Err(format!("Block '{}' shows an unknown upload", block.id)),
+            Err(e) => {
+                eprintln!("Database error loading attachment: {}", e);
+                return Err("Failed to check uploads".to_string());
+            }
+        };
+        if matches!(block.kind, BlockKind::Image { .. }) && !attachment.is_image() {
+            return Err(format!(
+                "Image block '{}' shows '{}', which is not an image",
+                block.id, attachment.file_name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Name, type and size of the uploads the blocks show, keyed by attachment ID
+pub fn for_blocks(conn: &Connection, blocks: &[InvitationBlock]) -> serde_json::Value {
+    let mut found = Map::new();
+    for attachment_id in blocks.iter().filter_map(InvitationBlock::attachment_id) {
+        if let Ok(Some(attachment)) = attachments::load(conn, attachment_id) {
+            found.insert(attachment.id.clone(), attachment.to_json());
+        }
+    }
+    serde_json::Value::Object(found)
+}
+
+pub fn subroutes() -> Scope {
+    web::scope("/attachment")
+        .service(upload)
+        .service(list)
+        .service(download_thumbnail)
+        .service(download)
+}
@@ -1,15 +1,17 @@
 use crate::db::Author;
-use crate::detect_language;
+use crate::i18n::{self, detect_language};
 use actix_web::{
     HttpResponse, Responder, Scope,
     cookie::{Cookie, SameSite},
-    get, post, web,
+    get,
+    http::header,
+    post, web,
 };
+use base64::{Engine, engine::general_purpose::STANDARD};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use serde::Deserialize;
 use std::env;
-use std::fs;
 use time::{Duration, OffsetDateTime};
 
 #[get("")]
@@ -17,16 +19,7 @@ async fn auth(req: actix_web::HttpRequest) -> impl Responder {
     // Detect language from Accept-Language header
     let language = detect_language(&req);
 
-    let filename = match language.as_str() {
-        "de" => "pages/de/auth_de.html",
-        _ => "pages/en/auth_en.html", // Default to English
-    };
-
-    let html_content =
-        fs::read_to_string(filename).unwrap_or_else(|_| "<h1>404: File Not Found</h1>".to_string());
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(html_content)
+    i18n::page_response(&req, HttpResponse::Ok(), "auth", &language)
 }
 
 #[derive(Deserialize)]
@@ -80,28 +73,43 @@ async fn auth_post(
     }
 }
 
+fn author_for_secret(db: &Pool<SqliteConnectionManager>, auth_secret: &str) -> Option<String> {
+    // Validate the token against the database
+    let conn = db.get().ok()?;
+    conn.prepare("SELECT id, name, author_secret FROM authors WHERE author_secret = ?1")
+        .and_then(|mut stmt| stmt.query_row([auth_secret], Author::from_row))
+        .map(|author| author.id)
+        .ok()
+}
+
 pub fn is_authenticated_as_author(
     req: &actix_web::HttpRequest,
     db: &Pool<SqliteConnectionManager>,
 ) -> Option<String> {
-    if let Some(cookie) = req.cookie("auth_token") {
-        let auth_secret = cookie.value();
-
-        // Validate the token against the database
-        if let Ok(conn) = db.get() {
-            let author_result = conn
-                .prepare("SELECT id, name, author_secret FROM authors WHERE author_secret = ?1")
-                .and_then(|mut stmt| stmt.query_row([auth_secret], Author::from_row));
+    let cookie = req.cookie("auth_token")?;
+    author_for_secret(db, cookie.value())
+}
 
-            match author_result {
-                Ok(author) => Some(author.id),
-                Err(_) => None,
-            }
-        } else {
-            None
+/// Authenticates clients that can't log in on the auth page, such as calendar apps.
+/// They send HTTP Basic credentials with the author secret as the password (the user name is
+/// ignored). The auth cookie works as well.
+pub fn is_authenticated_as_author_basic(
+    req: &actix_web::HttpRequest,
+    db: &Pool<SqliteConnectionManager>,
+) -> Option<String> {
+    let credentials = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok());
+    match credentials {
+        Some(credentials) => {
+            let (_user, password) = credentials.split_once(':')?;
+            author_for_secret(db, password)
         }
-    } else {
-        None
+        None => is_authenticated_as_author(req, db),
     }
 }
 
@@ -0,0 +1,511 @@
+use super::{BlockKind, InvitationBlock, condition, poll};
+use crate::i18n;
+use crate::templating::Variables;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Upper bound for text answers, also used when a text block sets no `max_length`
+pub const MAX_TEXT_LENGTH: usize = 2000;
+
+/// Upper bound for the name of a companion (plus-one)
+pub const MAX_COMPANION_NAME_LENGTH: usize = 100;
+
+/// Why a single answer was rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnswerError {
+    Required,
+    ExpectedText,
+    ExpectedNumber,
+    ExpectedOption,
+    ExpectedSelection,
+    OptionOutOfRange,
+    TooLong { max: usize },
+    TooSmall { min: f64 },
+    TooLarge { max: f64 },
+    TooFewSelections { min: usize },
+    TooManySelections { max: usize },
+    TooManyCompanions { max: i64 },
+    IncompletePoll,
+    ItemUnavailable { item: String, left: i64 },
+}
+
+impl AnswerError {
+    /// Message shown to the guest next to the block
+    pub fn message(&self, language: &str) -> String {
+        let mut variables = Variables::default();
+        let key = match self {
+            AnswerError::Required => "answer-required",
+            AnswerError::ExpectedText => "answer-expected-text",
+            AnswerError::ExpectedNumber => "answer-expected-number",
+            AnswerError::ExpectedOption | AnswerError::OptionOutOfRange => "answer-expected-option",
+            AnswerError::ExpectedSelection => "answer-invalid-selection",
+            AnswerError::TooLong { max } => {
+                variables.set("max", max.to_string());
+                "answer-too-long"
+            }
+            AnswerError::TooSmall { min } => {
+                variables.set("min", min.to_string());
+                "answer-too-small"
+            }
+            AnswerError::TooLarge { max } => {
+                variables.set("max", max.to_string());
+                "answer-too-large"
+            }
+            AnswerError::TooFewSelections { min } => {
+                variables.set("min", min.to_string());
+                "answer-too-few-selections"
+            }
+            AnswerError::TooManySelections { max } => {
+                variables.set("max", max.to_string());
+                "answer-too-many-selections"
+            }
+            AnswerError::TooManyCompanions { max } => {
+                variables.set("max", max.to_string());
+                "answer-too-many-companions"
+            }
+            AnswerError::IncompletePoll => "answer-incomplete-poll",
+            AnswerError::ItemUnavailable { item, left: 0 } => {
+                variables.set("item", item.as_str());
+                "answer-item-taken"
+            }
+            AnswerError::ItemUnavailable { item, left } => {
+                variables.set("item", item.as_str()).set("left", left.to_string());
+                "answer-item-few-left"
+            }
+        };
+        i18n::text_with(language, key, &variables)
+    }
+}
+
+/// Checks one answer against its block and returns it in normalized form.
+/// `Value::Null` means the block is unanswered (an empty text, no selected option, ...).
+pub fn validate_answer(block: &InvitationBlock, value: &Value) -> Result<Value, AnswerError> {
+    let normalized = match &block.kind {
+        BlockKind::TextInput(q) => match value {
+            Value::Null => Value::Null,
+            Value::String(text) if text.trim().is_empty() => Value::Null,
+            Value::String(text) => {
+                let max = q.max_length.unwrap_or(MAX_TEXT_LENGTH).min(MAX_TEXT_LENGTH);
+                if text.chars().count() > max {
+                    return Err(AnswerError::TooLong { max });
+                }
+                Value::String(text.clone())
+            }
+            _ => return Err(AnswerError::ExpectedText),
+        },
+        BlockKind::NumberInput(q) => {
+            // Older clients send the raw input value as a string
+            let number = match value {
+                Value::Null => None,
+                Value::String(text) if text.trim().is_empty() => None,
+                Value::String(text) => Some(
+                    text.trim()
+                        .parse::<f64>()
+                        .map_err(|_| AnswerError::ExpectedNumber)?,
+                ),
+                Value::Number(n) => Some(n.as_f64().ok_or(AnswerError::ExpectedNumber)?),
+                _ => return Err(AnswerError::ExpectedNumber),
+            };
+
+            match number {
+                None => Value::Null,
+                Some(n) if !n.is_finite() => return Err(AnswerError::ExpectedNumber),
+                Some(n) => {
+                    if let Some(min) = q.min
+                        && n < min
+                    {
+                        return Err(AnswerError::TooSmall { min });
+                    }
+                    if let Some(max) = q.max
+                        && n > max
+                    {
+                        return Err(AnswerError::TooLarge { max });
+                    }
+                    number_value(n)
+                }
+            }
+        }
+        BlockKind::SingleChoice(q) | BlockKind::Attendance(q) => match value {
+            Value::Null => Value::Null,
+            Value::Number(n) => match n.as_i64() {
+                // The invitation page uses -1 for "nothing selected"
+                Some(-1) => Value::Null,
+                Some(index) if index >= 0 && (index as usize) < q.options.len() => {
+                    Value::from(index)
+                }
+                Some(_) => return Err(AnswerError::OptionOutOfRange),
+                None => return Err(AnswerError::ExpectedOption),
+            },
+            _ => return Err(AnswerError::ExpectedOption),
+        },
+        BlockKind::MultipleChoice(q) => match value {
+            Value::Null => Value::Null,
+            Value::Array(selection) => {
+                if selection.len() > q.options.len() {
+                    return Err(AnswerError::OptionOutOfRange);
+                }
+                if selection.iter().any(|selected| !selected.is_boolean()) {
+                    return Err(AnswerError::ExpectedSelection);
+                }
+                let selected = selection
+                    .iter()
+                    .filter(|selected| selected.as_bool() == Some(true))
+                    .count();
+                if selected == 0 {
+                    Value::Null
+                } else {
+                    if let Some(min) = q.min_selections
+                        && selected < min
+                    {
+                        return Err(AnswerError::TooFewSelections { min });
+                    }
+                    if let Some(max) = q.max_selections
+                        && selected > max
+                    {
+                        return Err(AnswerError::TooManySelections { max });
+                    }
+                    Value::Array(selection.clone())
+                }
+            }
+            _ => return Err(AnswerError::ExpectedSelection),
+        },
+        BlockKind::DatePoll(date_poll) => match value {
+            Value::Null => Value::Null,
+            Value::Array(choices) => {
+                if choices.len() > date_poll.slots.len() {
+                    return Err(AnswerError::OptionOutOfRange);
+                }
+                let valid_choice = |choice: &Value| {
+                    choice.is_null()
+                        || choice
+                            .as_i64()
+                            .is_some_and(|c| (poll::POLL_YES..=poll::POLL_NO).contains(&c))
+                };
+                if !choices.iter().all(valid_choice) {
+                    return Err(AnswerError::ExpectedOption);
+                }
+                let answered = choices.iter().filter(|choice| !choice.is_null()).count();
+                if answered == 0 {
+                    Value::Null
+                } else if date_poll.required && answered < date_poll.slots.len() {
+                    return Err(AnswerError::IncompletePoll);
+                } else {
+                    Value::Array(choices.clone())
+                }
+            }
+            _ => return Err(AnswerError::ExpectedSelection),
+        },
+        BlockKind::ItemSignup(signup) => match value {
+            Value::Null => Value::Null,
+            Value::Array(quantities) => {
+                if quantities.len() > signup.items.len() {
+                    return Err(AnswerError::OptionOutOfRange);
+                }
+                let mut claimed = 0;
+                for (item, quantity) in signup.items.iter().zip(quantities) {
+                    let quantity = quantity.as_i64().ok_or(AnswerError::ExpectedNumber)?;
+                    if quantity < 0 {
+                        return Err(AnswerError::ExpectedNumber);
+                    }
+                    if quantity > item.capacity {
+                        return Err(AnswerError::ItemUnavailable {
+                            item: item.name.clone(),
+                            left: item.capacity,
+                        });
+                    }
+                    claimed += quantity;
+                }
+                if claimed == 0 {
+                    Value::Null
+                } else {
+                    Value::Array(quantities.clone())
+                }
+            }
+            _ => return Err(AnswerError::ExpectedSelection),
+        },
+        // Content blocks cannot be answered
+        _ => Value::Null,
+    };
+
+    if normalized.is_null() && block.is_required() {
+        return Err(AnswerError::Required);
+    }
+
+    Ok(normalized)
+}
+
+/// Validates a guest's submitted answers against the party's blocks.
+/// Answers to unknown or non-question blocks are dropped, unanswered blocks are left out.
+/// Blocks hidden by an earlier answer are skipped, so their answers are discarded too.
+/// On failure, returns the error for every offending block, keyed by block ID.
+pub fn validate_answers(
+    blocks: &[InvitationBlock],
+    answers: &Value,
+) -> Result<Map<String, Value>, HashMap<String, AnswerError>> {
+    let mut valid = Map::new();
+    let mut errors = HashMap::new();
+
+    for block in blocks.iter().filter(|block| block.is_question()) {
+        if !condition::is_shown(block, &valid) {
+            continue;
+        }
+        let value = answers.get(&block.id).unwrap_or(&Value::Null);
+        match validate_answer(block, value) {
+            Ok(Value::Null) => {}
+            Ok(normalized) => {
+                valid.insert(block.id.clone(), normalized);
+            }
+            Err(e) => {
+                errors.insert(block.id.clone(), e);
+            }
+        }
+    }
+
+    if errors.is_empty() { Ok(valid) } else { Err(errors) }
+}
+
+/// Checks the number of companions a guest brings and cleans up their names.
+/// Names are optional; blank names are kept as empty strings so they stay in line with the count.
+pub fn validate_companions(
+    count: i64,
+    names: &[String],
+    max_companions: i64,
+) -> Result<(i64, Vec<String>), AnswerError> {
+    if count < 0 {
+        return Err(AnswerError::ExpectedNumber);
+    }
+    if count > max_companions {
+        return Err(AnswerError::TooManyCompanions { max: max_companions.max(0) });
+    }
+
+    let mut cleaned = Vec::new();
+    for name in names.iter().take(count as usize) {
+        let name = name.trim();
+        if name.chars().count() > MAX_COMPANION_NAME_LENGTH {
+            return Err(AnswerError::TooLong { max: MAX_COMPANION_NAME_LENGTH });
+        }
+        cleaned.push(name.to_string());
+    }
+    Ok((count, cleaned))
+}
+
+/// Drops stored answers that no longer match their block (saved before validation existed
+/// or invalidated by an edit of the block) and normalizes the rest for display.
+/// Answers to blocks that are hidden by now are dropped as well.
+pub fn sanitize_stored_answers(blocks: &[InvitationBlock], answers: &Value) -> Value {
+    let mut sanitized = Map::new();
+    for block in blocks.iter().filter(|block| block.is_question()) {
+        if condition::is_shown(block, &sanitized)
+            && let Some(value) = answers.get(&block.id)
+            && let Ok(normalized) = validate_answer(block, value)
+            && !normalized.is_null()
+        {
+            sanitized.insert(block.id.clone(), normalized);
+        }
+    }
+    Value::Object(sanitized)
+}
+
+/// IDs of required blocks that have no valid answer yet, leaving out blocks the guest doesn't see
+pub fn missing_required(blocks: &[InvitationBlock], answers: &Value) -> Vec<String> {
+    let Value::Object(answered) = sanitize_stored_answers(blocks, answers) else {
+        return Vec::new();
+    };
+    blocks
+        .iter()
+        .filter(|block| block.is_required())
+        .filter(|block| condition::is_shown(block, &answered) && !answered.contains_key(&block.id))
+        .map(|block| block.id.clone())
+        .collect()
+}
+
+/// Whole numbers are stored as integers so `4` doesn't turn into `4.0`
+fn number_value(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::parse_blocks;
+    use serde_json::json;
+
+    /// One block of each question kind, none of them required
+    fn blocks() -> Vec<InvitationBlock> {
+        parse_blocks(
+            r#"[
+                {"id":"intro","type":"paragraph","text":"Hi"},
+                {"id":"rsvp","type":"attendance","label":"Coming?","options":["Yes","Maybe","No"]},
+                {"id":"note","type":"text_input","label":"Note","max_length":10},
+                {"id":"story","type":"text_input","label":"Story"},
+                {"id":"cake","type":"number_input","label":"Slices","min":0,"max":3},
+                {"id":"main","type":"single_choice","label":"Main","options":["Pasta","Curry"]},
+                {"id":"drinks","type":"multiple_choice","label":"Drinks","options":["Beer","Wine","Juice"],"min_selections":1,"max_selections":2},
+                {"id":"date","type":"date_poll","label":"When?","slots":["2026-07-01T19:00","2026-07-02T19:00"]},
+                {"id":"bring","type":"item_signup","label":"Bring","items":[{"name":"Salad","capacity":2}]}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn block(id: &str) -> InvitationBlock {
+        blocks().into_iter().find(|block| block.id == id).unwrap()
+    }
+
+    #[test]
+    fn checks_answer_types() {
+        assert_eq!(validate_answer(&block("note"), &json!(3)), Err(AnswerError::ExpectedText));
+        assert_eq!(validate_answer(&block("cake"), &json!("lots")), Err(AnswerError::ExpectedNumber));
+        assert_eq!(validate_answer(&block("cake"), &json!([1])), Err(AnswerError::ExpectedNumber));
+        assert_eq!(validate_answer(&block("main"), &json!("Pasta")), Err(AnswerError::ExpectedOption));
+        assert_eq!(validate_answer(&block("main"), &json!(0.5)), Err(AnswerError::ExpectedOption));
+        assert_eq!(validate_answer(&block("drinks"), &json!(1)), Err(AnswerError::ExpectedSelection));
+        assert_eq!(
+            validate_answer(&block("drinks"), &json!([true, "yes"])),
+            Err(AnswerError::ExpectedSelection)
+        );
+        assert_eq!(validate_answer(&block("date"), &json!([0, 7])), Err(AnswerError::ExpectedOption));
+        assert_eq!(validate_answer(&block("bring"), &json!([-1])), Err(AnswerError::ExpectedNumber));
+    }
+
+    #[test]
+    fn checks_option_indices() {
+        assert_eq!(validate_answer(&block("main"), &json!(1)), Ok(json!(1)));
+        assert_eq!(validate_answer(&block("main"), &json!(2)), Err(AnswerError::OptionOutOfRange));
+        assert_eq!(validate_answer(&block("rsvp"), &json!(-2)), Err(AnswerError::OptionOutOfRange));
+        // -1 is the invitation page's "nothing selected"
+        assert_eq!(validate_answer(&block("rsvp"), &json!(-1)), Ok(Value::Null));
+        assert_eq!(
+            validate_answer(&block("drinks"), &json!([true, false, false, true])),
+            Err(AnswerError::OptionOutOfRange)
+        );
+        assert_eq!(
+            validate_answer(&block("date"), &json!([0, 1, 2])),
+            Err(AnswerError::OptionOutOfRange)
+        );
+        assert_eq!(
+            validate_answer(&block("bring"), &json!([3])),
+            Err(AnswerError::ItemUnavailable { item: "Salad".to_string(), left: 2 })
+        );
+    }
+
+    #[test]
+    fn checks_ranges_and_lengths() {
+        assert_eq!(validate_answer(&block("note"), &json!("0123456789")), Ok(json!("0123456789")));
+        assert_eq!(
+            validate_answer(&block("note"), &json!("01234567890")),
+            Err(AnswerError::TooLong { max: 10 })
+        );
+        // Characters are counted, not bytes
+        assert!(validate_answer(&block("note"), &json!("ääääääääää")).is_ok());
+        // Without a limit of its own, a text block takes the overall one
+        let long = "x".repeat(MAX_TEXT_LENGTH + 1);
+        assert_eq!(
+            validate_answer(&block("story"), &json!(long)),
+            Err(AnswerError::TooLong { max: MAX_TEXT_LENGTH })
+        );
+
+        assert_eq!(validate_answer(&block("cake"), &json!(-1)), Err(AnswerError::TooSmall { min: 0.0 }));
+        assert_eq!(validate_answer(&block("cake"), &json!(3.5)), Err(AnswerError::TooLarge { max: 3.0 }));
+        // Older clients send numbers as text; whole numbers are stored as integers
+        assert_eq!(validate_answer(&block("cake"), &json!(" 2 ")), Ok(json!(2)));
+        assert_eq!(validate_answer(&block("cake"), &json!(2.5)), Ok(json!(2.5)));
+
+        assert_eq!(
+            validate_answer(&block("drinks"), &json!([true, true, true])),
+            Err(AnswerError::TooManySelections { max: 2 })
+        );
+        assert_eq!(validate_answer(&block("drinks"), &json!([false, true])), Ok(json!([false, true])));
+    }
+
+    #[test]
+    fn treats_empty_answers_as_unanswered() {
+        for (id, empty) in [
+            ("note", json!("  ")),
+            ("cake", json!("")),
+            ("drinks", json!([false, false])),
+            ("date", json!([null, null])),
+            ("bring", json!([0])),
+            ("intro", json!("content blocks can't be answered")),
+        ] {
+            assert_eq!(validate_answer(&block(id), &empty), Ok(Value::Null), "{}", id);
+        }
+    }
+
+    #[test]
+    fn validates_all_answers() {
+        let answers = json!({
+            "rsvp": 0,
+            "note": "",
+            "cake": "2",
+            "intro": "dropped",
+            "unknown": "dropped as well"
+        });
+        let valid = validate_answers(&blocks(), &answers).unwrap();
+        assert_eq!(Value::Object(valid), json!({ "rsvp": 0, "cake": 2 }));
+
+        let answers = json!({ "rsvp": 5, "note": "far too long here", "cake": 1 });
+        let errors = validate_answers(&blocks(), &answers).unwrap_err();
+        assert_eq!(
+            errors,
+            HashMap::from([
+                ("rsvp".to_string(), AnswerError::OptionOutOfRange),
+                ("note".to_string(), AnswerError::TooLong { max: 10 }),
+            ])
+        );
+    }
+
+    /// An attendance question and a required text and choice question, the choice only for
+    /// guests who attend
+    fn required_blocks() -> Vec<InvitationBlock> {
+        parse_blocks(
+            r#"[
+                {"id":"rsvp","type":"attendance","label":"Coming?","options":["Yes","Maybe","No"],"required":true},
+                {"id":"name_tag","type":"text_input","label":"Name tag","required":true},
+                {"id":"note","type":"text_input","label":"Note"},
+                {"id":"main","type":"single_choice","label":"Main","options":["Pasta","Curry"],"required":true,
+                 "condition":{"block_id":"rsvp","options":[0]}}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn lists_missing_required_answers() {
+        let blocks = required_blocks();
+        assert_eq!(missing_required(&blocks, &json!({})), ["rsvp", "name_tag"]);
+        assert_eq!(missing_required(&blocks, &json!({ "rsvp": 0, "name_tag": "Bo" })), ["main"]);
+        assert!(missing_required(&blocks, &json!({ "rsvp": 0, "name_tag": "Bo", "main": 1 })).is_empty());
+        // Optional questions are never missing
+        assert!(!missing_required(&blocks, &json!({})).contains(&"note".to_string()));
+    }
+
+    #[test]
+    fn invalid_or_empty_answers_are_missing() {
+        let blocks = required_blocks();
+        let answers = json!({ "rsvp": 7, "name_tag": "   ", "main": 0 });
+        // The choice stays hidden while attendance has no valid answer
+        assert_eq!(missing_required(&blocks, &answers), ["rsvp", "name_tag"]);
+        assert_eq!(missing_required(&blocks, &Value::Null), ["rsvp", "name_tag"]);
+    }
+
+    #[test]
+    fn rejects_unanswered_required_questions() {
+        let blocks = required_blocks();
+        let errors = validate_answers(&blocks, &json!({ "rsvp": -1, "name_tag": "" })).unwrap_err();
+        assert_eq!(
+            errors,
+            HashMap::from([
+                ("rsvp".to_string(), AnswerError::Required),
+                ("name_tag".to_string(), AnswerError::Required),
+            ])
+        );
+        let errors = validate_answers(&blocks, &json!({ "rsvp": 0, "name_tag": "Bo" })).unwrap_err();
+        assert_eq!(errors, HashMap::from([("main".to_string(), AnswerError::Required)]));
+        assert!(validate_answers(&blocks, &json!({ "rsvp": 2, "name_tag": "Bo" })).is_ok());
+    }
+}
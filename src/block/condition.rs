@@ -0,0 +1,211 @@
+use super::{BlockError, BlockKind, InvitationBlock};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Shows a block only if an earlier choice question was answered with one of `options`,
+/// e.g. dietary needs only for guests who attend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    /// ID of the single choice, multiple choice or attendance block the condition depends on
+    pub block_id: String,
+    /// Indices of the options that reveal the block
+    pub options: Vec<usize>,
+}
+
+impl Condition {
+    /// Whether the normalized answer to the referenced block reveals the block.
+    /// Multiple choice answers (one flag per option) reveal it if any ticked option does.
+    pub fn is_met_by(&self, answer: Option<&Value>) -> bool {
+        match answer {
+            Some(Value::Array(selection)) => self
+                .options
+                .iter()
+                .any(|&index| selection.get(index).and_then(|v| v.as_bool()) == Some(true)),
+            Some(value) => value
+                .as_u64()
+                .is_some_and(|index| self.options.contains(&(index as usize))),
+            None => false,
+        }
+    }
+}
+
+/// Whether `block` is shown to a guest with the given answers.
+/// `answers` must only hold answers to shown blocks, so a block depending on a hidden block
+/// stays hidden as well; building the map in block order takes care of that.
+pub fn is_shown(block: &InvitationBlock, answers: &Map<String, Value>) -> bool {
+    block
+        .condition
+        .as_ref()
+        .is_none_or(|condition| condition.is_met_by(answers.get(&condition.block_id)))
+}
+
+/// Checks that a block's condition refers to a choice question above it and names
+/// at least one of its options. `earlier` holds the blocks before it, keyed by ID.
+pub fn validate(
+    block: &InvitationBlock,
+    earlier: &HashMap<&str, &InvitationBlock>,
+) -> Result<(), BlockError> {
+    let Some(condition) = &block.condition else {
+        return Ok(());
+    };
+
+    let options = match earlier
+        .get(condition.block_id.as_str())
+        .map(|target| &target.kind)
+    {
+        Some(
+            BlockKind::SingleChoice(q) | BlockKind::MultipleChoice(q) | BlockKind::Attendance(q),
+        ) => &q.options,
+        _ => {
+            return Err(BlockError::InvalidConditionTarget {
+                id: block.id.clone(),
+                target: condition.block_id.clone(),
+            });
+        }
+    };
+
+    if condition.options.is_empty()
+        || condition
+            .options
+            .iter()
+            .any(|&index| index >= options.len())
+    {
+        return Err(BlockError::InvalidConditionOptions {
+            id: block.id.clone(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::answer::{missing_required, sanitize_stored_answers, validate_answers};
+    use crate::block::{parse_blocks, validate_blocks};
+    use serde_json::json;
+
+    /// Dietary needs only for guests who attend, and the dish only for vegetarians among them
+    fn blocks() -> Vec<InvitationBlock> {
+        parse_blocks(
+            r#"[
+                {"id":"rsvp","type":"attendance","label":"Coming?","options":["Yes","Maybe","No"]},
+                {"id":"diet","type":"multiple_choice","label":"Diet","options":["Vegetarian","Vegan","None"],"required":true,
+                 "condition":{"block_id":"rsvp","options":[0,1]}},
+                {"id":"dish","type":"text_input","label":"Favourite dish","required":true,
+                 "condition":{"block_id":"diet","options":[0,1]}}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn condition(options: &[usize]) -> Condition {
+        Condition {
+            block_id: "rsvp".to_string(),
+            options: options.to_vec(),
+        }
+    }
+
+    #[test]
+    fn conditions_match_answers() {
+        assert!(condition(&[0, 1]).is_met_by(Some(&json!(1))));
+        assert!(!condition(&[0, 1]).is_met_by(Some(&json!(2))));
+        assert!(!condition(&[0]).is_met_by(None));
+        // Any ticked option of a multiple choice counts
+        assert!(condition(&[1, 2]).is_met_by(Some(&json!([true, false, true]))));
+        assert!(!condition(&[1]).is_met_by(Some(&json!([true, false]))));
+        assert!(!condition(&[0]).is_met_by(Some(&json!("0"))));
+    }
+
+    #[test]
+    fn blocks_behind_hidden_blocks_stay_hidden() {
+        let blocks = blocks();
+        let answers = Map::from_iter([("rsvp".to_string(), json!(0))]);
+        assert!(is_shown(&blocks[1], &answers));
+        assert!(!is_shown(&blocks[2], &answers));
+        assert!(is_shown(&blocks[0], &Map::new()));
+    }
+
+    #[test]
+    fn hidden_questions_are_not_required() {
+        let blocks = blocks();
+        // Declining hides both follow-up questions, required or not
+        assert_eq!(
+            Value::Object(validate_answers(&blocks, &json!({ "rsvp": 2 })).unwrap()),
+            json!({ "rsvp": 2 })
+        );
+        assert!(missing_required(&blocks, &json!({ "rsvp": 2 })).is_empty());
+        assert_eq!(missing_required(&blocks, &json!({ "rsvp": 0 })), ["diet"]);
+        assert_eq!(
+            missing_required(&blocks, &json!({ "rsvp": 0, "diet": [true, false, false] })),
+            ["dish"]
+        );
+        assert!(
+            missing_required(&blocks, &json!({ "rsvp": 0, "diet": [false, false, true] }))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn hidden_answers_are_not_stored() {
+        let blocks = blocks();
+        let answers = json!({ "rsvp": 2, "diet": [true, false, false], "dish": "Curry" });
+        assert_eq!(
+            Value::Object(validate_answers(&blocks, &answers).unwrap()),
+            json!({ "rsvp": 2 })
+        );
+        // Answers stored before the guest declined are left out as well
+        assert_eq!(
+            sanitize_stored_answers(&blocks, &answers),
+            json!({ "rsvp": 2 })
+        );
+        let answers = json!({ "rsvp": 0, "diet": [false, false, true], "dish": "Curry" });
+        assert_eq!(
+            sanitize_stored_answers(&blocks, &answers),
+            json!({ "rsvp": 0, "diet": [false, false, true] })
+        );
+        // Invalid answers to shown questions still fail, hidden ones aren't looked at
+        let errors = validate_answers(&blocks, &json!({ "rsvp": 1, "diet": "Vegan" })).unwrap_err();
+        assert!(errors.contains_key("diet"));
+        assert!(validate_answers(&blocks, &json!({ "rsvp": 2, "diet": "Vegan" })).is_ok());
+    }
+
+    #[test]
+    fn conditions_point_to_earlier_choices() {
+        validate_blocks(&blocks()).unwrap();
+
+        let mut blocks = self::blocks();
+        blocks.swap(0, 1);
+        assert!(matches!(
+            validate_blocks(&blocks),
+            Err(BlockError::InvalidConditionTarget { .. })
+        ));
+
+        let mut blocks = self::blocks();
+        blocks[1].condition = Some(condition(&[3]));
+        assert!(matches!(
+            validate_blocks(&blocks),
+            Err(BlockError::InvalidConditionOptions { .. })
+        ));
+        blocks[1].condition = Some(condition(&[]));
+        assert!(matches!(
+            validate_blocks(&blocks),
+            Err(BlockError::InvalidConditionOptions { .. })
+        ));
+
+        // Only choice questions can reveal other blocks
+        let mut blocks = self::blocks();
+        blocks.push(InvitationBlock {
+            id: "dessert".to_string(),
+            kind: blocks[2].kind.clone(),
+            condition: Some(Condition {
+                block_id: "dish".to_string(),
+                options: vec![0],
+            }),
+        });
+        assert!(matches!(
+            validate_blocks(&blocks),
+            Err(BlockError::InvalidConditionTarget { .. })
+        ));
+    }
+}
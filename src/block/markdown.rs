@@ -0,0 +1,162 @@
+use super::{BlockKind, InvitationBlock};
+use crate::templating::{self, Variables};
+use ammonia::Builder;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd, html};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+// Paragraphs and headings are written in Markdown and rendered here, so guests' browsers
+// only ever receive sanitized HTML. Template variables are filled in before rendering.
+
+/// Strips everything but formatting, and only lets links point to web, mail and phone addresses
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::default();
+    builder
+        .url_schemes(HashSet::from(["http", "https", "mailto", "tel"]))
+        .link_rel(Some("noopener noreferrer nofollow"));
+    builder
+});
+
+/// Backslash-escapes Markdown syntax, so a guest named `*Bo*` isn't shown in italics
+fn escape_markdown(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_ascii_punctuation() {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Fills in the template variables, escaping their values so they show up as typed
+pub fn personalize(text: &str, variables: &Variables) -> String {
+    templating::render_with(text, variables, &escape_markdown)
+}
+
+fn options() -> Options {
+    Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES
+}
+
+/// Renders Markdown into sanitized HTML
+pub fn render(text: &str) -> String {
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(text, options()));
+    SANITIZER.clean(&unsafe_html).to_string()
+}
+
+/// Renders Markdown for a heading: only inline formatting, without the surrounding paragraph
+pub fn render_inline(text: &str) -> String {
+    let events = Parser::new_ext(text, options()).filter(|event| {
+        !matches!(
+            event,
+            Event::Start(Tag::Paragraph) | Event::End(TagEnd::Paragraph)
+        )
+    });
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events);
+    SANITIZER.clean(unsafe_html.trim_end()).to_string()
+}
+
+/// The blocks as sent to guests: paragraphs and headings get an `html` field with their
+/// personalized, rendered text, and question labels are personalized as plain text.
+/// Stored blocks keep the Markdown source.
+pub fn render_blocks(blocks: &[InvitationBlock], variables: &Variables) -> Value {
+    Value::Array(
+        blocks
+            .iter()
+            .map(|block| {
+                let html = match &block.kind {
+                    BlockKind::Paragraph { text } => Some(render(&personalize(text, variables))),
+                    BlockKind::Heading { text, .. } => {
+                        Some(render_inline(&personalize(text, variables)))
+                    }
+                    _ => None,
+                };
+                let mut value = serde_json::to_value(block).unwrap_or(Value::Null);
+                if let Some(fields) = value.as_object_mut() {
+                    if let Some(html) = html {
+                        fields.insert("html".to_string(), Value::String(html));
+                    }
+                    if let Some(Value::String(label)) = fields.get_mut("label") {
+                        *label = templating::render(label, variables);
+                    }
+                }
+                value
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guest(first: &str) -> Variables {
+        let mut variables = Variables::default();
+        variables.set("first", first);
+        variables
+    }
+
+    #[test]
+    fn strips_scripts_and_unsafe_links() {
+        let html = render("Hi<script>alert(1)</script> there");
+        assert!(!html.contains("script"), "{}", html);
+        assert!(!html.contains("alert"), "{}", html);
+
+        let html = render("[x](javascript:alert(1)) and [site](https://example.com)");
+        assert!(!html.contains("javascript"), "{}", html);
+        assert!(
+            html.contains(
+                r#"<a href="https://example.com" rel="noopener noreferrer nofollow">site</a>"#
+            ),
+            "{}",
+            html
+        );
+
+        let html = render(r#"<img src=x onerror="alert(1)">"#);
+        assert!(!html.contains("onerror"), "{}", html);
+        assert!(!html.contains("alert"), "{}", html);
+
+        let html =
+            render_inline("Title <script>alert(1)</script><a href=\"javascript:alert(1)\">x</a>");
+        assert!(
+            !html.contains("script") && !html.contains("alert"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn keeps_formatting() {
+        assert_eq!(
+            render("Some **bold** and _italic_ text\n\n- one\n- two"),
+            "<p>Some <strong>bold</strong> and <em>italic</em> text</p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn shows_guest_names_as_typed() {
+        let html = render(&personalize("Hello {{first}}!", &guest("*Bo*")));
+        assert_eq!(html, "<p>Hello *Bo*!</p>\n");
+
+        let html = render(&personalize("Hello {{first}}!", &guest("<b>x</b>")));
+        assert_eq!(html, "<p>Hello &lt;b&gt;x&lt;/b&gt;!</p>\n");
+
+        let html = render_inline(&personalize(
+            "Welcome {{first}}",
+            &guest("[a](https://evil.example)"),
+        ));
+        assert!(!html.contains("<a"), "{}", html);
+    }
+
+    #[test]
+    fn headings_have_no_paragraph() {
+        assert_eq!(
+            render_inline("Welcome, **friends**"),
+            "Welcome, <strong>friends</strong>"
+        );
+        assert_eq!(render_inline("Line one\nline two"), "Line one\nline two");
+    }
+}
@@ -0,0 +1,664 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+pub mod answer;
+pub mod condition;
+pub mod markdown;
+pub mod poll;
+
+/// Attendance answers are stored as the index of the chosen option: "yes", "maybe" and "no"
+pub const ATTENDANCE_YES: i64 = 0;
+pub const ATTENDANCE_MAYBE: i64 = 1;
+pub const ATTENDANCE_NO: i64 = 2;
+
+/// Whether other guests may see the answers to a question
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    #[default]
+    Private,
+}
+
+/// A free-form question (text or number)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Question {
+    pub label: String,
+    #[serde(default)]
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub required: bool,
+    /// Smallest accepted value (number input only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Largest accepted value (number input only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Maximum number of characters (text input only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+}
+
+/// A question with a fixed list of options (single choice, multiple choice, attendance)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChoiceQuestion {
+    pub label: String,
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub required: bool,
+    /// Fewest options a guest has to tick (multiple choice only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_selections: Option<usize>,
+    /// Most options a guest may tick (multiple choice only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_selections: Option<usize>,
+}
+
+/// Proposed dates guests answer with yes, if need be or no, before the party date is fixed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatePoll {
+    pub label: String,
+    /// Proposed start times, in the same format as the party date
+    pub slots: Vec<String>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Something guests can sign up to bring, needed `capacity` times
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignupItem {
+    pub name: String,
+    pub capacity: i64,
+}
+
+/// A list of items guests claim, e.g. who brings what to a potluck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemSignup {
+    pub label: String,
+    pub items: Vec<SignupItem>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockKind {
+    Heading { level: u8, text: String },
+    Paragraph { text: String },
+    Code { text: String },
+    Calendar,
+    /// An uploaded image, e.g. a header banner or a map screenshot
+    Image {
+        attachment_id: String,
+        #[serde(default)]
+        alt: String,
+    },
+    /// An uploaded file guests can download, e.g. directions as PDF
+    File {
+        attachment_id: String,
+        #[serde(default)]
+        label: String,
+    },
+    TextInput(Question),
+    NumberInput(Question),
+    SingleChoice(ChoiceQuestion),
+    MultipleChoice(ChoiceQuestion),
+    Attendance(ChoiceQuestion),
+    DatePoll(DatePoll),
+    ItemSignup(ItemSignup),
+}
+
+/// One block of a party's invitation, as stored in `parties.invitation_blocks`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvitationBlock {
+    pub id: String,
+    #[serde(flatten)]
+    pub kind: BlockKind,
+    /// Hides the block unless an earlier question was answered a certain way
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<condition::Condition>,
+}
+
+impl InvitationBlock {
+    /// Whether guests can answer this block
+    pub fn is_question(&self) -> bool {
+        self.visibility().is_some()
+    }
+
+    /// Visibility of a question block, `None` for content blocks
+    pub fn visibility(&self) -> Option<Visibility> {
+        match &self.kind {
+            BlockKind::TextInput(q) | BlockKind::NumberInput(q) => Some(q.visibility),
+            BlockKind::SingleChoice(q) | BlockKind::MultipleChoice(q) | BlockKind::Attendance(q) => {
+                Some(q.visibility)
+            }
+            BlockKind::DatePoll(poll) => Some(poll.visibility),
+            BlockKind::ItemSignup(signup) => Some(signup.visibility),
+            _ => None,
+        }
+    }
+
+    /// Whether guests must answer this block before saving
+    pub fn is_required(&self) -> bool {
+        match &self.kind {
+            BlockKind::TextInput(q) | BlockKind::NumberInput(q) => q.required,
+            BlockKind::SingleChoice(q) | BlockKind::MultipleChoice(q) | BlockKind::Attendance(q) => {
+                q.required
+            }
+            BlockKind::DatePoll(poll) => poll.required,
+            BlockKind::ItemSignup(signup) => signup.required,
+            _ => false,
+        }
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.visibility() == Some(Visibility::Public)
+    }
+
+    pub fn is_attendance(&self) -> bool {
+        matches!(self.kind, BlockKind::Attendance(_))
+    }
+
+    pub fn is_item_signup(&self) -> bool {
+        matches!(self.kind, BlockKind::ItemSignup(_))
+    }
+
+    /// The uploaded attachment an image or file block shows
+    pub fn attachment_id(&self) -> Option<&str> {
+        match &self.kind {
+            BlockKind::Image { attachment_id, .. } | BlockKind::File { attachment_id, .. } => {
+                Some(attachment_id)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BlockError {
+    InvalidJson(String),
+    InvalidBlock { index: usize, reason: String },
+    MissingId { index: usize },
+    DuplicateId(String),
+    InvalidHeadingLevel { id: String },
+    MissingOptions { id: String },
+    EmptyOption { id: String },
+    AttendanceOptionCount { id: String },
+    MultipleAttendanceBlocks,
+    InvalidRange { id: String },
+    InvalidMaxLength { id: String },
+    InvalidSelectionRange { id: String },
+    MissingSlots { id: String },
+    InvalidSlot { id: String, slot: String },
+    DuplicateSlot { id: String, slot: String },
+    MissingItems { id: String },
+    EmptyItem { id: String },
+    InvalidCapacity { id: String, item: String },
+    InvalidConditionTarget { id: String, target: String },
+    InvalidConditionOptions { id: String },
+    MissingAttachment { id: String },
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockError::InvalidJson(e) => write!(f, "Invalid invitation_blocks JSON: {}", e),
+            BlockError::InvalidBlock { index, reason } => {
+                write!(f, "Invalid block at position {}: {}", index + 1, reason)
+            }
+            BlockError::MissingId { index } => {
+                write!(f, "Block at position {} has no ID", index + 1)
+            }
+            BlockError::DuplicateId(id) => write!(f, "Duplicate block ID '{}'", id),
+            BlockError::InvalidHeadingLevel { id } => {
+                write!(f, "Heading block '{}' must have a level between 1 and 3", id)
+            }
+            BlockError::MissingOptions { id } => {
+                write!(f, "Choice block '{}' needs at least one option", id)
+            }
+            BlockError::EmptyOption { id } => {
+                write!(f, "Choice block '{}' has an empty option", id)
+            }
+            BlockError::AttendanceOptionCount { id } => write!(
+                f,
+                "Attendance block '{}' must have exactly three options (yes, maybe, no)",
+                id
+            ),
+            BlockError::MultipleAttendanceBlocks => {
+                write!(f, "Only one attendance block is allowed per party")
+            }
+            BlockError::InvalidRange { id } => {
+                write!(f, "Number block '{}' has a minimum greater than its maximum", id)
+            }
+            BlockError::InvalidMaxLength { id } => write!(
+                f,
+                "Text block '{}' must allow between 1 and {} characters",
+                id,
+                answer::MAX_TEXT_LENGTH
+            ),
+            BlockError::InvalidSelectionRange { id } => write!(
+                f,
+                "Multiple choice block '{}' has selection limits that no answer can meet",
+                id
+            ),
+            BlockError::MissingSlots { id } => {
+                write!(f, "Date poll '{}' needs at least one proposed date", id)
+            }
+            BlockError::InvalidSlot { id, slot } => {
+                write!(f, "Date poll '{}' has an invalid date '{}'", id, slot)
+            }
+            BlockError::DuplicateSlot { id, slot } => {
+                write!(f, "Date poll '{}' proposes '{}' more than once", id, slot)
+            }
+            BlockError::MissingItems { id } => {
+                write!(f, "Sign-up block '{}' needs at least one item", id)
+            }
+            BlockError::EmptyItem { id } => {
+                write!(f, "Sign-up block '{}' has an item without a name", id)
+            }
+            BlockError::InvalidCapacity { id, item } => write!(
+                f,
+                "Item '{}' of sign-up block '{}' must be needed at least once",
+                item, id
+            ),
+            BlockError::InvalidConditionTarget { id, target } => write!(
+                f,
+                "Block '{}' can only depend on a choice question above it, not '{}'",
+                id, target
+            ),
+            BlockError::InvalidConditionOptions { id } => write!(
+                f,
+                "The condition of block '{}' must name at least one existing option",
+                id
+            ),
+            BlockError::MissingAttachment { id } => {
+                write!(f, "Block '{}' needs an uploaded image or file", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+/// Parses stored or submitted invitation blocks.
+/// Accepts both the typed format and the legacy `{id, template, content}` format,
+/// where question blocks keep their label, options and public flag in a JSON string.
+pub fn parse_blocks(raw: &str) -> Result<Vec<InvitationBlock>, BlockError> {
+    if raw.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let values: Vec<Value> =
+        serde_json::from_str(raw).map_err(|e| BlockError::InvalidJson(e.to_string()))?;
+
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            if value.get("type").is_some() {
+                serde_json::from_value(value).map_err(|e| BlockError::InvalidBlock {
+                    index,
+                    reason: e.to_string(),
+                })
+            } else {
+                parse_legacy_block(index, &value)
+            }
+        })
+        .collect()
+}
+
+/// Converts a block saved by Party Hub 0.2 or earlier
+fn parse_legacy_block(index: usize, value: &Value) -> Result<InvitationBlock, BlockError> {
+    let template = value
+        .get("template")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| BlockError::InvalidBlock {
+            index,
+            reason: "missing block type".to_string(),
+        })?;
+
+    // Blocks without an ID were addressed by their position
+    let id = value
+        .get("id")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| index.to_string());
+
+    let content = value.get("content").and_then(|v| v.as_str()).unwrap_or("");
+
+    let kind = match template {
+        "h1" | "h2" | "h3" => BlockKind::Heading {
+            level: template[1..].parse().unwrap_or(1),
+            text: content.to_string(),
+        },
+        "p" => BlockKind::Paragraph {
+            text: content.to_string(),
+        },
+        "code" => BlockKind::Code {
+            text: content.to_string(),
+        },
+        "calendar" => BlockKind::Calendar,
+        "text_input" | "number_input" | "single_choice" | "multiple_choice" | "attendance" => {
+            // Question content is a JSON string; plain text is treated as the label
+            let question = serde_json::from_str::<Value>(content)
+                .ok()
+                .filter(|v| v.is_object())
+                .unwrap_or_else(|| serde_json::json!({ "label": content }));
+
+            let label = question
+                .get("label")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let visibility = if question.get("public").and_then(|v| v.as_bool()) == Some(true) {
+                Visibility::Public
+            } else {
+                Visibility::Private
+            };
+            let options: Vec<String> = question
+                .get("options")
+                .and_then(|v| v.as_array())
+                .map(|opts| {
+                    opts.iter()
+                        .filter_map(|o| o.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            match template {
+                "text_input" | "number_input" => {
+                    let question = Question {
+                        label,
+                        visibility,
+                        required: false,
+                        min: None,
+                        max: None,
+                        max_length: None,
+                    };
+                    if template == "text_input" {
+                        BlockKind::TextInput(question)
+                    } else {
+                        BlockKind::NumberInput(question)
+                    }
+                }
+                "single_choice" => BlockKind::SingleChoice(ChoiceQuestion {
+                    label,
+                    options,
+                    visibility,
+                    required: false,
+                    min_selections: None,
+                    max_selections: None,
+                }),
+                "multiple_choice" => BlockKind::MultipleChoice(ChoiceQuestion {
+                    label,
+                    options,
+                    visibility,
+                    required: false,
+                    min_selections: None,
+                    max_selections: None,
+                }),
+                _ => BlockKind::Attendance(ChoiceQuestion {
+                    label,
+                    // The old editor fell back to these when options were missing
+                    options: if options.is_empty() {
+                        vec!["Yes".to_string(), "Maybe".to_string(), "No".to_string()]
+                    } else {
+                        options
+                    },
+                    visibility,
+                    required: false,
+                    min_selections: None,
+                    max_selections: None,
+                }),
+            }
+        }
+        other => {
+            return Err(BlockError::InvalidBlock {
+                index,
+                reason: format!("unknown block type '{}'", other),
+            });
+        }
+    };
+
+    Ok(InvitationBlock {
+        id,
+        kind,
+        condition: None,
+    })
+}
+
+/// Checks the rules every saved invitation must follow
+pub fn validate_blocks(blocks: &[InvitationBlock]) -> Result<(), BlockError> {
+    let mut earlier: HashMap<&str, &InvitationBlock> = HashMap::new();
+    let mut attendance_count = 0;
+
+    for (index, block) in blocks.iter().enumerate() {
+        if block.id.trim().is_empty() {
+            return Err(BlockError::MissingId { index });
+        }
+        if earlier.contains_key(block.id.as_str()) {
+            return Err(BlockError::DuplicateId(block.id.clone()));
+        }
+        condition::validate(block, &earlier)?;
+
+        match &block.kind {
+            BlockKind::Heading { level, .. } if !(1..=3).contains(level) => {
+                return Err(BlockError::InvalidHeadingLevel {
+                    id: block.id.clone(),
+                });
+            }
+            BlockKind::NumberInput(q) => {
+                if let (Some(min), Some(max)) = (q.min, q.max)
+                    && min > max
+                {
+                    return Err(BlockError::InvalidRange {
+                        id: block.id.clone(),
+                    });
+                }
+            }
+            BlockKind::TextInput(q) => {
+                if let Some(max_length) = q.max_length
+                    && !(1..=answer::MAX_TEXT_LENGTH).contains(&max_length)
+                {
+                    return Err(BlockError::InvalidMaxLength {
+                        id: block.id.clone(),
+                    });
+                }
+            }
+            BlockKind::SingleChoice(q) => {
+                validate_options(&block.id, &q.options)?;
+            }
+            BlockKind::MultipleChoice(q) => {
+                validate_options(&block.id, &q.options)?;
+                let min = q.min_selections.unwrap_or(0);
+                let max = q.max_selections.unwrap_or(q.options.len());
+                if max == 0 || min > max || max > q.options.len() {
+                    return Err(BlockError::InvalidSelectionRange {
+                        id: block.id.clone(),
+                    });
+                }
+            }
+            BlockKind::Attendance(q) => {
+                validate_options(&block.id, &q.options)?;
+                if q.options.len() != 3 {
+                    return Err(BlockError::AttendanceOptionCount {
+                        id: block.id.clone(),
+                    });
+                }
+                attendance_count += 1;
+            }
+            BlockKind::Image { attachment_id, .. } | BlockKind::File { attachment_id, .. }
+                if attachment_id.trim().is_empty() =>
+            {
+                return Err(BlockError::MissingAttachment {
+                    id: block.id.clone(),
+                });
+            }
+            BlockKind::DatePoll(poll) => {
+                poll::validate_slots(&block.id, &poll.slots)?;
+            }
+            BlockKind::ItemSignup(signup) => {
+                if signup.items.is_empty() {
+                    return Err(BlockError::MissingItems {
+                        id: block.id.clone(),
+                    });
+                }
+                for item in &signup.items {
+                    if item.name.trim().is_empty() {
+                        return Err(BlockError::EmptyItem {
+                            id: block.id.clone(),
+                        });
+                    }
+                    if item.capacity < 1 {
+                        return Err(BlockError::InvalidCapacity {
+                            id: block.id.clone(),
+                            item: item.name.clone(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+        earlier.insert(block.id.as_str(), block);
+    }
+
+    if attendance_count > 1 {
+        return Err(BlockError::MultipleAttendanceBlocks);
+    }
+
+    Ok(())
+}
+
+fn validate_options(id: &str, options: &[String]) -> Result<(), BlockError> {
+    if options.is_empty() {
+        return Err(BlockError::MissingOptions { id: id.to_string() });
+    }
+    if options.iter().any(|o| o.trim().is_empty()) {
+        return Err(BlockError::EmptyOption { id: id.to_string() });
+    }
+    Ok(())
+}
+
+/// Serializes blocks in the typed storage format
+pub fn blocks_to_json(blocks: &[InvitationBlock]) -> String {
+    serde_json::to_string(blocks).unwrap_or_else(|_| "[]".to_string())
+}
+
+pub fn attendance_block(blocks: &[InvitationBlock]) -> Option<&InvitationBlock> {
+    blocks.iter().find(|block| block.is_attendance())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Blocks as the 0.1 editor saved them: question settings are a JSON string in `content`
+    const LEGACY_BLOCKS: &str = r#"[{"template":"h1","content":"Summer Party 🎉","id":"block_0f8e2a4c-7d1b-4c3e-9a6f-1b2c3d4e5f60"},{"template":"h2","content":"Where","id":"block_1a2b3c4d-5e6f-4a7b-8c9d-0e1f2a3b4c5d"},{"template":"h3","content":"Getting there","id":"block_2b3c4d5e-6f7a-4b8c-9d0e-1f2a3b4c5d6e"},{"template":"p","content":"Bring *snacks*!\nAnd a towel.","id":"block_3c4d5e6f-7a8b-4c9d-8e1f-2a3b4c5d6e7f"},{"template":"code","content":"WiFi: party / hunter2","id":"block_4d5e6f7a-8b9c-4d0e-9f2a-3b4c5d6e7f80"},{"template":"calendar","content":"","id":"block_5e6f7a8b-9c0d-4e1f-8a3b-4c5d6e7f8091"},{"template":"attendance","content":"{\"label\":\"Are you coming?\",\"public\":true,\"options\":[\"Sure\",\"Perhaps\",\"Sadly not\"]}","id":"block_6f7a8b9c-0d1e-4f2a-9b4c-5d6e7f8091a2"},{"template":"text_input","content":"{\"label\":\"Allergies?\",\"public\":false}","id":"block_7a8b9c0d-1e2f-4a3b-8c5d-6e7f8091a2b3"},{"template":"number_input","content":"{\"label\":\"How many slices of cake?\",\"public\":true}","id":"block_8b9c0d1e-2f3a-4b4c-9d6e-7f8091a2b3c4"},{"template":"single_choice","content":"{\"label\":\"Main course\",\"public\":false,\"options\":[\"Pasta\",\"Curry\"]}","id":"block_9c0d1e2f-3a4b-4c5d-8e7f-8091a2b3c4d5"},{"template":"multiple_choice","content":"{\"label\":\"Drinks\",\"public\":true,\"options\":[\"Beer\",\"Wine\",\"Juice\"]}","id":"block_0d1e2f3a-4b5c-4d6e-9f80-91a2b3c4d5e6"}]"#;
+
+    fn choice(block: &InvitationBlock) -> &ChoiceQuestion {
+        match &block.kind {
+            BlockKind::SingleChoice(q) | BlockKind::MultipleChoice(q) | BlockKind::Attendance(q) => q,
+            other => panic!("{:?} is no choice question", other),
+        }
+    }
+
+    fn question(block: &InvitationBlock) -> &Question {
+        match &block.kind {
+            BlockKind::TextInput(q) | BlockKind::NumberInput(q) => q,
+            other => panic!("{:?} is no question", other),
+        }
+    }
+
+    #[test]
+    fn converts_legacy_blocks() {
+        let blocks = parse_blocks(LEGACY_BLOCKS).unwrap();
+        assert_eq!(blocks.len(), 11);
+        assert_eq!(blocks[0].id, "block_0f8e2a4c-7d1b-4c3e-9a6f-1b2c3d4e5f60");
+        assert!(blocks.iter().all(|block| block.condition.is_none()));
+
+        for (block, expected_level, expected_text) in [
+            (&blocks[0], 1, "Summer Party 🎉"),
+            (&blocks[1], 2, "Where"),
+            (&blocks[2], 3, "Getting there"),
+        ] {
+            let BlockKind::Heading { level, text } = &block.kind else {
+                panic!("{:?} is no heading", block.kind);
+            };
+            assert_eq!((*level, text.as_str()), (expected_level, expected_text));
+        }
+        assert!(matches!(&blocks[3].kind, BlockKind::Paragraph { text } if text == "Bring *snacks*!\nAnd a towel."));
+        assert!(matches!(&blocks[4].kind, BlockKind::Code { text } if text == "WiFi: party / hunter2"));
+        assert!(matches!(blocks[5].kind, BlockKind::Calendar));
+
+        assert!(blocks[6].is_attendance());
+        let attendance = choice(&blocks[6]);
+        assert_eq!(attendance.label, "Are you coming?");
+        assert_eq!(attendance.options, ["Sure", "Perhaps", "Sadly not"]);
+        assert_eq!(attendance.visibility, Visibility::Public);
+
+        assert!(matches!(blocks[7].kind, BlockKind::TextInput(_)));
+        assert_eq!(question(&blocks[7]).label, "Allergies?");
+        assert_eq!(question(&blocks[7]).visibility, Visibility::Private);
+        assert!(matches!(blocks[8].kind, BlockKind::NumberInput(_)));
+        assert_eq!(question(&blocks[8]).visibility, Visibility::Public);
+        assert_eq!(question(&blocks[8]).min, None);
+
+        assert!(matches!(blocks[9].kind, BlockKind::SingleChoice(_)));
+        assert_eq!(choice(&blocks[9]).options, ["Pasta", "Curry"]);
+        assert!(matches!(blocks[10].kind, BlockKind::MultipleChoice(_)));
+        assert_eq!(choice(&blocks[10]).label, "Drinks");
+        assert_eq!(choice(&blocks[10]).options, ["Beer", "Wine", "Juice"]);
+
+        // Nothing about them is required, so old invitations keep working the same
+        assert!(blocks.iter().all(|block| !block.is_required()));
+        validate_blocks(&blocks).unwrap();
+        // Saving them again writes the typed format, which reads back the same
+        let typed = parse_blocks(&blocks_to_json(&blocks)).unwrap();
+        assert_eq!(blocks_to_json(&typed), blocks_to_json(&blocks));
+    }
+
+    #[test]
+    fn fills_in_what_legacy_blocks_left_out() {
+        let blocks = parse_blocks(
+            r#"[{"template":"p","content":"Hi"},{"template":"attendance","content":"{\"label\":\"Coming?\"}"},{"template":"text_input","content":"Plain label"}]"#,
+        )
+        .unwrap();
+        // Blocks without an ID were addressed by their position
+        assert_eq!(blocks[0].id, "0");
+        assert_eq!(choice(&blocks[1]).options, ["Yes", "Maybe", "No"]);
+        assert_eq!(question(&blocks[2]).label, "Plain label");
+        assert_eq!(parse_blocks("  ").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn rejects_invalid_blocks() {
+        assert!(matches!(
+            parse_blocks(r#"[{"template":"p","content":"ok","id":"a"},{"template":"marquee","content":"x","id":"b"}]"#),
+            Err(BlockError::InvalidBlock { index: 1, .. })
+        ));
+        assert!(matches!(
+            parse_blocks(r#"[{"content":"no type","id":"a"}]"#),
+            Err(BlockError::InvalidBlock { index: 0, .. })
+        ));
+        assert!(matches!(
+            parse_blocks(r#"[{"id":"a","type":"heading","text":"No level"}]"#),
+            Err(BlockError::InvalidBlock { index: 0, .. })
+        ));
+        assert!(matches!(
+            parse_blocks(r#"[{"template":"p","content":"cut off""#),
+            Err(BlockError::InvalidJson(_))
+        ));
+        assert!(matches!(
+            parse_blocks(r#"{"template":"p"}"#),
+            Err(BlockError::InvalidJson(_))
+        ));
+    }
+}
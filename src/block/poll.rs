@@ -0,0 +1,118 @@
+use super::{BlockError, BlockKind, DatePoll, InvitationBlock};
+use chrono::NaiveDateTime;
+use serde_json::{Map, Value, json};
+use std::collections::HashSet;
+
+// A date poll answer is an array with one entry per slot: the index of the chosen
+// availability, or null for slots the guest didn't answer.
+
+pub const POLL_YES: i64 = 0;
+pub const POLL_IF_NEED_BE: i64 = 1;
+pub const POLL_NO: i64 = 2;
+
+/// Format of proposed slots, as produced by datetime-local inputs
+const SLOT_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+pub fn validate_slots(id: &str, slots: &[String]) -> Result<(), BlockError> {
+    if slots.is_empty() {
+        return Err(BlockError::MissingSlots { id: id.to_string() });
+    }
+    let mut seen = HashSet::new();
+    for slot in slots {
+        if NaiveDateTime::parse_from_str(slot, SLOT_FORMAT).is_err() {
+            return Err(BlockError::InvalidSlot {
+                id: id.to_string(),
+                slot: slot.clone(),
+            });
+        }
+        if !seen.insert(slot.as_str()) {
+            return Err(BlockError::DuplicateSlot {
+                id: id.to_string(),
+                slot: slot.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The slot most guests can make (if need be counts), preferring more clear yeses
+/// and then the earlier proposal. `None` until someone answered.
+pub fn winning_slot(poll: &DatePoll, answers: &[&Value]) -> Option<usize> {
+    let tallies = tally(poll, answers);
+    let mut winner: Option<(usize, (usize, usize))> = None;
+    for (index, [yes, if_need_be, _]) in tallies.into_iter().enumerate() {
+        let score = (yes + if_need_be, yes);
+        if score.0 > 0 && winner.is_none_or(|(_, best)| score > best) {
+            winner = Some((index, score));
+        }
+    }
+    winner.map(|(index, _)| index)
+}
+
+/// Number of yes, if need be and no answers per slot
+fn tally(poll: &DatePoll, answers: &[&Value]) -> Vec<[usize; 3]> {
+    let mut tallies = vec![[0; 3]; poll.slots.len()];
+    for answer in answers {
+        let Some(choices) = answer.as_array() else {
+            continue;
+        };
+        for (tally, choice) in tallies.iter_mut().zip(choices) {
+            match choice.as_i64() {
+                Some(POLL_YES) => tally[0] += 1,
+                Some(POLL_IF_NEED_BE) => tally[1] += 1,
+                Some(POLL_NO) => tally[2] += 1,
+                _ => {}
+            }
+        }
+    }
+    tallies
+}
+
+/// Results matrix of every date poll in `blocks` that `include` accepts, keyed by block ID.
+/// `responses` are (guest name, answers) pairs with answers keyed by block ID.
+pub fn results(
+    blocks: &[InvitationBlock],
+    responses: &[(String, Value)],
+    include: impl Fn(&InvitationBlock) -> bool,
+) -> Value {
+    let mut polls = Map::new();
+    for block in blocks.iter().filter(|block| include(block)) {
+        let BlockKind::DatePoll(poll) = &block.kind else {
+            continue;
+        };
+
+        let answered: Vec<(&String, &Value)> = responses
+            .iter()
+            .filter_map(|(name, answers)| answers.get(&block.id).map(|answer| (name, answer)))
+            .collect();
+        let answers: Vec<&Value> = answered.iter().map(|(_, answer)| *answer).collect();
+
+        let slots: Vec<Value> = poll
+            .slots
+            .iter()
+            .zip(tally(poll, &answers))
+            .map(|(slot, [yes, if_need_be, no])| {
+                json!({
+                    "slot": slot,
+                    "yes": yes,
+                    "if_need_be": if_need_be,
+                    "no": no
+                })
+            })
+            .collect();
+        let rows: Vec<Value> = answered
+            .iter()
+            .map(|(name, answer)| json!({ "guest_name": name, "answers": answer }))
+            .collect();
+
+        polls.insert(
+            block.id.clone(),
+            json!({
+                "slots": slots,
+                "rows": rows,
+                "winner": winning_slot(poll, &answers)
+            }),
+        );
+    }
+    Value::Object(polls)
+}
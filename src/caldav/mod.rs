@@ -0,0 +1,537 @@
+use crate::auth::is_authenticated_as_author_basic;
+use crate::calendar::{self, Calendar, Method};
+use crate::i18n::{self, detect_language};
+use crate::templating::Variables;
+use crate::timezone;
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, Responder, Scope, route, web};
+use chrono::NaiveDateTime;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+// A read-only CalDAV server (RFC 4791), so calendar apps can sync an author's parties.
+// Apps log in with HTTP Basic credentials, the author secret being the password
+// (see `auth::is_authenticated_as_author_basic`). Everything lives under `/caldav/`:
+//   /caldav/                      the author, who is the principal and the calendar home
+//   /caldav/parties/              the calendar with all of the author's parties
+//   /caldav/parties/<id>.ics      one party, as the author's feed shows it
+// Requested properties aren't looked at: every response carries all properties there are.
+
+const HOME: &str = "/caldav/";
+const COLLECTION: &str = "/caldav/parties/";
+const DAV_HEADER: &str = "1, calendar-access";
+const ALLOW_HEADER: &str = "OPTIONS, GET, HEAD, PROPFIND, REPORT";
+
+/// A party in the collection
+struct Resource {
+    href: String,
+    calendar: Calendar,
+}
+
+/// The author's parties that have a date and time, in the author's feed language
+fn load_resources(
+    conn: &rusqlite::Connection,
+    author_id: &str,
+    language: &str,
+) -> rusqlite::Result<(String, Vec<Resource>)> {
+    let (author_name, parties) = calendar::hosted_parties(conn, author_id)?;
+    let mut resources = Vec::new();
+    for party in &parties {
+        if let Some(event) = calendar::hosted_event(conn, party, &author_name, language)? {
+            resources.push(Resource {
+                href: format!("{}{}.ics", COLLECTION, party.id),
+                calendar: Calendar {
+                    method: Method::Publish,
+                    name: None,
+                    events: vec![event],
+                },
+            });
+        }
+    }
+    Ok((author_name, resources))
+}
+
+/// Authenticates the request and loads the author's name and parties
+fn load(
+    req: &HttpRequest,
+    db: &Pool<SqliteConnectionManager>,
+) -> Result<(String, Vec<Resource>), HttpResponse> {
+    let Some(author_id) = is_authenticated_as_author_basic(req, db) else {
+        return Err(HttpResponse::Unauthorized()
+            .append_header((
+                "WWW-Authenticate",
+                "Basic realm=\"Party Hub\", charset=\"UTF-8\"",
+            ))
+            .finish());
+    };
+    let conn = db
+        .get()
+        .map_err(|_| HttpResponse::InternalServerError().body("Database connection failed"))?;
+    load_resources(&conn, &author_id, &detect_language(req)).map_err(|e| {
+        eprintln!("Database error loading CalDAV collection: {}", e);
+        HttpResponse::InternalServerError().body("Calendar could not be loaded")
+    })
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The `<response>` for a resource, with its properties as XML
+fn response(href: &str, props: &str) -> String {
+    format!(
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>{}</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        escape_xml(href),
+        props
+    )
+}
+
+fn not_found(href: &str) -> String {
+    format!(
+        "<D:response><D:href>{}</D:href><D:status>HTTP/1.1 404 Not Found</D:status></D:response>",
+        escape_xml(href)
+    )
+}
+
+fn multistatus(responses: Vec<String>) -> HttpResponse {
+    HttpResponse::build(StatusCode::MULTI_STATUS)
+        .content_type("application/xml; charset=utf-8")
+        .append_header(("DAV", DAV_HEADER))
+        .body(format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\" xmlns:CS=\"http://calendarserver.org/ns/\">{}</D:multistatus>",
+            responses.concat()
+        ))
+}
+
+/// Whether a PROPFIND asks for the members of a collection as well (`Depth: 1` or the
+/// default `infinity`, which goes no deeper here)
+fn include_members(req: &HttpRequest) -> bool {
+    req.headers()
+        .get("Depth")
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|depth| depth.trim() != "0")
+}
+
+/// Properties pointing clients from any resource to the author and their calendars
+fn principal_props() -> String {
+    format!(
+        "<D:current-user-principal><D:href>{HOME}</D:href></D:current-user-principal>\
+         <D:principal-URL><D:href>{HOME}</D:href></D:principal-URL>\
+         <C:calendar-home-set><D:href>{HOME}</D:href></C:calendar-home-set>\
+         <D:current-user-privilege-set><D:privilege><D:read/></D:privilege></D:current-user-privilege-set>"
+    )
+}
+
+fn home_props(author_name: &str) -> String {
+    format!(
+        "<D:resourcetype><D:collection/><D:principal/></D:resourcetype><D:displayname>{}</D:displayname>{}",
+        escape_xml(author_name),
+        principal_props()
+    )
+}
+
+fn collection_props(req: &HttpRequest, author_name: &str, resources: &[Resource]) -> String {
+    // The collection changes whenever one of its parties does, or one is added or removed
+    let mut hasher = DefaultHasher::new();
+    for resource in resources {
+        (&resource.href, resource.calendar.etag()).hash(&mut hasher);
+    }
+    let mut variables = Variables::default();
+    variables.set("author_name", author_name);
+    let name = i18n::text_with(
+        &detect_language(req),
+        "calendar-author-feed-name",
+        &variables,
+    );
+    format!(
+        "<D:resourcetype><D:collection/><C:calendar/></D:resourcetype>\
+         <D:displayname>{}</D:displayname>\
+         <C:supported-calendar-component-set><C:comp name=\"VEVENT\"/></C:supported-calendar-component-set>\
+         <CS:getctag>\"{:016x}\"</CS:getctag>{}",
+        escape_xml(&name),
+        hasher.finish(),
+        principal_props()
+    )
+}
+
+fn resource_props(resource: &Resource, with_data: bool) -> String {
+    let mut props = format!(
+        "<D:resourcetype/><D:getcontenttype>text/calendar; charset=utf-8; component=vevent</D:getcontenttype><D:getetag>{}</D:getetag>",
+        escape_xml(&resource.calendar.etag())
+    );
+    if with_data {
+        props.push_str(&format!(
+            "<C:calendar-data>{}</C:calendar-data>",
+            escape_xml(&resource.calendar.render_object())
+        ));
+    }
+    props
+}
+
+/// Every element called `name` (in any namespace) as the text inside its tag and the text it
+/// contains up to the next tag, e.g. `("C:time-range start=\"…\"", "")`
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<(&'a str, &'a str)> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        let Some(length) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + length];
+        rest = &rest[start + length + 1..];
+        let tag_name = tag
+            .split([' ', '\t', '\r', '\n', '/'])
+            .next()
+            .unwrap_or_default();
+        let local_name = tag_name.rsplit(':').next().unwrap_or_default();
+        if local_name == name && !tag.starts_with(['/', '?', '!']) {
+            let text = if tag.ends_with('/') {
+                ""
+            } else {
+                &rest[..rest.find('<').unwrap_or(rest.len())]
+            };
+            found.push((tag, text.trim()));
+        }
+    }
+    found
+}
+
+/// The value of the attribute `name` of a tag as `elements` returns it. Attributes are read
+/// one after the other, so `name` only matches a whole attribute name.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag.trim_end_matches('/').split_once(char::is_whitespace)?.1;
+    loop {
+        let (key, value) = rest.split_once('=')?;
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        let (value, after) = value[1..].split_once(quote)?;
+        if key.trim() == name {
+            return Some(value);
+        }
+        rest = after;
+    }
+}
+
+/// Whether the party overlaps a `time-range` filter (RFC 4791, section 9.9)
+fn in_time_range(resource: &Resource, start: Option<&str>, end: Option<&str>) -> bool {
+    let parse = |value: Option<&str>| {
+        value.and_then(|value| NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ").ok())
+    };
+    let (range_start, range_end) = (parse(start), parse(end));
+    resource.calendar.events.iter().any(|event| {
+        let event_start = timezone::to_utc(event.start, event.time_zone).naive_utc();
+        let event_end = timezone::to_utc(event.end, event.time_zone).naive_utc();
+        let starts_before_end = range_end.is_none_or(|range_end| event_start < range_end);
+        let ends_after_start = range_start.is_none_or(|range_start| {
+            if event_end > event_start {
+                event_end > range_start
+            } else {
+                event_start >= range_start
+            }
+        });
+        starts_before_end && ends_after_start
+    })
+}
+
+/// Calendar apps ask which methods and features are supported before anything else
+#[route("/{path:.*}", method = "OPTIONS")]
+async fn options() -> impl Responder {
+    HttpResponse::Ok()
+        .append_header(("DAV", DAV_HEADER))
+        .append_header(("Allow", ALLOW_HEADER))
+        .finish()
+}
+
+#[route("/", method = "PROPFIND")]
+async fn propfind_home(
+    req: HttpRequest,
+    db: web::Data<Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    let (author_name, resources) = match load(&req, &db) {
+        Ok(loaded) => loaded,
+        Err(response) => return response,
+    };
+    let mut responses = vec![response(HOME, &home_props(&author_name))];
+    if include_members(&req) {
+        responses.push(response(
+            COLLECTION,
+            &collection_props(&req, &author_name, &resources),
+        ));
+    }
+    multistatus(responses)
+}
+
+#[route("/parties/", method = "PROPFIND")]
+async fn propfind_collection(
+    req: HttpRequest,
+    db: web::Data<Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    let (author_name, resources) = match load(&req, &db) {
+        Ok(loaded) => loaded,
+        Err(response) => return response,
+    };
+    let mut responses = vec![response(
+        COLLECTION,
+        &collection_props(&req, &author_name, &resources),
+    )];
+    if include_members(&req) {
+        for resource in &resources {
+            responses.push(response(&resource.href, &resource_props(resource, false)));
+        }
+    }
+    multistatus(responses)
+}
+
+#[route("/parties/{file}", method = "PROPFIND")]
+async fn propfind_resource(
+    path: web::Path<String>,
+    req: HttpRequest,
+    db: web::Data<Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    let href = format!("{}{}", COLLECTION, path.into_inner());
+    let (_, resources) = match load(&req, &db) {
+        Ok(loaded) => loaded,
+        Err(response) => return response,
+    };
+    match resources.iter().find(|resource| resource.href == href) {
+        Some(resource) => multistatus(vec![response(&href, &resource_props(resource, false))]),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// The responses to a `calendar-multiget`: each party asked for, or a 404 for hrefs that aren't one
+fn multiget(body: &str, resources: &[Resource]) -> Vec<String> {
+    elements(body, "href")
+        .into_iter()
+        .map(|(_, href)| {
+            // Clients may send full URLs, the path is what identifies the party
+            let file = href.rsplit('/').next().unwrap_or_default();
+            match resources
+                .iter()
+                .find(|resource| resource.href.rsplit('/').next() == Some(file))
+            {
+                Some(resource) => response(&resource.href, &resource_props(resource, true)),
+                None => not_found(href),
+            }
+        })
+        .collect()
+}
+
+/// The responses to a `calendar-query`: the parties in its time range, or all of them
+fn query(body: &str, resources: &[Resource]) -> Vec<String> {
+    let time_range = elements(body, "time-range").into_iter().next();
+    let (start, end) = match time_range {
+        Some((tag, _)) => (attribute(tag, "start"), attribute(tag, "end")),
+        None => (None, None),
+    };
+    resources
+        .iter()
+        .filter(|resource| in_time_range(resource, start, end))
+        .map(|resource| response(&resource.href, &resource_props(resource, true)))
+        .collect()
+}
+
+/// `calendar-multiget` returns the parties asked for by their `href`, `calendar-query` all
+/// parties, narrowed down by a `time-range` if there is one. Other filters are ignored.
+#[route("/parties/", method = "REPORT")]
+async fn report(
+    body: String,
+    req: HttpRequest,
+    db: web::Data<Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    let (_, resources) = match load(&req, &db) {
+        Ok(loaded) => loaded,
+        Err(response) => return response,
+    };
+
+    if !elements(&body, "calendar-multiget").is_empty() {
+        multistatus(multiget(&body, &resources))
+    } else if !elements(&body, "calendar-query").is_empty() {
+        multistatus(query(&body, &resources))
+    } else {
+        HttpResponse::Forbidden()
+            .content_type("application/xml; charset=utf-8")
+            .body("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:error xmlns:D=\"DAV:\"><D:supported-report/></D:error>")
+    }
+}
+
+#[route("/parties/{file}", method = "GET", method = "HEAD")]
+async fn get_resource(
+    path: web::Path<String>,
+    req: HttpRequest,
+    db: web::Data<Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    let href = format!("{}{}", COLLECTION, path.into_inner());
+    let (_, resources) = match load(&req, &db) {
+        Ok(loaded) => loaded,
+        Err(response) => return response,
+    };
+    match resources.iter().find(|resource| resource.href == href) {
+        Some(resource) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .append_header(("ETag", resource.calendar.etag()))
+            .body(resource.calendar.render_object()),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// Where calendar apps look for the server when they are only given the host (RFC 6764)
+#[route("/.well-known/caldav", method = "GET", method = "PROPFIND")]
+pub async fn well_known() -> impl Responder {
+    HttpResponse::MovedPermanently()
+        .append_header(("Location", HOME))
+        .finish()
+}
+
+pub fn subroutes() -> Scope {
+    web::scope("/caldav")
+        .service(options)
+        .service(propfind_home)
+        .service(propfind_collection)
+        .service(propfind_resource)
+        .service(report)
+        .service(get_resource)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::Event;
+    use actix_web::test::TestRequest;
+    use chrono::{Duration, NaiveDate};
+    use chrono_tz::Tz;
+
+    /// A party in the collection starting at the given wall-clock time and lasting `hours`
+    fn resource(id: &str, time_zone: Tz, hour: u32, hours: i64) -> Resource {
+        let start = NaiveDate::from_ymd_opt(2026, 11, 7)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap();
+        Resource {
+            href: format!("{}{}.ics", COLLECTION, id),
+            calendar: Calendar {
+                method: Method::Publish,
+                name: None,
+                events: vec![Event {
+                    uid: format!("{}@party-hub", id),
+                    sequence: 0,
+                    start,
+                    end: start + Duration::hours(hours),
+                    time_zone,
+                    summary: "Autumn party".to_string(),
+                    location: String::new(),
+                    description: String::new(),
+                    url: None,
+                    organizer_name: String::new(),
+                    attendee: None,
+                    reminder: false,
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn multiget_resolves_paths_and_urls() {
+        let resources = [resource("a", Tz::UTC, 18, 5), resource("b", Tz::UTC, 18, 5)];
+        let body = r#"<?xml version="1.0" encoding="utf-8" ?>
+<C:calendar-multiget xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:prop><D:getetag/><C:calendar-data/></D:prop>
+  <D:href>/caldav/parties/a.ics</D:href>
+  <D:href>https://party.example.com/caldav/parties/b.ics</D:href>
+  <D:href>/caldav/parties/gone.ics</D:href>
+</C:calendar-multiget>"#;
+        let responses = multiget(body, &resources);
+        assert_eq!(responses.len(), 3);
+        assert!(responses[0].starts_with("<D:response><D:href>/caldav/parties/a.ics</D:href>"));
+        assert!(responses[0].contains("UID:a@party-hub"));
+        // Full URLs are answered with the party's own href
+        assert!(responses[1].starts_with("<D:response><D:href>/caldav/parties/b.ics</D:href>"));
+        assert!(responses[1].contains("UID:b@party-hub"));
+        assert_eq!(
+            responses[2],
+            "<D:response><D:href>/caldav/parties/gone.ics</D:href><D:status>HTTP/1.1 404 Not Found</D:status></D:response>"
+        );
+    }
+
+    #[test]
+    fn finds_elements_and_attributes() {
+        let body = r#"<c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT">
+<c:time-range xstart="20000101T000000Z" end='20261108T000000Z'
+  start="20261107T000000Z"/></c:comp-filter></c:comp-filter></c:filter>"#;
+        let ranges = elements(body, "time-range");
+        assert_eq!(ranges.len(), 1);
+        let (tag, text) = ranges[0];
+        assert_eq!(text, "");
+        assert_eq!(attribute(tag, "start"), Some("20261107T000000Z"));
+        assert_eq!(attribute(tag, "end"), Some("20261108T000000Z"));
+        assert_eq!(attribute(tag, "art"), None);
+        assert_eq!(elements(body, "comp-filter").len(), 2);
+        assert_eq!(
+            elements("<D:href> /a.ics </D:href>", "href"),
+            [("D:href", "/a.ics")]
+        );
+    }
+
+    #[test]
+    fn time_ranges_overlap() {
+        let in_range = |resource: &Resource, start: &str, end: &str| {
+            in_time_range(
+                resource,
+                Some(start).filter(|start| !start.is_empty()),
+                Some(end).filter(|end| !end.is_empty()),
+            )
+        };
+        // 18:00 to 23:00 UTC
+        let party = resource("a", Tz::UTC, 18, 5);
+        assert!(in_range(&party, "20261107T000000Z", "20261108T000000Z"));
+        assert!(in_range(&party, "20261107T225900Z", ""));
+        assert!(in_range(&party, "", "20261107T180100Z"));
+        assert!(!in_range(&party, "20261107T230000Z", ""));
+        assert!(!in_range(&party, "", "20261107T180000Z"));
+        assert!(in_range(&party, "", ""));
+
+        // The wall-clock time is in the party's zone: 19:00 in Berlin is 18:00 UTC
+        let berlin = resource("b", chrono_tz::Europe::Berlin, 19, 5);
+        assert!(!in_range(&berlin, "", "20261107T180000Z"));
+        assert!(in_range(&berlin, "", "20261107T180100Z"));
+
+        // Events without a duration are in a range that contains their start
+        let moment = resource("c", Tz::UTC, 18, 0);
+        assert!(in_range(&moment, "20261107T180000Z", "20261107T180100Z"));
+        assert!(!in_range(&moment, "20261107T180100Z", ""));
+        assert!(!in_range(&moment, "", "20261107T180000Z"));
+    }
+
+    #[test]
+    fn queries_filter_by_time_range() {
+        let resources = [resource("a", Tz::UTC, 18, 5), resource("b", Tz::UTC, 23, 1)];
+        let body = r#"<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+<C:filter><C:comp-filter name="VCALENDAR"><C:comp-filter name="VEVENT">
+<C:time-range start="20261107T230000Z" end="20261108T000000Z"/>
+</C:comp-filter></C:comp-filter></C:filter></C:calendar-query>"#;
+        let responses = query(body, &resources);
+        assert_eq!(responses.len(), 1);
+        assert!(responses[0].contains("/caldav/parties/b.ics"));
+        assert_eq!(query("<C:calendar-query/>", &resources).len(), 2);
+    }
+
+    #[test]
+    fn depth_zero_leaves_out_members() {
+        let depth = |value: Option<&str>| {
+            let mut req = TestRequest::default();
+            if let Some(value) = value {
+                req = req.insert_header(("Depth", value));
+            }
+            include_members(&req.to_http_request())
+        };
+        assert!(!depth(Some("0")));
+        assert!(!depth(Some(" 0 ")));
+        assert!(depth(Some("1")));
+        assert!(depth(Some("infinity")));
+        assert!(depth(None));
+    }
+}
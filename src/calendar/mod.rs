@@ -0,0 +1,580 @@
+use crate::block;
+use crate::db::series;
+use crate::db::{GUEST_COLUMNS, Guest, Invitation, PARTY_COLUMNS, Party, answers};
+use crate::templating::{self, Variables};
+use crate::{i18n, timezone};
+use chrono::{Duration, NaiveDateTime};
+use chrono_tz::Tz;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+pub mod reply;
+
+// iCalendar (RFC 5545) files for invitations and series feeds, and the scheduling messages
+// (RFC 5546) attached to emails. Each invitation is its own event with the UID
+// `<invitation ID>@party-hub`, so a downloaded file and later emails about the invitation
+// change the same calendar entry. SEQUENCE counts the party's updates, so calendars can tell
+// which version is the newest.
+//
+// Guests and authors can also subscribe to a feed of all their parties (see `db::feeds`), and
+// guests can answer from their calendar app (see `reply`).
+
+/// When calendars remind guests of a party they were invited to
+const REMINDER: &str = "-P1D";
+
+/// Lines longer than this many octets are folded
+const MAX_LINE_OCTETS: usize = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// A plain file, e.g. a download or a subscribed feed
+    Publish,
+    /// An invitation or an update to one
+    Request,
+    /// The party was called off
+    Cancel,
+}
+
+impl Method {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Publish => "PUBLISH",
+            Method::Request => "REQUEST",
+            Method::Cancel => "CANCEL",
+        }
+    }
+}
+
+/// The guest an invitation is for, if they have an email address
+#[derive(Debug, Clone)]
+pub struct Attendee {
+    pub name: String,
+    pub email: String,
+    pub status: &'static str, // PARTSTAT, from the guest's attendance answer
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub uid: String,
+    pub sequence: i64,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub time_zone: Tz,
+    pub summary: String,
+    pub location: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub organizer_name: String,
+    pub attendee: Option<Attendee>,
+    pub reminder: bool,
+}
+
+impl Event {
+    /// The party as an event, if it has a date and time yet
+    pub fn for_party(party: &Party, uid: String) -> Option<Self> {
+        let start = series::parse_date(&party.date)?;
+        Some(Event {
+            uid,
+            sequence: party.sequence,
+            start,
+            end: start + Duration::minutes((party.duration * 60.0).round() as i64),
+            time_zone: party.zone(),
+            summary: party.name.clone(),
+            location: party.location.clone(),
+            description: String::new(),
+            url: None,
+            organizer_name: String::new(),
+            attendee: None,
+            reminder: false,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    pub method: Method,
+    pub name: Option<String>,
+    pub events: Vec<Event>,
+}
+
+/// Escapes a TEXT value
+pub fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\n', '\r'], "\\n")
+}
+
+/// A parameter value, quoted since it may contain `:`, `;` or `,` (which can't be escaped)
+fn quote_param(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .filter(|c| *c != '"' && !c.is_control())
+        .collect();
+    format!("\"{}\"", value)
+}
+
+/// Splits a content line into lines of at most 75 octets, each continued with a space,
+/// without cutting a UTF-8 character in half
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / MAX_LINE_OCTETS * 3);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// The address emails are sent from (`SMTP_FROM`), which guests reply to as the organizer
+fn organizer_address() -> Option<String> {
+    let from = std::env::var("SMTP_FROM").ok()?;
+    let address = match (from.find('<'), from.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &from[start + 1..end],
+        _ => from.as_str(),
+    };
+    let address = address.trim();
+    address.contains('@').then(|| address.to_string())
+}
+
+impl Calendar {
+    pub fn render(&self) -> String {
+        self.render_with_method(true)
+    }
+
+    /// The calendar as a resource of a CalDAV collection, which must not name a method
+    pub fn render_object(&self) -> String {
+        self.render_with_method(false)
+    }
+
+    fn render_with_method(&self, with_method: bool) -> String {
+        let mut output = String::new();
+        let mut line = |line: String| output.push_str(&fold(&line));
+
+        line("BEGIN:VCALENDAR".to_string());
+        line("VERSION:2.0".to_string());
+        line("PRODID:-//Party Hub//EN".to_string());
+        line("CALSCALE:GREGORIAN".to_string());
+        if with_method {
+            line(format!("METHOD:{}", self.method.as_str()));
+        }
+        if let Some(name) = &self.name {
+            line(format!("X-WR-CALNAME:{}", escape_text(name)));
+        }
+
+        // Each time zone is described once, covering all of its events
+        let mut time_zones: Vec<Tz> = self.events.iter().map(|event| event.time_zone).collect();
+        time_zones.sort_by_key(|time_zone| time_zone.name());
+        time_zones.dedup();
+        let mut components = String::new();
+        for time_zone in time_zones {
+            let in_zone = self
+                .events
+                .iter()
+                .filter(|event| event.time_zone == time_zone);
+            let start = in_zone.clone().map(|event| event.start).min();
+            let end = in_zone.map(|event| event.end).max();
+            if let (Some(start), Some(end)) = (start, end) {
+                components.push_str(&timezone::vtimezone(time_zone, start, end));
+            }
+        }
+
+        let dtstamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let organizer = organizer_address();
+        for event in &self.events {
+            components.push_str(&self.render_event(event, &dtstamp, organizer.as_deref()));
+        }
+        output.push_str(&components);
+        output.push_str(&fold("END:VCALENDAR"));
+        output
+    }
+
+    /// A tag that changes whenever the content does, for caching and syncing.
+    /// `DTSTAMP` is left out, since it only records when the file was made.
+    pub fn etag(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        for line in self.render_object().lines() {
+            if !line.starts_with("DTSTAMP:") {
+                line.hash(&mut hasher);
+            }
+        }
+        format!("\"{:016x}\"", hasher.finish())
+    }
+
+    fn render_event(&self, event: &Event, dtstamp: &str, organizer: Option<&str>) -> String {
+        let mut output = String::new();
+        let mut line = |line: String| output.push_str(&fold(&line));
+
+        // A cancellation is a newer version of the event than the one calendars know
+        let (status, sequence) = if self.method == Method::Cancel {
+            ("CANCELLED", event.sequence + 1)
+        } else {
+            ("CONFIRMED", event.sequence)
+        };
+
+        line("BEGIN:VEVENT".to_string());
+        line(format!("UID:{}", event.uid));
+        line(format!("DTSTAMP:{}", dtstamp));
+        line(format!("SEQUENCE:{}", sequence));
+        line(format!("STATUS:{}", status));
+        line(timezone::ics_property(
+            "DTSTART",
+            event.start,
+            event.time_zone,
+        ));
+        line(timezone::ics_property("DTEND", event.end, event.time_zone));
+        line(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if !event.location.is_empty() {
+            line(format!("LOCATION:{}", escape_text(&event.location)));
+        }
+        if !event.description.is_empty() {
+            line(format!("DESCRIPTION:{}", escape_text(&event.description)));
+        }
+        if let Some(url) = &event.url {
+            line(format!("URL:{}", url));
+        }
+        if let Some(address) = organizer {
+            if event.organizer_name.is_empty() {
+                line(format!("ORGANIZER:mailto:{}", address));
+            } else {
+                line(format!(
+                    "ORGANIZER;CN={}:mailto:{}",
+                    quote_param(&event.organizer_name),
+                    address
+                ));
+            }
+        }
+        // Published files aren't addressed to anyone, so they name no attendees
+        if let Some(attendee) = &event.attendee
+            && self.method != Method::Publish
+        {
+            // Calendar apps only answer invitations if the replies are read (see `reply`)
+            let rsvp = self.method == Method::Request && reply::maildir().is_some();
+            line(format!(
+                "ATTENDEE;CN={};ROLE=REQ-PARTICIPANT;PARTSTAT={};RSVP={}:mailto:{}",
+                quote_param(&attendee.name),
+                attendee.status,
+                if rsvp { "TRUE" } else { "FALSE" },
+                attendee.email
+            ));
+        }
+        if event.reminder && self.method != Method::Cancel {
+            line("BEGIN:VALARM".to_string());
+            line("ACTION:DISPLAY".to_string());
+            line(format!("DESCRIPTION:{}", escape_text(&event.summary)));
+            line(format!("TRIGGER:{}", REMINDER));
+            line("END:VALARM".to_string());
+        }
+        line("END:VEVENT".to_string());
+        output
+    }
+}
+
+/// The participation status matching the guest's attendance answer (yes, maybe, no)
+fn participation_status(conn: &Connection, party: &Party, invitation_id: &str) -> &'static str {
+    let answer = block::attendance_block(&party.invitation_blocks)
+        .and_then(|attendance| {
+            answers::load_one(conn, invitation_id, &attendance.id)
+                .ok()
+                .flatten()
+        })
+        .and_then(|answer| answer.as_i64());
+    match answer {
+        Some(block::ATTENDANCE_YES) => "ACCEPTED",
+        Some(block::ATTENDANCE_MAYBE) => "TENTATIVE",
+        Some(block::ATTENDANCE_NO) => "DECLINED",
+        _ => "NEEDS-ACTION",
+    }
+}
+
+/// A guest's invitation as an event, described in the guest's language and linking to
+/// `invitation_url`. `None` if the party has no date and time yet.
+pub fn invitation_event(
+    conn: &Connection,
+    invitation_id: &str,
+    invitation_url: &str,
+) -> rusqlite::Result<Option<Event>> {
+    let invitation = Invitation::load(conn, invitation_id)?;
+    let party = Party::load(conn, &invitation.party_id)?;
+    let Some(mut event) = Event::for_party(&party, format!("{}@party-hub", invitation_id)) else {
+        return Ok(None);
+    };
+    let guest = conn.query_row(
+        &format!("SELECT {} FROM guests WHERE id = ?1", GUEST_COLUMNS),
+        [&invitation.guest_id],
+        Guest::from_row,
+    )?;
+    event.organizer_name = conn.query_row(
+        "SELECT name FROM authors WHERE id = ?1",
+        [&party.author],
+        |row| row.get(0),
+    )?;
+
+    let language = guest.notification_language(&party.language);
+    event.description = format!(
+        "{} {}",
+        i18n::text(language, "notification-invitation-link"),
+        invitation_url
+    );
+    event.url = Some(invitation_url.to_string());
+    event.reminder = true;
+    if !guest.email.is_empty() {
+        event.attendee = Some(Attendee {
+            name: format!("{} {}", guest.first, guest.last).trim().to_string(),
+            email: guest.email.clone(),
+            status: participation_status(conn, &party, invitation_id),
+        });
+    }
+    Ok(Some(event))
+}
+
+/// The address a feed is served at. Calendar apps subscribe to it with `webcal://`.
+pub fn feed_url(token: &str) -> String {
+    format!("{}/invitation/feed/{}.ics", templating::base_url(), token)
+}
+
+/// The message describing a participation status, e.g. "Your answer: yes"
+fn status_text(language: &str, status: &str) -> &'static str {
+    match status {
+        "ACCEPTED" => i18n::text(language, "calendar-answer-yes"),
+        "TENTATIVE" => i18n::text(language, "calendar-answer-maybe"),
+        "DECLINED" => i18n::text(language, "calendar-answer-no"),
+        _ => i18n::text(language, "calendar-answer-none"),
+    }
+}
+
+/// All parties a guest is invited to, each as the event of their invitation (so it is the same
+/// calendar entry as a downloaded file) with the guest's answer at the top of the description
+pub fn guest_feed(conn: &Connection, guest_id: &str) -> rusqlite::Result<Calendar> {
+    let guest = conn.query_row(
+        &format!("SELECT {} FROM guests WHERE id = ?1", GUEST_COLUMNS),
+        [guest_id],
+        Guest::from_row,
+    )?;
+    let invitations: Vec<(String, String)> = conn
+        .prepare("SELECT id, party_id FROM invitations WHERE guest_id = ?1")?
+        .query_map([guest_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut events = Vec::new();
+    for (invitation_id, party_id) in invitations {
+        let url = templating::invitation_url(&invitation_id);
+        let Some(mut event) = invitation_event(conn, &invitation_id, &url)? else {
+            continue;
+        };
+        let party = Party::load(conn, &party_id)?;
+        let language = guest.notification_language(&party.language);
+        let status = participation_status(conn, &party, &invitation_id);
+        event.description = format!("{}\n\n{}", status_text(language, status), event.description);
+        events.push(event);
+    }
+
+    let mut variables = Variables::default();
+    variables.set_guest(&guest.salutation, &guest.first, &guest.last);
+    Ok(Calendar {
+        method: Method::Publish,
+        name: Some(i18n::text_with(
+            guest.notification_language(""),
+            "calendar-guest-feed-name",
+            &variables,
+        )),
+        events,
+    })
+}
+
+/// The name of an author and the parties they host
+pub fn hosted_parties(
+    conn: &Connection,
+    author_id: &str,
+) -> rusqlite::Result<(String, Vec<Party>)> {
+    let author_name: String = conn.query_row(
+        "SELECT name FROM authors WHERE id = ?1",
+        [author_id],
+        |row| row.get(0),
+    )?;
+    let parties = conn
+        .prepare(&format!(
+            "SELECT {} FROM parties WHERE author = ?1",
+            PARTY_COLUMNS
+        ))?
+        .query_map([author_id], Party::from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok((author_name, parties))
+}
+
+/// A party as its author sees it, with how many guests gave which answer.
+/// `None` if the party has no date and time yet.
+pub fn hosted_event(
+    conn: &Connection,
+    party: &Party,
+    author_name: &str,
+    language: &str,
+) -> rusqlite::Result<Option<Event>> {
+    let Some(mut event) = Event::for_party(party, format!("{}@party-hub", party.id)) else {
+        return Ok(None);
+    };
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for invitation_id in Party::guest_invitations(conn, &party.id)?.values() {
+        *counts
+            .entry(participation_status(conn, party, invitation_id))
+            .or_default() += 1;
+    }
+    let mut variables = Variables::default();
+    for (name, status) in [
+        ("yes", "ACCEPTED"),
+        ("maybe", "TENTATIVE"),
+        ("no", "DECLINED"),
+        ("none", "NEEDS-ACTION"),
+    ] {
+        variables.set(name, counts.get(status).unwrap_or(&0).to_string());
+    }
+    event.description = i18n::text_with(language, "calendar-author-feed-answers", &variables);
+    event.organizer_name = author_name.to_string();
+    Ok(Some(event))
+}
+
+/// All parties an author hosts (see `hosted_event`)
+pub fn author_feed(
+    conn: &Connection,
+    author_id: &str,
+    language: &str,
+) -> rusqlite::Result<Calendar> {
+    let (author_name, parties) = hosted_parties(conn, author_id)?;
+    let mut events = Vec::new();
+    for party in &parties {
+        events.extend(hosted_event(conn, party, &author_name, language)?);
+    }
+
+    let mut variables = Variables::default();
+    variables.set("author_name", author_name);
+    Ok(Calendar {
+        method: Method::Publish,
+        name: Some(i18n::text_with(
+            language,
+            "calendar-author-feed-name",
+            &variables,
+        )),
+        events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn event(time_zone: Tz) -> Event {
+        let start = NaiveDate::from_ymd_opt(2026, 7, 1)
+            .unwrap()
+            .and_hms_opt(19, 0, 0)
+            .unwrap();
+        Event {
+            uid: "i1@party-hub".to_string(),
+            sequence: 3,
+            start,
+            end: start + Duration::hours(3),
+            time_zone,
+            summary: "Summer party".to_string(),
+            location: String::new(),
+            description: String::new(),
+            url: None,
+            organizer_name: String::new(),
+            attendee: None,
+            reminder: true,
+        }
+    }
+
+    fn render(method: Method, event: Event) -> String {
+        Calendar {
+            method,
+            name: None,
+            events: vec![event],
+        }
+        .render()
+    }
+
+    /// Checks that every line of `folded` fits and that unfolding gives back `line`
+    fn assert_folded(line: &str, folded: &str) {
+        let lines: Vec<&str> = folded.strip_suffix("\r\n").unwrap().split("\r\n").collect();
+        for (index, part) in lines.iter().enumerate() {
+            assert!(part.len() <= MAX_LINE_OCTETS, "{:?} is too long", part);
+            assert_eq!(index > 0, part.starts_with(' '), "{:?}", part);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let short = format!("SUMMARY:{}", "x".repeat(MAX_LINE_OCTETS - 8));
+        assert_eq!(fold(&short), format!("{}\r\n", short));
+
+        let long = format!("DESCRIPTION:{}", "x".repeat(200));
+        assert_folded(&long, &fold(&long));
+        assert_eq!(fold(&long).lines().count(), 3);
+    }
+
+    #[test]
+    fn folds_between_multibyte_characters() {
+        // Two, three and four octets per character, so folds fall next to a character's middle
+        for text in ["ü".repeat(100), "€".repeat(100), "🎉".repeat(100)] {
+            let line = format!("SUMMARY:{}", text);
+            assert_folded(&line, &fold(&line));
+        }
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(
+            escape_text("Drinks, snacks; music\\dancing\r\nBring a friend\nor two"),
+            r"Drinks\, snacks\; music\\dancing\nBring a friend\nor two"
+        );
+        assert_eq!(quote_param("Eve \"E\" Example"), "\"Eve E Example\"");
+    }
+
+    #[test]
+    fn cancellations_are_a_newer_sequence() {
+        let request = render(Method::Request, event(Tz::UTC));
+        assert!(request.contains("METHOD:REQUEST\r\n"));
+        assert!(request.contains("SEQUENCE:3\r\nSTATUS:CONFIRMED\r\n"));
+        assert!(request.contains("BEGIN:VALARM\r\n"));
+
+        let cancel = render(Method::Cancel, event(Tz::UTC));
+        assert!(cancel.contains("METHOD:CANCEL\r\n"));
+        assert!(cancel.contains("SEQUENCE:4\r\nSTATUS:CANCELLED\r\n"));
+        assert!(!cancel.contains("BEGIN:VALARM"));
+    }
+
+    #[test]
+    fn writes_times_in_the_party_zone() {
+        let utc = render(Method::Publish, event(Tz::UTC));
+        assert!(utc.contains("DTSTART:20260701T190000Z\r\nDTEND:20260701T220000Z\r\n"));
+        assert!(!utc.contains("VTIMEZONE"));
+
+        let berlin = render(Method::Publish, event(chrono_tz::Europe::Berlin));
+        assert!(berlin.contains("DTSTART;TZID=Europe/Berlin:20260701T190000\r\n"));
+        assert!(berlin.contains("BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\n"));
+        // Time zones come before the events referring to them
+        assert!(berlin.find("END:VTIMEZONE").unwrap() < berlin.find("BEGIN:VEVENT").unwrap());
+    }
+
+    #[test]
+    fn etag_ignores_the_time_stamp() {
+        let calendar = Calendar {
+            method: Method::Publish,
+            name: Some("Parties".to_string()),
+            events: vec![event(Tz::UTC)],
+        };
+        assert_eq!(calendar.etag(), calendar.clone().etag());
+
+        let mut changed = calendar.clone();
+        changed.events[0].sequence += 1;
+        assert_ne!(calendar.etag(), changed.etag());
+    }
+}
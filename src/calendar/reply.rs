@@ -0,0 +1,905 @@
+use crate::block::{self, answer};
+use crate::db::{GUEST_COLUMNS, Guest, Invitation, Party, answers};
+use crate::invitation::{self, SaveError};
+use crate::notification;
+use actix_web::web;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::Connection;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Calendar apps answer an invitation email (METHOD:REQUEST) with a METHOD:REPLY email to the
+// organizer, `SMTP_FROM`. Mail for that address is delivered into a maildir (`REPLY_MAILDIR`),
+// e.g. by the mail server or fetchmail, and read from there. The event's UID names the
+// invitation and the attendee must be the guest's email address; like the invitation link,
+// knowing the invitation ID is what allows answering. Replies follow the same rules as
+// answers from the invitation page. Guests whose reply couldn't be taken as is are told so,
+// along with an updated invitation that resets the answer in their calendar.
+
+/// How often the maildir is checked for new messages
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The maildir replies are delivered to, if there is one
+pub fn maildir() -> Option<PathBuf> {
+    std::env::var("REPLY_MAILDIR")
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// Reads new messages in the maildir `dir` until the server stops. Handled messages move from
+/// `new` to `cur`, marked as seen, so each one is read once; messages with a reply that couldn't
+/// be saved for now stay in `new`.
+pub async fn watch(dir: PathBuf, db: web::Data<Pool<SqliteConnectionManager>>) {
+    for subdir in ["new", "cur", "tmp"] {
+        if let Err(e) = fs::create_dir_all(dir.join(subdir)) {
+            eprintln!(
+                "[REPLY ERROR] Failed to create {}: {}",
+                dir.join(subdir).display(),
+                e
+            );
+            return;
+        }
+    }
+    let mut handled = HashMap::new();
+    loop {
+        read_new(&dir, &db, &mut handled).await;
+        actix_web::rt::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Reads the messages in `new`. `handled` remembers which events of the messages that stay there
+/// are done, by file name, so only the others are tried again.
+async fn read_new(
+    dir: &Path,
+    db: &web::Data<Pool<SqliteConnectionManager>>,
+    handled: &mut HashMap<String, HashSet<usize>>,
+) {
+    let entries = match fs::read_dir(dir.join("new")) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!(
+                "[REPLY ERROR] Failed to read {}: {}",
+                dir.join("new").display(),
+                e
+            );
+            return;
+        }
+    };
+    // Maildir file names start with the delivery time
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let Some(name) = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+        else {
+            continue;
+        };
+        let message = match fs::read(&path) {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("[REPLY ERROR] Failed to read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let mut done = true;
+        match find_reply(&String::from_utf8_lossy(&message)) {
+            Some(replies) => {
+                let handled = handled.entry(name.clone()).or_default();
+                for (index, reply) in replies.into_iter().enumerate() {
+                    if handled.contains(&index) {
+                        continue;
+                    }
+                    if apply(db, reply).await {
+                        handled.insert(index);
+                    } else {
+                        done = false;
+                    }
+                }
+            }
+            None => println!("INFO: Message {} is no calendar reply, skipping it", name),
+        }
+        // Replies that couldn't be saved for now stay in `new` and are read again next time
+        if !done {
+            continue;
+        }
+        handled.remove(&name);
+        let seen = dir.join("cur").join(format!("{}:2,S", name));
+        if let Err(e) = fs::rename(&path, &seen) {
+            eprintln!(
+                "[REPLY ERROR] Failed to move {} to {}: {}",
+                path.display(),
+                seen.display(),
+                e
+            );
+        }
+    }
+}
+
+/// An event of a METHOD:REPLY calendar
+#[derive(Debug, Default)]
+struct Reply {
+    uid: String,
+    /// The version of the event the guest answered
+    sequence: i64,
+    /// Email address and PARTSTAT of each attendee
+    attendees: Vec<(String, String)>,
+}
+
+/// What became of a reply
+struct Stored {
+    guest_id: String,
+    /// The catalog key of the reason the answer wasn't taken as is (e.g. the party is full)
+    not_taken: Option<&'static str>,
+    promoted: Vec<(String, String)>,
+}
+
+/// Why a reply wasn't stored
+enum NotStored {
+    /// The reply doesn't answer an invitation, so reading it again won't help
+    Ignored(String),
+    /// The database failed, the reply is worth another try
+    Failed(String),
+}
+
+impl From<rusqlite::Error> for NotStored {
+    fn from(e: rusqlite::Error) -> Self {
+        NotStored::Failed(e.to_string())
+    }
+}
+
+/// Saves a reply and tells the guests concerned. `false` if it should be tried again later.
+async fn apply(db: &web::Data<Pool<SqliteConnectionManager>>, reply: Reply) -> bool {
+    let Some(invitation_id) = reply.uid.strip_suffix("@party-hub") else {
+        eprintln!(
+            "[REPLY ERROR] Ignoring reply to unknown event {}",
+            reply.uid
+        );
+        return true;
+    };
+    let stored = match db.get() {
+        Ok(mut conn) => store(&mut conn, invitation_id, &reply),
+        Err(e) => Err(NotStored::Failed(format!(
+            "database connection failed: {}",
+            e
+        ))),
+    };
+    let stored = match stored {
+        Ok(stored) => stored,
+        Err(NotStored::Ignored(reason)) => {
+            eprintln!(
+                "[REPLY ERROR] Ignoring reply for invitation {}: {}",
+                invitation_id, reason
+            );
+            return true;
+        }
+        Err(NotStored::Failed(e)) => {
+            eprintln!(
+                "[REPLY ERROR] Failed to save reply for invitation {}, trying again later: {}",
+                invitation_id, e
+            );
+            return false;
+        }
+    };
+
+    invitation::notify_promoted(db.clone(), stored.promoted).await;
+    match stored.not_taken {
+        None => println!(
+            "INFO: Saved calendar reply for invitation {}",
+            invitation_id
+        ),
+        Some(reason) => {
+            println!(
+                "INFO: Calendar reply for invitation {} not taken: {}",
+                invitation_id, reason
+            );
+            notification::notify_guests(
+                db.clone(),
+                |language| notification::reply_not_taken_message(language, reason),
+                HashMap::from([(stored.guest_id, invitation_id.to_string())]),
+            )
+            .await;
+        }
+    }
+    true
+}
+
+/// Saves the guest's PARTSTAT as their attendance answer. Their other answers are kept, and
+/// checked like answers from the invitation page, so answers to questions that are hidden now
+/// are dropped and questions that are shown now may need answering first.
+fn store(conn: &mut Connection, invitation_id: &str, reply: &Reply) -> Result<Stored, NotStored> {
+    let invitation = match Invitation::load(conn, invitation_id) {
+        Ok(invitation) => invitation,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(NotStored::Ignored("unknown invitation".to_string()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let party = Party::load(conn, &invitation.party_id)?;
+    // Answers to an older version of the party are outdated (RFC 5546, section 3.2.3)
+    if reply.sequence < party.sequence {
+        return Err(NotStored::Ignored(format!(
+            "it answers version {} of the party, which is at version {} by now",
+            reply.sequence, party.sequence
+        )));
+    }
+    let guest = conn.query_row(
+        &format!("SELECT {} FROM guests WHERE id = ?1", GUEST_COLUMNS),
+        [&invitation.guest_id],
+        Guest::from_row,
+    )?;
+
+    let status = reply
+        .attendees
+        .iter()
+        .find(|(email, _)| !guest.email.is_empty() && email.eq_ignore_ascii_case(&guest.email))
+        .map(|(_, status)| status.to_uppercase())
+        .ok_or_else(|| NotStored::Ignored("the guest is not an attendee".to_string()))?;
+    let answer = match status.as_str() {
+        "ACCEPTED" => block::ATTENDANCE_YES,
+        "TENTATIVE" => block::ATTENDANCE_MAYBE,
+        "DECLINED" => block::ATTENDANCE_NO,
+        _ => {
+            return Err(NotStored::Ignored(format!(
+                "no answer in PARTSTAT {}",
+                status
+            )));
+        }
+    };
+    let attendance_id = block::attendance_block(&party.invitation_blocks)
+        .map(|block| block.id.clone())
+        .ok_or_else(|| NotStored::Ignored("the party doesn't ask for attendance".to_string()))?;
+
+    let mut stored = Stored {
+        guest_id: guest.id.clone(),
+        not_taken: None,
+        promoted: Vec::new(),
+    };
+    if let Err(key) = invitation::check_open(&party) {
+        stored.not_taken = Some(key);
+        return Ok(stored);
+    }
+
+    let mut answers = answers::load(conn, invitation_id)?;
+    if let Value::Object(answers) = &mut answers {
+        answers.insert(attendance_id, json!(answer));
+    } else {
+        answers = json!({ attendance_id: answer });
+    }
+    let Ok(validated_answers) = answer::validate_answers(&party.invitation_blocks, &answers) else {
+        stored.not_taken = Some("error-reply-needs-answers");
+        return Ok(stored);
+    };
+
+    let language = guest.notification_language(&party.language).to_string();
+    match invitation::store_answers(
+        conn,
+        &party,
+        invitation_id,
+        validated_answers,
+        None,
+        &language,
+    ) {
+        Ok(saved) => {
+            if saved.waitlisted.is_some() {
+                stored.not_taken = Some("waitlisted");
+            }
+            stored.promoted = saved.promoted;
+        }
+        Err(SaveError::Refused(key)) => stored.not_taken = Some(key),
+        Err(SaveError::ItemsClaimed(_)) => stored.not_taken = Some("error-items-claimed"),
+        Err(SaveError::Database(e)) => return Err(e.into()),
+    }
+    Ok(stored)
+}
+
+/// The events of the first METHOD:REPLY calendar in an email, if it has one
+fn find_reply(message: &str) -> Option<Vec<Reply>> {
+    let message = message.replace("\r\n", "\n");
+    calendars(&message)
+        .into_iter()
+        .map(|calendar| parse_reply(&calendar))
+        .find(|replies| !replies.is_empty())
+}
+
+/// A MIME entity's headers, keyed by lowercase name, and its body
+fn split_entity(entity: &str) -> (HashMap<String, String>, &str) {
+    let (head, body) = if let Some(body) = entity.strip_prefix('\n') {
+        ("", body)
+    } else {
+        entity.split_once("\n\n").unwrap_or((entity, ""))
+    };
+    let mut headers: HashMap<String, String> = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = &mut current {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((name, value)) = current.take() {
+            headers.insert(name, value);
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            current = Some((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    if let Some((name, value)) = current {
+        headers.insert(name, value);
+    }
+    (headers, body)
+}
+
+/// A parameter of a header such as `Content-Type: multipart/mixed; boundary="abc"`
+fn header_param(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// The parts of a multipart body
+fn multipart_parts(body: &str, boundary: &str) -> Vec<String> {
+    let delimiter = format!("--{}", boundary);
+    let close_delimiter = format!("{}--", delimiter);
+    let mut parts = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    for line in body.lines() {
+        let trimmed = line.trim_end();
+        if trimmed == delimiter || trimmed == close_delimiter {
+            if let Some(lines) = current.take() {
+                parts.push(lines.join("\n"));
+            }
+            if trimmed == close_delimiter {
+                break;
+            }
+            current = Some(Vec::new());
+        } else if let Some(lines) = &mut current {
+            lines.push(line);
+        }
+    }
+    parts
+}
+
+/// Every calendar in an email (or one of its parts), decoded
+fn calendars(entity: &str) -> Vec<String> {
+    let (headers, body) = split_entity(entity);
+    let content_type = headers
+        .get("content-type")
+        .map_or("text/plain", String::as_str);
+    let mime_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    if mime_type.starts_with("multipart/") {
+        let Some(boundary) = header_param(content_type, "boundary") else {
+            return Vec::new();
+        };
+        return multipart_parts(body, &boundary)
+            .iter()
+            .flat_map(|part| calendars(part))
+            .collect();
+    }
+    if mime_type != "text/calendar" && mime_type != "application/ics" {
+        return Vec::new();
+    }
+    let encoding = headers
+        .get("content-transfer-encoding")
+        .map(|encoding| encoding.trim().to_lowercase());
+    let content = match encoding.as_deref() {
+        Some("base64") => {
+            let data: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+            match BASE64.decode(data) {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Err(_) => return Vec::new(),
+            }
+        }
+        Some("quoted-printable") => decode_quoted_printable(body),
+        _ => body.to_string(),
+    };
+    vec![content]
+}
+
+fn decode_quoted_printable(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'=' {
+            decoded.push(bytes[index]);
+            index += 1;
+            continue;
+        }
+        // `=` at the end of a line joins it with the next one
+        if bytes.get(index + 1) == Some(&b'\n') {
+            index += 2;
+            continue;
+        }
+        match bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(b'=');
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// A content line such as `ATTENDEE;PARTSTAT=ACCEPTED:mailto:guest@example.com`
+struct ContentLine<'a> {
+    /// Uppercase, as are the parameter names
+    name: String,
+    params: Vec<(String, String)>,
+    value: &'a str,
+}
+
+fn content_line(line: &str) -> Option<ContentLine<'_>> {
+    let mut in_quotes = false;
+    let mut fields = Vec::new();
+    let mut start = 0;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                fields.push(&line[start..index]);
+                start = index + 1;
+            }
+            ':' if !in_quotes => {
+                fields.push(&line[start..index]);
+                let mut fields = fields.into_iter();
+                let name = fields.next()?.trim().to_uppercase();
+                let params = fields
+                    .filter_map(|param| param.split_once('='))
+                    .map(|(key, value)| {
+                        (
+                            key.trim().to_uppercase(),
+                            value.trim().trim_matches('"').to_string(),
+                        )
+                    })
+                    .collect();
+                return Some(ContentLine {
+                    name,
+                    params,
+                    value: &line[index + 1..],
+                });
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The events of a calendar, if it is a METHOD:REPLY
+fn parse_reply(calendar: &str) -> Vec<Reply> {
+    let unfolded = calendar
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut method = None;
+    let mut replies = Vec::new();
+    let mut current: Option<Reply> = None;
+    for line in unfolded.lines() {
+        let Some(ContentLine {
+            name,
+            params,
+            value,
+        }) = content_line(line)
+        else {
+            continue;
+        };
+        let value = value.trim();
+        match name.as_str() {
+            "METHOD" => method = Some(value.to_uppercase()),
+            "BEGIN" if value.eq_ignore_ascii_case("VEVENT") => current = Some(Reply::default()),
+            "END" if value.eq_ignore_ascii_case("VEVENT") => replies.extend(current.take()),
+            "UID" => {
+                if let Some(reply) = &mut current {
+                    reply.uid = value.to_string();
+                }
+            }
+            "SEQUENCE" => {
+                if let Some(reply) = &mut current {
+                    reply.sequence = value.parse().unwrap_or_default();
+                }
+            }
+            "ATTENDEE" => {
+                if let Some(reply) = &mut current {
+                    let email = value
+                        .get(..7)
+                        .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+                        .map_or(value, |_| &value[7..]);
+                    let status = params
+                        .into_iter()
+                        .find(|(key, _)| key == "PARTSTAT")
+                        .map_or_else(|| "NEEDS-ACTION".to_string(), |(_, status)| status);
+                    reply.attendees.push((email.to_string(), status));
+                }
+            }
+            _ => {}
+        }
+    }
+    if method.as_deref() == Some("REPLY") {
+        replies
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations;
+
+    /// Google Calendar: the reply is base64 in a nested multipart, and attached once more
+    const GOOGLE: &str = "\
+From: Alex Example <alex.example@gmail.com>
+To: party@example.com
+Subject: Accepted: Autumn Party @ Sat Nov 7, 2026 7pm - 12am (CET) (party@example.com)
+MIME-Version: 1.0
+Content-Type: multipart/mixed; boundary=\"000000000000a1b2c3061f4e5d6c\"
+
+--000000000000a1b2c3061f4e5d6c
+Content-Type: multipart/alternative; boundary=\"000000000000a1b2c2061f4e5d6a\"
+
+--000000000000a1b2c2061f4e5d6a
+Content-Type: text/plain; charset=\"UTF-8\"; format=flowed; delsp=yes
+
+Alex Example has accepted this invitation.
+
+--000000000000a1b2c2061f4e5d6a
+Content-Type: text/html; charset=\"UTF-8\"
+
+<p>Alex Example has accepted this invitation.</p>
+
+--000000000000a1b2c2061f4e5d6a
+Content-Type: text/calendar; charset=\"UTF-8\"; method=REPLY
+Content-Transfer-Encoding: base64
+
+QkVHSU46VkNBTEVOREFSDQpQUk9ESUQ6LS8vR29vZ2xlIEluYy8vR29vZ2xlIENhbGVuZGFyIDcw
+LjkwNTQvL0VODQpWRVJTSU9OOjIuMA0KQ0FMU0NBTEU6R1JFR09SSUFODQpNRVRIT0Q6UkVQTFkN
+CkJFR0lOOlZFVkVOVA0KRFRTVEFSVDoyMDI2MTEwN1QxODAwMDBaDQpEVEVORDoyMDI2MTEwN1Qy
+MzAwMDBaDQpEVFNUQU1QOjIwMjYxMDE3VDA5MTUxMloNCk9SR0FOSVpFUjtDTj1QYXJ0eSBIdWI6
+bWFpbHRvOnBhcnR5QGV4YW1wbGUuY29tDQpVSUQ6M2YxYzlkMmUtNWI3YS00YzFlLTlmMGEtMmQ0
+YjZlOGExYzNmQHBhcnR5LWh1Yg0KQVRURU5ERUU7Q1VUWVBFPUlORElWSURVQUw7Uk9MRT1SRVEt
+UEFSVElDSVBBTlQ7UEFSVFNUQVQ9QUNDRVBURUQ7Q049QWxleCBFeA0KIGFtcGxlO1gtTlVNLUdV
+RVNUUz0wOm1haWx0bzphbGV4LmV4YW1wbGVAZ21haWwuY29tDQpDUkVBVEVEOjIwMjYxMDE3VDA5
+MTAwMFoNCkxBU1QtTU9ESUZJRUQ6MjAyNjEwMTdUMDkxNTEyWg0KTE9DQVRJT046R2FyZGVuDQpT
+RVFVRU5DRToyDQpTVEFUVVM6Q09ORklSTUVEDQpTVU1NQVJZOkF1dHVtbiBQYXJ0eQ0KVFJBTlNQ
+Ok9QQVFVRQ0KRU5EOlZFVkVOVA0KRU5EOlZDQUxFTkRBUg0K
+--000000000000a1b2c2061f4e5d6a--
+
+--000000000000a1b2c3061f4e5d6c
+Content-Type: application/ics; name=\"invite.ics\"
+Content-Disposition: attachment; filename=\"invite.ics\"
+Content-Transfer-Encoding: base64
+
+QkVHSU46VkNBTEVOREFSDQpQUk9ESUQ6LS8vR29vZ2xlIEluYy8vR29vZ2xlIENhbGVuZGFyIDcw
+LjkwNTQvL0VODQpWRVJTSU9OOjIuMA0KQ0FMU0NBTEU6R1JFR09SSUFODQpNRVRIT0Q6UkVQTFkN
+CkJFR0lOOlZFVkVOVA0KRFRTVEFSVDoyMDI2MTEwN1QxODAwMDBaDQpEVEVORDoyMDI2MTEwN1Qy
+MzAwMDBaDQpEVFNUQU1QOjIwMjYxMDE3VDA5MTUxMloNCk9SR0FOSVpFUjtDTj1QYXJ0eSBIdWI6
+bWFpbHRvOnBhcnR5QGV4YW1wbGUuY29tDQpVSUQ6M2YxYzlkMmUtNWI3YS00YzFlLTlmMGEtMmQ0
+YjZlOGExYzNmQHBhcnR5LWh1Yg0KQVRURU5ERUU7Q1VUWVBFPUlORElWSURVQUw7Uk9MRT1SRVEt
+UEFSVElDSVBBTlQ7UEFSVFNUQVQ9QUNDRVBURUQ7Q049QWxleCBFeA0KIGFtcGxlO1gtTlVNLUdV
+RVNUUz0wOm1haWx0bzphbGV4LmV4YW1wbGVAZ21haWwuY29tDQpDUkVBVEVEOjIwMjYxMDE3VDA5
+MTAwMFoNCkxBU1QtTU9ESUZJRUQ6MjAyNjEwMTdUMDkxNTEyWg0KTE9DQVRJT046R2FyZGVuDQpT
+RVFVRU5DRToyDQpTVEFUVVM6Q09ORklSTUVEDQpTVU1NQVJZOkF1dHVtbiBQYXJ0eQ0KVFJBTlNQ
+Ok9QQVFVRQ0KRU5EOlZFVkVOVA0KRU5EOlZDQUxFTkRBUg0K
+--000000000000a1b2c3061f4e5d6c--
+";
+
+    /// Apple Calendar: quoted-printable with soft line breaks and a quoted boundary with `=`
+    const APPLE: &str = "\
+From: Bo =?utf-8?Q?Kr=C3=BCger?= <bo@example.net>
+Content-Type: multipart/alternative;
+\tboundary=\"Apple-Mail=_5C1E7A2B-9D4F-4E3A-8B6C-0F2D1E3C4B5A\"
+Mime-Version: 1.0 (1.0)
+Subject: Invitation: Autumn Party (Maybe)
+To: party@example.com
+
+--Apple-Mail=_5C1E7A2B-9D4F-4E3A-8B6C-0F2D1E3C4B5A
+Content-Transfer-Encoding: 7bit
+Content-Type: text/plain;
+\tcharset=us-ascii
+
+Bo Kr\u{fc}ger has tentatively accepted your invitation.
+--Apple-Mail=_5C1E7A2B-9D4F-4E3A-8B6C-0F2D1E3C4B5A
+Content-Transfer-Encoding: quoted-printable
+Content-Type: text/calendar;
+\tcharset=utf-8;
+\tmethod=REPLY
+
+BEGIN:VCALENDAR
+METHOD:REPLY
+PRODID:-//Apple Inc.//iPhone OS 18.0//EN
+VERSION:2.0
+BEGIN:VEVENT
+ATTENDEE;CN=3D\"Bo Kr=C3=BCger\";EMAIL=3Dbo@example.net;PARTSTAT=3DTENTATIVE:=
+mailto:bo@example.net
+DTSTAMP:20261017T120501Z
+DTSTART:20261107T180000Z
+SEQUENCE:3
+SUMMARY:Autumn Party
+UID:c2b8e0f4-1d3a-4b6c-8e7f-0a9b1c2d3e4f@party-hub
+END:VEVENT
+END:VCALENDAR
+
+--Apple-Mail=_5C1E7A2B-9D4F-4E3A-8B6C-0F2D1E3C4B5A--
+";
+
+    /// Outlook: a quoted CN containing `;` and `:`, and an uppercase `MAILTO:`
+    const OUTLOOK: &str = "\
+From: \"Doe, Jane\" <Jane.Doe@Example.org>
+To: Party Hub <party@example.com>
+Subject: Declined: Autumn Party
+Content-Type: multipart/alternative;
+\tboundary=\"_000_AM0PR07MB6114E2D1B0C8A7F4A9E3B1D2AM0PR07MB6114eurp_\"
+MIME-Version: 1.0
+
+--_000_AM0PR07MB6114E2D1B0C8A7F4A9E3B1D2AM0PR07MB6114eurp_
+Content-Type: text/plain; charset=\"iso-8859-1\"
+Content-Transfer-Encoding: quoted-printable
+
+Leider verhindert, viel Spa=DF!
+
+--_000_AM0PR07MB6114E2D1B0C8A7F4A9E3B1D2AM0PR07MB6114eurp_
+Content-Type: text/calendar; charset=\"utf-8\"; method=REPLY
+Content-Transfer-Encoding: base64
+
+QkVHSU46VkNBTEVOREFSDQpNRVRIT0Q6UkVQTFkNClBST0RJRDpNaWNyb3NvZnQgRXhjaGFuZ2Ug
+U2VydmVyIDIwMTANClZFUlNJT046Mi4wDQpCRUdJTjpWRVZFTlQNCkFUVEVOREVFO1BBUlRTVEFU
+PURFQ0xJTkVEO0NOPSJEb2U7IEphbmU6IFNhbGVzIjpNQUlMVE86SmFuZS5Eb2VARXhhbXBsZS5v
+cmcNCkNPTU1FTlQ7TEFOR1VBR0U9ZGUtREU6TGVpZGVyIHZlcmhpbmRlcnRcLCB2aWVsIFNwYcOf
+IQ0KU1VNTUFSWTtMQU5HVUFHRT1kZS1ERTpBYmdlbGVobnQ6IEF1dHVtbiBQYXJ0eQ0KRFRTVEFS
+VDoyMDI2MTEwN1QxODAwMDBaDQpEVEVORDoyMDI2MTEwN1QyMzAwMDBaDQpVSUQ6N2EwZTRkNTUt
+MGMxYi00ZThmLWE2ZDItOTFjM2I1ZjdlMjA0QHBhcnR5LWh1Yg0KQ0xBU1M6UFVCTElDDQpQUklP
+UklUWTo1DQpEVFNUQU1QOjIwMjYxMDE3VDEwMjIzM1oNClRSQU5TUDpPUEFRVUUNClNUQVRVUzpD
+T05GSVJNRUQNClNFUVVFTkNFOjENCkxPQ0FUSU9OOkdhcmRlbg0KRU5EOlZFVkVOVA0KRU5EOlZD
+QUxFTkRBUg0K
+
+--_000_AM0PR07MB6114E2D1B0C8A7F4A9E3B1D2AM0PR07MB6114eurp_--
+";
+
+    /// The only reply in a message as it arrives, with CRLF line endings
+    fn only_reply(message: &str) -> Reply {
+        let mut replies = find_reply(&message.replace('\n', "\r\n")).unwrap();
+        assert_eq!(replies.len(), 1);
+        replies.remove(0)
+    }
+
+    fn attendees(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(email, status)| (email.to_string(), status.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_google_replies() {
+        let reply = only_reply(GOOGLE);
+        assert_eq!(reply.uid, "3f1c9d2e-5b7a-4c1e-9f0a-2d4b6e8a1c3f@party-hub");
+        assert_eq!(reply.sequence, 2);
+        // The ATTENDEE line is folded in the middle of the CN
+        assert_eq!(
+            reply.attendees,
+            attendees(&[("alex.example@gmail.com", "ACCEPTED")])
+        );
+    }
+
+    #[test]
+    fn reads_apple_replies() {
+        let reply = only_reply(APPLE);
+        assert_eq!(reply.uid, "c2b8e0f4-1d3a-4b6c-8e7f-0a9b1c2d3e4f@party-hub");
+        assert_eq!(reply.sequence, 3);
+        assert_eq!(
+            reply.attendees,
+            attendees(&[("bo@example.net", "TENTATIVE")])
+        );
+    }
+
+    #[test]
+    fn reads_outlook_replies() {
+        let reply = only_reply(OUTLOOK);
+        assert_eq!(reply.uid, "7a0e4d55-0c1b-4e8f-a6d2-91c3b5f7e204@party-hub");
+        assert_eq!(reply.sequence, 1);
+        assert_eq!(
+            reply.attendees,
+            attendees(&[("Jane.Doe@Example.org", "DECLINED")])
+        );
+    }
+
+    #[test]
+    fn ignores_messages_without_replies() {
+        // Plain text isn't read as a calendar, even if it looks like one
+        let calendar = "BEGIN:VCALENDAR\nMETHOD:REQUEST\nBEGIN:VEVENT\nUID:x@party-hub\nEND:VEVENT\nEND:VCALENDAR\n";
+        let reply = calendar.replace("REQUEST", "REPLY");
+        assert!(find_reply(&format!("Subject: Hello\n\n{}", reply)).is_none());
+        // Invitations forwarded back to the organizer aren't replies
+        let request = format!(
+            "Content-Type: text/calendar; method=REQUEST\n\n{}",
+            calendar
+        );
+        assert!(find_reply(&request).is_none());
+        assert!(parse_reply(calendar).is_empty());
+        // Events without SEQUENCE are the first version
+        let replies = parse_reply(&reply);
+        assert_eq!(replies[0].sequence, 0);
+        assert!(replies[0].attendees.is_empty());
+    }
+
+    #[test]
+    fn splits_entities() {
+        let (headers, body) = split_entity(
+            "Content-Type: multipart/mixed;\n\tboundary=\"b\"\nSubject: Hi\n\nBody\n\nMore",
+        );
+        assert_eq!(headers["content-type"], "multipart/mixed; boundary=\"b\"");
+        assert_eq!(headers["subject"], "Hi");
+        assert_eq!(body, "Body\n\nMore");
+        assert_eq!(
+            header_param(&headers["content-type"], "Boundary").as_deref(),
+            Some("b")
+        );
+
+        // A part without headers starts with the empty line
+        let (headers, body) = split_entity("\nJust text");
+        assert!(headers.is_empty());
+        assert_eq!(body, "Just text");
+    }
+
+    #[test]
+    fn splits_multipart_bodies() {
+        let body = "Preamble\n--b\nContent-Type: text/plain\n\nOne\n--b \nTwo\n--b--\nEpilogue\n--b\nThree";
+        assert_eq!(
+            multipart_parts(body, "b"),
+            ["Content-Type: text/plain\n\nOne", "Two"]
+        );
+        // Without a closing delimiter, the parts end where the body does
+        assert_eq!(multipart_parts("--b\nOne\n--b\nTwo", "b"), ["One"]);
+    }
+
+    #[test]
+    fn decodes_quoted_printable() {
+        assert_eq!(decode_quoted_printable("Spa=C3=9F =3D fun"), "Spaß = fun");
+        assert_eq!(decode_quoted_printable("joined=\nline"), "joinedline");
+        // Broken escapes are kept as they are
+        assert_eq!(decode_quoted_printable("a=ZZ b="), "a=ZZ b=");
+    }
+
+    #[test]
+    fn splits_content_lines() {
+        let line = content_line(
+            "attendee;partstat=Accepted;CN=\"Doe; Jane: Sales\":mailto:jane@example.org",
+        )
+        .unwrap();
+        assert_eq!(line.name, "ATTENDEE");
+        assert_eq!(
+            line.params,
+            [
+                ("PARTSTAT".to_string(), "Accepted".to_string()),
+                ("CN".to_string(), "Doe; Jane: Sales".to_string())
+            ]
+        );
+        assert_eq!(line.value, "mailto:jane@example.org");
+
+        // Only the first unquoted `:` ends the name and parameters
+        let line = content_line("DESCRIPTION:Starts at 19:00; bring food").unwrap();
+        assert!(line.params.is_empty());
+        assert_eq!(line.value, "Starts at 19:00; bring food");
+        assert!(content_line("no value here").is_none());
+    }
+
+    /// An invitation `i` to party `p` at version 2, whose guest answered "yes"
+    fn setup() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::run_pending(&mut conn).unwrap();
+        let blocks = r#"[{"id":"rsvp","type":"attendance","label":"Coming?","options":["Yes","Maybe","No"]}]"#;
+        conn.execute(
+            "INSERT INTO authors (id, name, author_secret) VALUES ('a', 'Author', 's')",
+            (),
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO parties (id, name, author, invitation_blocks, has_rsvp_block, sequence) VALUES ('p', 'Party', 'a', ?1, 1, 2)",
+            [blocks],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO guests (id, first, email, author) VALUES ('g', 'Jane', 'jane.doe@example.org', 'a')",
+            (),
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO invitations (id, guest_id, party_id) VALUES ('i', 'g', 'p')",
+            (),
+        )
+        .unwrap();
+        answers::set_one(&conn, "i", "rsvp", &json!(block::ATTENDANCE_YES)).unwrap();
+        conn
+    }
+
+    fn reply(sequence: i64, attendees: &[(&str, &str)]) -> Reply {
+        Reply {
+            uid: "i@party-hub".to_string(),
+            sequence,
+            attendees: self::attendees(attendees),
+        }
+    }
+
+    #[test]
+    fn stores_the_guests_answer() {
+        let mut conn = setup();
+        let stored = store(
+            &mut conn,
+            "i",
+            &reply(2, &[("Jane.Doe@Example.org", "declined")]),
+        )
+        .unwrap_or_else(|_| panic!("reply wasn't stored"));
+        assert_eq!(stored.guest_id, "g");
+        assert_eq!(stored.not_taken, None);
+        assert_eq!(
+            answers::load(&conn, "i").unwrap()["rsvp"],
+            json!(block::ATTENDANCE_NO)
+        );
+    }
+
+    #[test]
+    fn ignores_outdated_and_foreign_replies() {
+        let mut conn = setup();
+        // An answer to an older version of the party
+        let outdated = store(
+            &mut conn,
+            "i",
+            &reply(1, &[("jane.doe@example.org", "DECLINED")]),
+        );
+        assert!(matches!(outdated, Err(NotStored::Ignored(_))));
+        // Someone else answering, or no answer at all
+        let foreign = store(
+            &mut conn,
+            "i",
+            &reply(2, &[("eve@example.org", "DECLINED")]),
+        );
+        assert!(matches!(foreign, Err(NotStored::Ignored(_))));
+        let no_answer = store(
+            &mut conn,
+            "i",
+            &reply(2, &[("jane.doe@example.org", "NEEDS-ACTION")]),
+        );
+        assert!(matches!(no_answer, Err(NotStored::Ignored(_))));
+        let unknown = store(
+            &mut conn,
+            "x",
+            &reply(2, &[("jane.doe@example.org", "DECLINED")]),
+        );
+        assert!(matches!(unknown, Err(NotStored::Ignored(_))));
+        assert_eq!(
+            answers::load(&conn, "i").unwrap()["rsvp"],
+            json!(block::ATTENDANCE_YES)
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::block;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+// Answers are stored one row per (invitation, block) with the value JSON-encoded,
+// so an attendance "yes" is the text `0` and a text answer is a quoted string.
+
+/// All answers of one invitation as a JSON object keyed by block ID
+pub fn load(conn: &Connection, invitation_id: &str) -> rusqlite::Result<Value> {
+    let mut stmt = conn.prepare("SELECT block_id, value FROM answers WHERE invitation_id = ?1")?;
+    let rows = stmt.query_map([invitation_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut answers = Map::new();
+    for (block_id, value) in rows.flatten() {
+        if let Ok(value) = serde_json::from_str(&value) {
+            answers.insert(block_id, value);
+        }
+    }
+    Ok(Value::Object(answers))
+}
+
+/// Answers of every invitation of a party, keyed by invitation ID.
+/// Invitations without any answer are not included.
+pub fn load_for_party(conn: &Connection, party_id: &str) -> rusqlite::Result<HashMap<String, Value>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.invitation_id, a.block_id, a.value FROM answers a INNER JOIN invitations i ON a.invitation_id = i.id WHERE i.party_id = ?1",
+    )?;
+    let rows = stmt.query_map([party_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+
+    let mut answers: HashMap<String, Value> = HashMap::new();
+    for (invitation_id, block_id, value) in rows.flatten() {
+        if let Ok(value) = serde_json::from_str(&value)
+            && let Value::Object(map) = answers
+                .entry(invitation_id)
+                .or_insert_with(|| Value::Object(Map::new()))
+        {
+            map.insert(block_id, value);
+        }
+    }
+    Ok(answers)
+}
+
+/// The answer an invitation gave to a single block
+pub fn load_one(conn: &Connection, invitation_id: &str, block_id: &str) -> rusqlite::Result<Option<Value>> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM answers WHERE invitation_id = ?1 AND block_id = ?2",
+            [invitation_id, block_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value.and_then(|v| serde_json::from_str(&v).ok()))
+}
+
+/// Number of people attending a party: every invitation that answered "yes" to the
+/// attendance block plus its companions, optionally leaving one invitation out
+pub fn headcount(
+    conn: &Connection,
+    party_id: &str,
+    attendance_block_id: &str,
+    excluding_invitation: Option<&str>,
+) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(1 + i.companions), 0) FROM answers a INNER JOIN invitations i ON a.invitation_id = i.id WHERE i.party_id = ?1 AND a.block_id = ?2 AND a.value = ?3 AND a.invitation_id != COALESCE(?4, '')",
+        params![
+            party_id,
+            attendance_block_id,
+            block::ATTENDANCE_YES.to_string(),
+            excluding_invitation
+        ],
+        |row| row.get(0),
+    )
+}
+
+/// How many of each item of a sign-up block are claimed, indexed like the block's items,
+/// optionally leaving one invitation out
+pub fn claimed_items(
+    conn: &Connection,
+    party_id: &str,
+    block_id: &str,
+    item_count: usize,
+    excluding_invitation: Option<&str>,
+) -> rusqlite::Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT CAST(j.key AS INTEGER), SUM(j.value) FROM answers a INNER JOIN invitations i ON a.invitation_id = i.id, json_each(a.value) j WHERE i.party_id = ?1 AND a.block_id = ?2 AND a.invitation_id != COALESCE(?3, '') AND j.type = 'integer' GROUP BY j.key",
+    )?;
+    let rows = stmt.query_map(params![party_id, block_id, excluding_invitation], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+    })?;
+
+    let mut claimed = vec![0; item_count];
+    for (index, quantity) in rows.flatten() {
+        if let Some(total) = usize::try_from(index).ok().and_then(|index| claimed.get_mut(index)) {
+            *total += quantity;
+        }
+    }
+    Ok(claimed)
+}
+
+/// Replaces all answers of an invitation. Unchanged answers keep their `updated_at`.
+/// Should run inside a transaction so a failed write doesn't leave half the answers behind.
+pub fn replace(conn: &Connection, invitation_id: &str, answers: &Map<String, Value>) -> rusqlite::Result<()> {
+    let block_ids: Vec<&str> = answers.keys().map(String::as_str).collect();
+    let block_ids_json = serde_json::to_string(&block_ids).unwrap_or_else(|_| "[]".to_string());
+
+    conn.execute(
+        "DELETE FROM answers WHERE invitation_id = ?1 AND block_id NOT IN (SELECT value FROM json_each(?2))",
+        params![invitation_id, block_ids_json],
+    )?;
+
+    for (block_id, value) in answers {
+        set_one(conn, invitation_id, block_id, value)?;
+    }
+    Ok(())
+}
+
+/// Stores a single answer, keeping `updated_at` if the value didn't change
+pub fn set_one(conn: &Connection, invitation_id: &str, block_id: &str, value: &Value) -> rusqlite::Result<()> {
+    conn.prepare_cached(
+        "INSERT INTO answers (invitation_id, block_id, value) VALUES (?1, ?2, ?3)
+         ON CONFLICT (invitation_id, block_id) DO UPDATE SET
+            value = excluded.value,
+            updated_at = CASE WHEN answers.value = excluded.value THEN answers.updated_at ELSE excluded.updated_at END",
+    )?
+    .execute(params![invitation_id, block_id, value.to_string()])?;
+    Ok(())
+}
+
+/// Removes the answers of all invitations of a party
+pub fn delete_for_party(conn: &Connection, party_id: &str) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM answers WHERE invitation_id IN (SELECT id FROM invitations WHERE party_id = ?1)",
+        [party_id],
+    )
+}
+
+/// Removes the answers of all invitations of a guest, optionally only for one party
+pub fn delete_for_guest(conn: &Connection, guest_id: &str, party_id: Option<&str>) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM answers WHERE invitation_id IN (SELECT id FROM invitations WHERE guest_id = ?1 AND (?2 IS NULL OR party_id = ?2))",
+        params![guest_id, party_id],
+    )
+}
@@ -0,0 +1,83 @@
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::Serialize;
+
+// Attachments belong to the author who uploaded them, not to a party, so cloned parties,
+// templates and series occurrences can show the same image. Guests may fetch an attachment
+// only through a party whose blocks reference it.
+
+/// An uploaded image or file
+#[derive(Debug, Serialize)]
+pub struct Attachment {
+    pub id: String,
+    pub author: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64, // In bytes
+    pub has_thumbnail: bool,
+    pub created_at: String,
+}
+
+impl Attachment {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Attachment {
+            id: row.get("id")?,
+            author: row.get("author")?,
+            file_name: row.get("file_name")?,
+            content_type: row.get("content_type")?,
+            size: row.get("size")?,
+            has_thumbnail: row.get("has_thumbnail")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/")
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "file_name": self.file_name,
+            "content_type": self.content_type,
+            "size": self.size,
+            "has_thumbnail": self.has_thumbnail
+        })
+    }
+}
+
+pub fn insert(conn: &Connection, attachment: &Attachment) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO attachments (id, author, file_name, content_type, size, has_thumbnail, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            attachment.id,
+            attachment.author,
+            attachment.file_name,
+            attachment.content_type,
+            attachment.size,
+            attachment.has_thumbnail,
+            attachment.created_at
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn load(conn: &Connection, attachment_id: &str) -> rusqlite::Result<Option<Attachment>> {
+    conn.query_row(
+        "SELECT id, author, file_name, content_type, size, has_thumbnail, created_at
+         FROM attachments WHERE id = ?1",
+        [attachment_id],
+        Attachment::from_row,
+    )
+    .optional()
+}
+
+/// An author's uploads, newest first
+pub fn list_for_author(conn: &Connection, author_id: &str) -> rusqlite::Result<Vec<Attachment>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, author, file_name, content_type, size, has_thumbnail, created_at
+         FROM attachments WHERE author = ?1 ORDER BY created_at DESC",
+    )?;
+    let attachments = stmt.query_map([author_id], Attachment::from_row)?;
+    attachments.collect()
+}
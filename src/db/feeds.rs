@@ -0,0 +1,81 @@
+use rusqlite::{Connection, OptionalExtension};
+use uuid::Uuid;
+
+// Every guest and author can have a calendar feed, served under a secret token. The token is
+// created when the feed link is first shown, and replacing it revokes the old link.
+
+/// Whose parties a feed lists
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeedOwner {
+    /// The parties the guest is invited to
+    Guest(String),
+    /// The parties the author hosts
+    Author(String),
+}
+
+impl FeedOwner {
+    fn column(&self) -> &'static str {
+        match self {
+            FeedOwner::Guest(_) => "guest_id",
+            FeedOwner::Author(_) => "author_id",
+        }
+    }
+
+    fn id(&self) -> &str {
+        match self {
+            FeedOwner::Guest(id) | FeedOwner::Author(id) => id,
+        }
+    }
+}
+
+/// The token of the owner's feed, created if there is none yet
+pub fn token(conn: &Connection, owner: &FeedOwner) -> rusqlite::Result<String> {
+    let existing = conn
+        .query_row(
+            &format!("SELECT token FROM calendar_feeds WHERE {} = ?1", owner.column()),
+            [owner.id()],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match existing {
+        Some(token) => Ok(token),
+        None => reset(conn, owner),
+    }
+}
+
+/// Gives the owner's feed a new token, so links to the old one stop working
+pub fn reset(conn: &Connection, owner: &FeedOwner) -> rusqlite::Result<String> {
+    let token = Uuid::new_v4().to_string();
+    conn.execute(
+        &format!("DELETE FROM calendar_feeds WHERE {} = ?1", owner.column()),
+        [owner.id()],
+    )?;
+    conn.execute(
+        &format!(
+            "INSERT INTO calendar_feeds (token, {}) VALUES (?1, ?2)",
+            owner.column()
+        ),
+        [&token, owner.id()],
+    )?;
+    Ok(token)
+}
+
+pub fn owner(conn: &Connection, token: &str) -> rusqlite::Result<Option<FeedOwner>> {
+    conn.query_row(
+        "SELECT guest_id, author_id FROM calendar_feeds WHERE token = ?1",
+        [token],
+        |row| {
+            let guest_id: Option<String> = row.get(0)?;
+            let author_id: Option<String> = row.get(1)?;
+            Ok(guest_id.map(FeedOwner::Guest).or(author_id.map(FeedOwner::Author)))
+        },
+    )
+    .optional()
+    .map(Option::flatten)
+}
+
+/// Removes a deleted guest's feed
+pub fn delete_guest(conn: &Connection, guest_id: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM calendar_feeds WHERE guest_id = ?1", [guest_id])?;
+    Ok(())
+}
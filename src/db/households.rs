@@ -0,0 +1,169 @@
+use super::{GUEST_COLUMNS, Guest, Household};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use uuid::Uuid;
+
+// Members of a household keep their own invitations (and answers) for every party.
+// A household invitation is just the shared link that opens all of them at once.
+
+/// A household member's invitation to a party, as shown on the shared invitation page
+#[derive(Debug, Serialize)]
+pub struct MemberInvitation {
+    pub invitation_id: String,
+    pub guest_id: String,
+    pub name: String,
+}
+
+/// The shared invitation link an invitation is part of
+#[derive(Debug)]
+pub struct HouseholdLink {
+    pub id: String,
+    pub household_id: String,
+    pub household_name: String,
+    pub party_id: String,
+}
+
+pub fn load(conn: &Connection, household_id: &str, author_id: &str) -> rusqlite::Result<Household> {
+    conn.query_row(
+        "SELECT id, name, author FROM households WHERE id = ?1 AND author = ?2",
+        [household_id, author_id],
+        Household::from_row,
+    )
+}
+
+/// Guests belonging to a household, ordered by name
+pub fn members(conn: &Connection, household_id: &str) -> rusqlite::Result<Vec<Guest>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM guests WHERE household_id = ?1 ORDER BY first, last",
+        GUEST_COLUMNS
+    ))?;
+    let members = stmt.query_map([household_id], Guest::from_row)?;
+    members.collect()
+}
+
+/// Makes `guest_ids` the members of a household. Guests can only be in one household,
+/// so members are moved over from their previous one. Guests of other authors are ignored.
+pub fn set_members(
+    conn: &Connection,
+    household_id: &str,
+    author_id: &str,
+    guest_ids: &[String],
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE guests SET household_id = NULL WHERE household_id = ?1",
+        [household_id],
+    )?;
+    let mut stmt =
+        conn.prepare("UPDATE guests SET household_id = ?1 WHERE id = ?2 AND author = ?3")?;
+    for guest_id in guest_ids {
+        stmt.execute([household_id, guest_id, author_id])?;
+    }
+    Ok(())
+}
+
+/// Removes a household; its members stay as individual guests
+pub fn delete(conn: &Connection, household_id: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE guests SET household_id = NULL WHERE household_id = ?1",
+        [household_id],
+    )?;
+    conn.execute(
+        "DELETE FROM household_invitations WHERE household_id = ?1",
+        [household_id],
+    )?;
+    conn.execute("DELETE FROM households WHERE id = ?1", [household_id])?;
+    Ok(())
+}
+
+/// ID of the shared invitation link of a household for a party, created on first use
+pub fn link_for(conn: &Connection, household_id: &str, party_id: &str) -> rusqlite::Result<String> {
+    conn.execute(
+        "INSERT OR IGNORE INTO household_invitations (id, household_id, party_id) VALUES (?1, ?2, ?3)",
+        [&Uuid::new_v4().to_string(), household_id, party_id],
+    )?;
+    conn.query_row(
+        "SELECT id FROM household_invitations WHERE household_id = ?1 AND party_id = ?2",
+        [household_id, party_id],
+        |row| row.get(0),
+    )
+}
+
+/// Looks up a shared invitation link by its ID
+pub fn resolve_link(conn: &Connection, link_id: &str) -> rusqlite::Result<Option<HouseholdLink>> {
+    conn.query_row(
+        "SELECT hi.id, hi.household_id, h.name, hi.party_id FROM household_invitations hi
+         INNER JOIN households h ON hi.household_id = h.id WHERE hi.id = ?1",
+        [link_id],
+        link_from_row,
+    )
+    .optional()
+}
+
+/// The shared invitation link an invitation belongs to, if its guest's household has one for the party
+pub fn link_for_invitation(
+    conn: &Connection,
+    invitation_id: &str,
+) -> rusqlite::Result<Option<HouseholdLink>> {
+    conn.query_row(
+        "SELECT hi.id, hi.household_id, h.name, hi.party_id FROM invitations i
+         INNER JOIN guests g ON i.guest_id = g.id
+         INNER JOIN household_invitations hi ON hi.household_id = g.household_id AND hi.party_id = i.party_id
+         INNER JOIN households h ON hi.household_id = h.id
+         WHERE i.id = ?1",
+        [invitation_id],
+        link_from_row,
+    )
+    .optional()
+}
+
+/// Invitations of the household's members to a party, ordered by name
+pub fn member_invitations(
+    conn: &Connection,
+    household_id: &str,
+    party_id: &str,
+) -> rusqlite::Result<Vec<MemberInvitation>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, g.id, g.first, g.last FROM invitations i
+         INNER JOIN guests g ON i.guest_id = g.id
+         WHERE g.household_id = ?1 AND i.party_id = ?2 ORDER BY g.first, g.last",
+    )?;
+    let rows = stmt.query_map([household_id, party_id], |row| {
+        let first: String = row.get(2)?;
+        let last: String = row.get(3)?;
+        Ok(MemberInvitation {
+            invitation_id: row.get(0)?,
+            guest_id: row.get(1)?,
+            name: format!("{} {}", first, last).trim().to_string(),
+        })
+    })?;
+    rows.collect()
+}
+
+/// The guest that receives emails for a group of household members:
+/// the first one by name who has an email address
+pub fn email_contact(conn: &Connection, guest_ids: &[String]) -> rusqlite::Result<Option<String>> {
+    let guest_ids_json = serde_json::to_string(guest_ids).unwrap_or_else(|_| "[]".to_string());
+    conn.query_row(
+        "SELECT id FROM guests WHERE id IN (SELECT value FROM json_each(?1)) AND email != '' ORDER BY first, last LIMIT 1",
+        params![guest_ids_json],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+pub fn delete_links_for_party(conn: &Connection, party_id: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM household_invitations WHERE party_id = ?1",
+        [party_id],
+    )?;
+    Ok(())
+}
+
+fn link_from_row(row: &rusqlite::Row) -> rusqlite::Result<HouseholdLink> {
+    Ok(HouseholdLink {
+        id: row.get(0)?,
+        household_id: row.get(1)?,
+        household_name: row.get(2)?,
+        party_id: row.get(3)?,
+    })
+}
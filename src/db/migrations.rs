@@ -0,0 +1,648 @@
+use rusqlite::{Connection, Transaction};
+
+/// Upgrade script for databases created by Party Hub 0.1.x (before the schema was versioned)
+const LEGACY_0_1_TO_0_2: &str = include_str!("migrations/legacy_0.1_to_0.2.sql");
+
+/// A single schema change. Migrations are applied in ascending `version` order and
+/// each one runs inside its own transaction together with its `schema_migrations` record.
+struct Migration {
+    version: i64,
+    name: &'static str,
+    up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// All known migrations, in order. Never edit or reorder an entry that has been released;
+/// add a new one with the next version number instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        up: initial_schema,
+    },
+    Migration {
+        version: 2,
+        name: "answers_table",
+        up: answers_table,
+    },
+    Migration {
+        version: 3,
+        name: "waitlist",
+        up: waitlist,
+    },
+    Migration {
+        version: 4,
+        name: "companions",
+        up: companions,
+    },
+    Migration {
+        version: 5,
+        name: "households",
+        up: households,
+    },
+    Migration {
+        version: 6,
+        name: "block_templates",
+        up: block_templates,
+    },
+    Migration {
+        version: 7,
+        name: "party_series",
+        up: party_series,
+    },
+    Migration {
+        version: 8,
+        name: "attachments",
+        up: attachments,
+    },
+    Migration {
+        version: 9,
+        name: "guest_language",
+        up: guest_language,
+    },
+    Migration {
+        version: 10,
+        name: "party_language",
+        up: party_language,
+    },
+    Migration {
+        version: 11,
+        name: "party_time_zone",
+        up: party_time_zone,
+    },
+    Migration {
+        version: 12,
+        name: "party_sequence",
+        up: party_sequence,
+    },
+    Migration {
+        version: 13,
+        name: "calendar_feeds",
+        up: calendar_feeds,
+    },
+];
+
+/// Highest schema version this binary knows how to handle
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Returns the highest applied schema version (0 for a fresh or unversioned database)
+pub fn current_version(conn: &Connection) -> rusqlite::Result<i64> {
+    ensure_migrations_table(conn)?;
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+        (),
+        |row| row.get(0),
+    )
+}
+
+/// Lists every known migration together with whether it has been applied
+pub fn status(conn: &Connection) -> rusqlite::Result<Vec<(i64, &'static str, bool)>> {
+    ensure_migrations_table(conn)?;
+    let mut stmt = conn.prepare("SELECT COUNT(*) FROM schema_migrations WHERE version = ?1")?;
+    MIGRATIONS
+        .iter()
+        .map(|m| {
+            let count: i64 = stmt.query_row([m.version], |row| row.get(0))?;
+            Ok((m.version, m.name, count > 0))
+        })
+        .collect()
+}
+
+/// Applies all pending migrations and returns the names of the ones that were run.
+/// Refuses to touch a database that was migrated by a newer version of Party Hub.
+pub fn run_pending(conn: &mut Connection) -> Result<Vec<String>, String> {
+    let db_version =
+        current_version(conn).map_err(|e| format!("Failed to read schema version: {}", e))?;
+    let supported = latest_version();
+
+    if db_version > supported {
+        return Err(format!(
+            "Database schema version {} is newer than the latest version supported by this binary ({}). Please upgrade Party Hub.",
+            db_version, supported
+        ));
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > db_version) {
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start migration transaction: {}", e))?;
+
+        (migration.up)(&tx).map_err(|e| {
+            format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.name, e
+            )
+        })?;
+
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES (?1, ?2)",
+            rusqlite::params![migration.version, migration.name],
+        )
+        .and_then(|_| tx.commit())
+        .map_err(|e| {
+            format!(
+                "Failed to record migration {} ({}): {}",
+                migration.version, migration.name, e
+            )
+        })?;
+
+        applied.push(format!("{:04}_{}", migration.version, migration.name));
+    }
+
+    Ok(applied)
+}
+
+fn ensure_migrations_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version    INTEGER PRIMARY KEY,
+            name       TEXT NOT NULL,
+            applied_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+fn column_exists(tx: &Transaction, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let count: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Version 1: the 0.2.0 schema.
+/// Databases that predate versioning are either already on this layout (created by 0.2.0)
+/// or still on the 0.1 layout, which is detected by the old `guests.name` column and upgraded in place.
+fn initial_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    if column_exists(tx, "guests", "name")? {
+        tx.execute_batch(LEGACY_0_1_TO_0_2)?;
+    }
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS parties (
+            id    TEXT PRIMARY KEY,
+            name  TEXT NOT NULL,
+            author TEXT NOT NULL,
+            invitation_blocks JSON,
+            date TEXT NOT NULL DEFAULT '',
+            respond_until TEXT NOT NULL DEFAULT '',
+            frozen BOOLEAN NOT NULL DEFAULT FALSE,
+            public BOOLEAN NOT NULL DEFAULT FALSE,
+            max_guests INTEGER NOT NULL DEFAULT 0,
+            has_rsvp_block BOOLEAN NOT NULL DEFAULT FALSE,
+            duration REAL NOT NULL DEFAULT 0,
+            location TEXT NOT NULL DEFAULT ''
+        )",
+        (),
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS invitations (
+            id    TEXT PRIMARY KEY,
+            guest_id TEXT NOT NULL,
+            party_id TEXT NOT NULL,
+            invitation_block_answers JSON,
+            organizer BOOLEAN NOT NULL DEFAULT FALSE
+        )",
+        (),
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS guests (
+            id    TEXT PRIMARY KEY,
+            salutation TEXT NOT NULL DEFAULT '',
+            first TEXT NOT NULL DEFAULT '',
+            last TEXT NOT NULL DEFAULT '',
+            email TEXT NOT NULL DEFAULT '',
+            note TEXT NOT NULL DEFAULT '',
+            author TEXT NOT NULL,
+            selfcreated BOOLEAN NOT NULL DEFAULT FALSE
+        )",
+        (),
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS authors (
+            id    TEXT PRIMARY KEY,
+            name  TEXT NOT NULL,
+            author_secret TEXT NOT NULL
+        )",
+        (),
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS web_push_subscriptions (
+            id        TEXT PRIMARY KEY,
+            endpoint  TEXT NOT NULL UNIQUE,
+            p256dh    TEXT NOT NULL,
+            auth      TEXT NOT NULL,
+            created_at INTEGER DEFAULT (strftime('%s', 'now'))
+        )",
+        (),
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS guest_subscriptions (
+            guest_id        TEXT NOT NULL,
+            subscription_id TEXT NOT NULL,
+            created_at      INTEGER DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (guest_id, subscription_id),
+            FOREIGN KEY (guest_id) REFERENCES guests(id),
+            FOREIGN KEY (subscription_id) REFERENCES web_push_subscriptions(id)
+        )",
+        (),
+    )?;
+
+    Ok(())
+}
+
+/// Version 2: one row per answered block instead of a JSON blob per invitation.
+/// Existing answers are copied over and the old `invitations.invitation_block_answers` column is dropped.
+fn answers_table(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE answers (
+            invitation_id TEXT NOT NULL,
+            block_id      TEXT NOT NULL,
+            value         TEXT NOT NULL,
+            updated_at    INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (invitation_id, block_id),
+            FOREIGN KEY (invitation_id) REFERENCES invitations(id)
+        )",
+        (),
+    )?;
+    tx.execute(
+        "CREATE INDEX idx_answers_block ON answers (block_id, value)",
+        (),
+    )?;
+
+    let blobs: Vec<(String, String)> = {
+        let mut stmt = tx.prepare(
+            "SELECT id, invitation_block_answers FROM invitations WHERE invitation_block_answers IS NOT NULL",
+        )?;
+        stmt.query_map((), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?
+    };
+
+    let mut insert = tx.prepare(
+        "INSERT OR REPLACE INTO answers (invitation_id, block_id, value) VALUES (?1, ?2, ?3)",
+    )?;
+    for (invitation_id, blob) in blobs {
+        // Unreadable blobs were treated as "no answers" before, so they are skipped
+        let Ok(serde_json::Value::Object(answers)) = serde_json::from_str(&blob) else {
+            continue;
+        };
+        for (block_id, value) in answers.iter().filter(|(_, value)| !value.is_null()) {
+            insert.execute(rusqlite::params![invitation_id, block_id, value.to_string()])?;
+        }
+    }
+    drop(insert);
+
+    tx.execute(
+        "ALTER TABLE invitations DROP COLUMN invitation_block_answers",
+        (),
+    )?;
+
+    Ok(())
+}
+
+/// Version 3: opt-in waitlist for full parties
+fn waitlist(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE parties ADD COLUMN waitlist BOOLEAN NOT NULL DEFAULT FALSE",
+        (),
+    )?;
+    tx.execute(
+        "CREATE TABLE waitlist (
+            invitation_id TEXT PRIMARY KEY,
+            party_id      TEXT NOT NULL,
+            position      INTEGER NOT NULL,
+            created_at    INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            FOREIGN KEY (invitation_id) REFERENCES invitations(id)
+        )",
+        (),
+    )?;
+    tx.execute(
+        "CREATE INDEX idx_waitlist_party ON waitlist (party_id, position)",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 4: plus-ones, configured per party and stored per invitation
+fn companions(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE parties ADD COLUMN max_companions INTEGER NOT NULL DEFAULT 0",
+        (),
+    )?;
+    tx.execute(
+        "ALTER TABLE parties ADD COLUMN ask_companion_names BOOLEAN NOT NULL DEFAULT FALSE",
+        (),
+    )?;
+    tx.execute(
+        "ALTER TABLE invitations ADD COLUMN companions INTEGER NOT NULL DEFAULT 0",
+        (),
+    )?;
+    tx.execute(
+        "ALTER TABLE invitations ADD COLUMN companion_names TEXT NOT NULL DEFAULT '[]'",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 5: households that share one invitation link per party
+fn households(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE households (
+            id     TEXT PRIMARY KEY,
+            name   TEXT NOT NULL,
+            author TEXT NOT NULL,
+            FOREIGN KEY (author) REFERENCES authors(id)
+        )",
+        (),
+    )?;
+    tx.execute(
+        "ALTER TABLE guests ADD COLUMN household_id TEXT REFERENCES households(id)",
+        (),
+    )?;
+    tx.execute(
+        "CREATE TABLE household_invitations (
+            id           TEXT PRIMARY KEY,
+            household_id TEXT NOT NULL,
+            party_id     TEXT NOT NULL,
+            UNIQUE (household_id, party_id),
+            FOREIGN KEY (household_id) REFERENCES households(id),
+            FOREIGN KEY (party_id) REFERENCES parties(id)
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 6: named invitation block layouts that can seed new parties
+fn block_templates(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE block_templates (
+            id                TEXT PRIMARY KEY,
+            name              TEXT NOT NULL,
+            author            TEXT NOT NULL,
+            invitation_blocks TEXT NOT NULL,
+            FOREIGN KEY (author) REFERENCES authors(id)
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 7: recurring party series, whose occurrences are regular parties
+fn party_series(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE party_series (
+            id                  TEXT PRIMARY KEY,
+            author              TEXT NOT NULL,
+            rrule               TEXT NOT NULL,
+            dtstart             TEXT NOT NULL,
+            exdates             TEXT NOT NULL DEFAULT '[]',
+            name                TEXT NOT NULL,
+            invitation_blocks   TEXT NOT NULL,
+            duration            REAL NOT NULL,
+            location            TEXT NOT NULL,
+            public              BOOLEAN NOT NULL,
+            max_guests          INTEGER NOT NULL,
+            waitlist            BOOLEAN NOT NULL,
+            max_companions      INTEGER NOT NULL,
+            ask_companion_names BOOLEAN NOT NULL,
+            FOREIGN KEY (author) REFERENCES authors(id)
+        )",
+        (),
+    )?;
+    tx.execute(
+        "CREATE TABLE series_guests (
+            series_id TEXT NOT NULL,
+            guest_id  TEXT NOT NULL,
+            organizer BOOLEAN NOT NULL DEFAULT FALSE,
+            PRIMARY KEY (series_id, guest_id),
+            FOREIGN KEY (series_id) REFERENCES party_series(id),
+            FOREIGN KEY (guest_id) REFERENCES guests(id)
+        )",
+        (),
+    )?;
+    tx.execute(
+        "ALTER TABLE parties ADD COLUMN series_id TEXT REFERENCES party_series(id)",
+        (),
+    )?;
+    tx.execute("ALTER TABLE parties ADD COLUMN series_date TEXT", ())?;
+    tx.execute(
+        "ALTER TABLE parties ADD COLUMN series_overrides TEXT NOT NULL DEFAULT '[]'",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 8: uploaded images and files, whose content lives in `attachment::UPLOAD_DIR`
+fn attachments(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE attachments (
+            id            TEXT PRIMARY KEY,
+            author        TEXT NOT NULL,
+            file_name     TEXT NOT NULL,
+            content_type  TEXT NOT NULL,
+            size          INTEGER NOT NULL,
+            has_thumbnail BOOLEAN NOT NULL,
+            created_at    TEXT NOT NULL,
+            FOREIGN KEY (author) REFERENCES authors(id)
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 9: the language notifications are sent in, set by the organizer or detected
+/// from the guest's browser when they open their invitation
+fn guest_language(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE guests ADD COLUMN language TEXT NOT NULL DEFAULT ''",
+        (),
+    )?;
+    tx.execute(
+        "ALTER TABLE guests ADD COLUMN detected_language TEXT NOT NULL DEFAULT ''",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 10: the language a party's pages are shown in, overriding the guest's browser,
+/// and the same setting as a series default
+fn party_language(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE parties ADD COLUMN language TEXT NOT NULL DEFAULT ''",
+        (),
+    )?;
+    tx.execute(
+        "ALTER TABLE party_series ADD COLUMN language TEXT NOT NULL DEFAULT ''",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 11: the IANA time zone party dates are in (empty = the server's default zone),
+/// also as a series default. Existing parties are left empty: their dates were meant in the
+/// server's local time, which is what the default zone falls back to (see `timezone`).
+fn party_time_zone(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE parties ADD COLUMN time_zone TEXT NOT NULL DEFAULT ''",
+        (),
+    )?;
+    tx.execute(
+        "ALTER TABLE party_series ADD COLUMN time_zone TEXT NOT NULL DEFAULT ''",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 12: how often a party was updated, the SEQUENCE of its calendar events
+fn party_sequence(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "ALTER TABLE parties ADD COLUMN sequence INTEGER NOT NULL DEFAULT 0",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Version 13: secret tokens for the calendar feeds of guests and authors
+fn calendar_feeds(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE calendar_feeds (
+            token     TEXT PRIMARY KEY,
+            guest_id  TEXT UNIQUE REFERENCES guests(id),
+            author_id TEXT UNIQUE REFERENCES authors(id),
+            CHECK ((guest_id IS NULL) != (author_id IS NULL))
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database as Party Hub 0.1 left it: guest names in one column, an RSVP status per
+    /// invitation and the answers as a JSON blob
+    fn legacy_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE parties (id TEXT PRIMARY KEY, author TEXT NOT NULL, invitation_blocks JSON);
+            CREATE TABLE invitations (
+                id TEXT PRIMARY KEY,
+                guest_id TEXT NOT NULL,
+                party_id TEXT NOT NULL,
+                status TEXT,
+                invitation_block_answers JSON
+            );
+            CREATE TABLE guests (id TEXT PRIMARY KEY, name TEXT NOT NULL);
+            CREATE TABLE authors (id TEXT PRIMARY KEY, name TEXT NOT NULL, author_secret TEXT NOT NULL);
+            INSERT INTO authors VALUES ('a', 'Author', 's');
+            INSERT INTO parties VALUES ('p', 'a', '[]');
+            INSERT INTO guests VALUES ('g1', 'Bo'), ('g2', 'Kim'), ('g3', 'Sam');
+            INSERT INTO invitations VALUES
+                ('i1', 'g1', 'p', 'yes', '{"rsvp":0,"note":"Bringing cake","diet":[true,false],"skip":null}'),
+                ('i2', 'g2', 'p', 'no', 'not json'),
+                ('i3', 'g3', 'p', NULL, NULL);
+            "#,
+        )
+        .unwrap();
+        conn
+    }
+
+    fn answers(conn: &Connection) -> Vec<(String, String, String)> {
+        let mut stmt = conn
+            .prepare("SELECT invitation_id, block_id, value FROM answers ORDER BY invitation_id, block_id")
+            .unwrap();
+        stmt.query_map((), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn upgrades_legacy_databases() {
+        let mut conn = legacy_db();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        let applied = run_pending(&mut conn).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(applied[0], "0001_initial_schema");
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(
+            status(&conn)
+                .unwrap()
+                .iter()
+                .all(|(_, _, applied)| *applied)
+        );
+
+        // Names move to `first` and the guest is linked to the party's author
+        let (first, author): (String, String) = conn
+            .query_row(
+                "SELECT first, author FROM guests WHERE id = 'g1'",
+                (),
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((first.as_str(), author.as_str()), ("Bo", "a"));
+
+        for (table, column) in [
+            ("guests", "name"),
+            ("invitations", "status"),
+            ("invitations", "invitation_block_answers"),
+        ] {
+            let count: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+                    [table, column],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(count, 0, "{}.{} should be gone", table, column);
+        }
+    }
+
+    #[test]
+    fn moves_answers_into_rows() {
+        let mut conn = legacy_db();
+        run_pending(&mut conn).unwrap();
+
+        // Null answers and unreadable blobs are dropped, the rest keeps its JSON form
+        let row = |block_id: &str, value: &str| {
+            ("i1".to_string(), block_id.to_string(), value.to_string())
+        };
+        assert_eq!(
+            answers(&conn),
+            [
+                row("diet", "[true,false]"),
+                row("note", "\"Bringing cake\""),
+                row("rsvp", "0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_each_migration_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(run_pending(&mut conn).unwrap().len(), MIGRATIONS.len());
+        assert!(run_pending(&mut conn).unwrap().is_empty());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES (?1, 'future')",
+            [latest_version() + 1],
+        )
+        .unwrap();
+        assert!(run_pending(&mut conn).unwrap_err().contains("newer"));
+    }
+}
@@ -0,0 +1,351 @@
+pub mod answers;
+pub mod attachments;
+pub mod feeds;
+pub mod households;
+pub mod migrations;
+pub mod series;
+pub mod waitlist;
+
+use crate::block::{InvitationBlock, parse_blocks};
+use crate::timezone;
+use chrono_tz::Tz;
+use rusqlite::{Connection, Row};
+use serde::{Deserialize, Serialize};
+
+pub const DB_PATH: &str = "party.db";
+
+/// Column list for `Party::from_row`, so every query loading a party selects the same columns
+pub const PARTY_COLUMNS: &str = "id, name, author, invitation_blocks, date, duration, location, respond_until, frozen, public, max_guests, has_rsvp_block, waitlist, max_companions, ask_companion_names, series_id, series_date, series_overrides, language, time_zone, sequence";
+
+/// Column list for `Guest::from_row`
+pub const GUEST_COLUMNS: &str = "id, salutation, first, last, email, note, author, selfcreated, household_id, language, detected_language";
+
+/// Column list for `Invitation::from_row`
+pub const INVITATION_COLUMNS: &str = "id, guest_id, party_id, organizer, companions, companion_names";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Party {
+    pub id: String,
+    pub name: String,
+    pub author: String,
+    pub invitation_blocks: Vec<InvitationBlock>, // Stored as JSON
+    pub date: String, // ISO 8601 date string
+    pub respond_until: String, // ISO 8601 date string
+    pub frozen: bool,
+    pub public: bool,
+    pub max_guests: i64,
+    pub has_rsvp_block: bool,
+    pub duration: f64, // Duration in hours
+    pub location: String, // Party location/address
+    pub waitlist: bool, // Queue guests who say yes once max_guests is reached
+    pub max_companions: i64, // Plus-ones each guest may bring (0 = none)
+    pub ask_companion_names: bool,
+    pub series_id: Option<String>, // Recurring series this party is an occurrence of
+    pub series_date: Option<String>, // Start of the occurrence according to the series rule
+    pub series_overrides: Vec<String>, // Fields changed for this occurrence only (stored as JSON)
+    pub language: String, // Language guests see the party in (empty = their own choice)
+    pub time_zone: String, // IANA time zone of `date` and `respond_until` (empty = server default)
+    pub sequence: i64, // Number of updates, so calendars can tell which version is the newest
+}
+
+impl Party {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Party {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            author: row.get("author")?,
+            invitation_blocks: {
+                let raw: Option<String> = row.get("invitation_blocks")?;
+                parse_blocks(raw.as_deref().unwrap_or("")).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        row.as_ref().column_index("invitation_blocks").unwrap_or(0),
+                        rusqlite::types::Type::Text,
+                        Box::new(e),
+                    )
+                })?
+            },
+            date: row.get("date")?,
+            respond_until: row.get("respond_until")?,
+            frozen: row.get("frozen")?,
+            public: row.get("public")?,
+            max_guests: row.get("max_guests")?,
+            has_rsvp_block: row.get("has_rsvp_block")?,
+            duration: row.get("duration")?,
+            location: row.get("location")?,
+            waitlist: row.get("waitlist")?,
+            max_companions: row.get("max_companions")?,
+            ask_companion_names: row.get("ask_companion_names")?,
+            series_id: row.get("series_id")?,
+            series_date: row.get("series_date")?,
+            series_overrides: {
+                let raw: String = row.get("series_overrides")?;
+                serde_json::from_str(&raw).unwrap_or_default()
+            },
+            language: row.get("language")?,
+            time_zone: row.get("time_zone")?,
+            sequence: row.get("sequence")?,
+        })
+    }
+
+    /// The time zone the party's dates are in
+    pub fn zone(&self) -> Tz {
+        timezone::or_default(&self.time_zone)
+    }
+
+    // Load a single party by ID
+    pub fn load(conn: &Connection, party_id: &str) -> rusqlite::Result<Self> {
+        conn.query_row(
+            &format!("SELECT {} FROM parties WHERE id = ?1", PARTY_COLUMNS),
+            [party_id],
+            Party::from_row,
+        )
+    }
+
+    // Map the party's guest IDs to their invitation IDs
+    pub fn guest_invitations(
+        conn: &Connection,
+        party_id: &str,
+    ) -> rusqlite::Result<std::collections::HashMap<String, String>> {
+        let mut stmt = conn.prepare("SELECT guest_id, id FROM invitations WHERE party_id = ?1")?;
+        let map = stmt
+            .query_map([party_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(map)
+    }
+
+    // Delete a party together with its answers, waitlist, household links and invitations
+    pub fn delete(conn: &Connection, party_id: &str) -> rusqlite::Result<usize> {
+        answers::delete_for_party(conn, party_id)?;
+        waitlist::clear(conn, party_id)?;
+        households::delete_links_for_party(conn, party_id)?;
+        conn.execute("DELETE FROM invitations WHERE party_id = ?1", [party_id])?;
+        conn.execute("DELETE FROM parties WHERE id = ?1", [party_id])
+    }
+
+    // Convert to JSON representation for API responses
+    pub fn to_summary_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "date": self.date,
+            "respond_until": self.respond_until,
+            "frozen": self.frozen,
+            "public": self.public,
+            "max_guests": self.max_guests,
+            "has_rsvp_block": self.has_rsvp_block
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Invitation {
+    pub id: String,
+    pub guest_id: String,
+    pub party_id: String,
+    pub organizer: bool,
+    pub companions: i64, // Plus-ones coming along with the guest
+    pub companion_names: Vec<String>, // Stored as JSON, may be shorter than `companions`
+}
+
+impl Invitation {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Invitation {
+            id: row.get("id")?,
+            guest_id: row.get("guest_id")?,
+            party_id: row.get("party_id")?,
+            organizer: row.get("organizer")?,
+            companions: row.get("companions")?,
+            companion_names: {
+                let raw: String = row.get("companion_names")?;
+                serde_json::from_str(&raw).unwrap_or_default()
+            },
+        })
+    }
+
+    // Load a single invitation by ID
+    pub fn load(conn: &Connection, invitation_id: &str) -> rusqlite::Result<Self> {
+        conn.query_row(
+            &format!("SELECT {} FROM invitations WHERE id = ?1", INVITATION_COLUMNS),
+            [invitation_id],
+            Invitation::from_row,
+        )
+    }
+
+    // Store how many companions come along with the guest and their names
+    pub fn set_companions(
+        conn: &Connection,
+        invitation_id: &str,
+        companions: i64,
+        companion_names: &[String],
+    ) -> rusqlite::Result<()> {
+        conn.execute(
+            "UPDATE invitations SET companions = ?1, companion_names = ?2 WHERE id = ?3",
+            rusqlite::params![
+                companions,
+                serde_json::to_string(companion_names).unwrap_or_else(|_| "[]".to_string()),
+                invitation_id
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Convenience method to load this invitation's answers as a JSON object
+    pub fn load_answers(&self, conn: &Connection) -> serde_json::Value {
+        answers::load(conn, &self.id).unwrap_or(serde_json::json!({}))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Guest {
+    pub id: String,
+    pub salutation: String,
+    pub first: String,
+    pub last: String,
+    pub email: String,
+    pub note: String,
+    pub author: String,
+    pub selfcreated: bool,
+    pub household_id: Option<String>,
+    pub language: String, // Chosen by the organizer, empty for automatic
+    pub detected_language: String, // From the browser the guest last opened their invitation with
+}
+
+impl Guest {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Guest {
+            id: row.get("id")?,
+            salutation: row.get("salutation")?,
+            first: row.get("first")?,
+            last: row.get("last")?,
+            email: row.get("email")?,
+            note: row.get("note")?,
+            author: row.get("author")?,
+            selfcreated: row.get("selfcreated")?,
+            household_id: row.get("household_id")?,
+            language: row.get("language")?,
+            detected_language: row.get("detected_language")?,
+        })
+    }
+
+    /// The language emails and push notifications about a party are written in: the guest's
+    /// own, else the one they last saw an invitation in, else the party's
+    pub fn notification_language<'a>(&'a self, party_language: &'a str) -> &'a str {
+        [self.language.as_str(), &self.detected_language, party_language]
+            .into_iter()
+            .find(|language| !language.is_empty())
+            .unwrap_or("en")
+    }
+
+    pub fn set_detected_language(
+        conn: &Connection,
+        guest_id: &str,
+        language: &str,
+    ) -> rusqlite::Result<()> {
+        conn.execute(
+            "UPDATE guests SET detected_language = ?1 WHERE id = ?2",
+            [language, guest_id],
+        )?;
+        Ok(())
+    }
+
+    // Convert to JSON representation for API responses
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "salutation": self.salutation,
+            "first": self.first,
+            "last": self.last,
+            "email": self.email,
+            "note": self.note,
+            "selfcreated": self.selfcreated,
+            "household_id": self.household_id,
+            "language": self.language,
+            "detected_language": self.detected_language
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Household {
+    pub id: String,
+    pub name: String,
+    pub author: String,
+}
+
+impl Household {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Household {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            author: row.get("author")?,
+        })
+    }
+}
+
+/// A named invitation block layout that new parties can start from
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockTemplate {
+    pub id: String,
+    pub name: String,
+    pub author: String,
+    pub invitation_blocks: Vec<InvitationBlock>, // Stored as JSON
+}
+
+impl BlockTemplate {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(BlockTemplate {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            author: row.get("author")?,
+            invitation_blocks: {
+                let raw: String = row.get("invitation_blocks")?;
+                parse_blocks(&raw).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        row.as_ref().column_index("invitation_blocks").unwrap_or(0),
+                        rusqlite::types::Type::Text,
+                        Box::new(e),
+                    )
+                })?
+            },
+        })
+    }
+
+    pub fn load(conn: &Connection, template_id: &str, author_id: &str) -> rusqlite::Result<Self> {
+        conn.query_row(
+            "SELECT id, name, author, invitation_blocks FROM block_templates WHERE id = ?1 AND author = ?2",
+            [template_id, author_id],
+            BlockTemplate::from_row,
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Author {
+    pub id: String,
+    pub name: String,
+    pub author_secret: String,
+}
+
+impl Author {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Author {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            author_secret: row.get("author_secret")?,
+        })
+    }
+}
+
+/// Opens the database and brings its schema up to date
+pub fn prepare_db() -> Result<(), String> {
+    let mut conn =
+        Connection::open(DB_PATH).map_err(|e| format!("Failed to open {}: {}", DB_PATH, e))?;
+
+    let applied = migrations::run_pending(&mut conn)?;
+    for name in applied {
+        println!("INFO: Applied database migration {}", name);
+    }
+
+    Ok(())
+}
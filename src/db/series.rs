@@ -0,0 +1,612 @@
+use super::{PARTY_COLUMNS, Party, waitlist};
+use crate::block::{self, InvitationBlock, parse_blocks};
+use crate::calendar::Method;
+use crate::notification::{self, Notifications};
+use crate::series::rrule::RRule;
+use crate::timezone;
+use chrono::{Duration, NaiveDateTime};
+use chrono_tz::Tz;
+use rusqlite::{Connection, Row, params};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+// Every occurrence of a series is a regular party. The series keeps the rule, the shared settings
+// and the default guest list; occurrences remember which settings were changed for them alone.
+
+/// Format of `parties.date` for generated occurrences (what datetime-local inputs produce)
+pub const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// How far ahead occurrences of series without an end are created
+const HORIZON_DAYS: i64 = 365;
+
+/// Most upcoming occurrences that exist at once, so daily rules don't create hundreds of parties
+const MAX_UPCOMING: usize = 100;
+
+/// Settings occurrences inherit from their series unless they override them
+#[derive(Debug, Clone)]
+pub struct SeriesDefaults {
+    pub name: String,
+    pub invitation_blocks: Vec<InvitationBlock>,
+    pub duration: f64,
+    pub location: String,
+    pub public: bool,
+    pub max_guests: i64,
+    pub waitlist: bool,
+    pub max_companions: i64,
+    pub ask_companion_names: bool,
+    pub language: String,
+    pub time_zone: String,
+}
+
+impl SeriesDefaults {
+    pub fn from_party(party: &Party) -> Self {
+        SeriesDefaults {
+            name: party.name.clone(),
+            invitation_blocks: party.invitation_blocks.clone(),
+            duration: party.duration,
+            location: party.location.clone(),
+            public: party.public,
+            max_guests: party.max_guests,
+            waitlist: party.waitlist,
+            max_companions: party.max_companions,
+            ask_companion_names: party.ask_companion_names,
+            language: party.language.clone(),
+            time_zone: party.time_zone.clone(),
+        }
+    }
+
+    /// Names of the settings (as in `parties` columns) that differ from `other`
+    pub fn differing_fields(&self, other: &SeriesDefaults) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut check = |name: &str, differs: bool| {
+            if differs {
+                fields.push(name.to_string());
+            }
+        };
+        check("name", self.name != other.name);
+        check(
+            "invitation_blocks",
+            block::blocks_to_json(&self.invitation_blocks)
+                != block::blocks_to_json(&other.invitation_blocks),
+        );
+        check("duration", self.duration != other.duration);
+        check("location", self.location != other.location);
+        check("public", self.public != other.public);
+        check("max_guests", self.max_guests != other.max_guests);
+        check("waitlist", self.waitlist != other.waitlist);
+        check(
+            "max_companions",
+            self.max_companions != other.max_companions,
+        );
+        check(
+            "ask_companion_names",
+            self.ask_companion_names != other.ask_companion_names,
+        );
+        check("language", self.language != other.language);
+        check("time_zone", self.time_zone != other.time_zone);
+        fields
+    }
+
+    /// These settings, except for the ones listed in `overrides`, which are taken from `party`
+    fn merged_with(&self, party: &Party, overrides: &[String]) -> SeriesDefaults {
+        let own = SeriesDefaults::from_party(party);
+        let keep = |field: &str| overrides.iter().any(|overridden| overridden == field);
+        SeriesDefaults {
+            name: if keep("name") {
+                own.name
+            } else {
+                self.name.clone()
+            },
+            invitation_blocks: if keep("invitation_blocks") {
+                own.invitation_blocks
+            } else {
+                self.invitation_blocks.clone()
+            },
+            duration: if keep("duration") {
+                own.duration
+            } else {
+                self.duration
+            },
+            location: if keep("location") {
+                own.location
+            } else {
+                self.location.clone()
+            },
+            public: if keep("public") {
+                own.public
+            } else {
+                self.public
+            },
+            max_guests: if keep("max_guests") {
+                own.max_guests
+            } else {
+                self.max_guests
+            },
+            waitlist: if keep("waitlist") {
+                own.waitlist
+            } else {
+                self.waitlist
+            },
+            max_companions: if keep("max_companions") {
+                own.max_companions
+            } else {
+                self.max_companions
+            },
+            ask_companion_names: if keep("ask_companion_names") {
+                own.ask_companion_names
+            } else {
+                self.ask_companion_names
+            },
+            language: if keep("language") {
+                own.language
+            } else {
+                self.language.clone()
+            },
+            time_zone: if keep("time_zone") {
+                own.time_zone
+            } else {
+                self.time_zone.clone()
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Series {
+    pub id: String,
+    pub author: String,
+    pub rrule: String,
+    pub dtstart: String, // Start of the first occurrence, same format as `parties.date`
+    pub exdates: Vec<String>, // Occurrences that were deleted and must not come back (stored as JSON)
+    pub defaults: SeriesDefaults,
+}
+
+impl Series {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Series {
+            id: row.get("id")?,
+            author: row.get("author")?,
+            rrule: row.get("rrule")?,
+            dtstart: row.get("dtstart")?,
+            exdates: {
+                let raw: String = row.get("exdates")?;
+                serde_json::from_str(&raw).unwrap_or_default()
+            },
+            defaults: SeriesDefaults {
+                name: row.get("name")?,
+                invitation_blocks: {
+                    let raw: String = row.get("invitation_blocks")?;
+                    parse_blocks(&raw).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            row.as_ref().column_index("invitation_blocks").unwrap_or(0),
+                            rusqlite::types::Type::Text,
+                            Box::new(e),
+                        )
+                    })?
+                },
+                duration: row.get("duration")?,
+                location: row.get("location")?,
+                public: row.get("public")?,
+                max_guests: row.get("max_guests")?,
+                waitlist: row.get("waitlist")?,
+                max_companions: row.get("max_companions")?,
+                ask_companion_names: row.get("ask_companion_names")?,
+                language: row.get("language")?,
+                time_zone: row.get("time_zone")?,
+            },
+        })
+    }
+
+    pub fn load(conn: &Connection, series_id: &str, author_id: &str) -> rusqlite::Result<Self> {
+        conn.query_row(
+            "SELECT * FROM party_series WHERE id = ?1 AND author = ?2",
+            [series_id, author_id],
+            Series::from_row,
+        )
+    }
+
+    /// The time zone the series' dates are in
+    pub fn zone(&self) -> Tz {
+        timezone::or_default(&self.defaults.time_zone)
+    }
+
+    /// Loads a series without checking its author, for the public calendar feed
+    pub fn load_public(conn: &Connection, series_id: &str) -> rusqlite::Result<Self> {
+        conn.query_row(
+            "SELECT * FROM party_series WHERE id = ?1",
+            [series_id],
+            Series::from_row,
+        )
+    }
+}
+
+/// Parties that were created or deleted while bringing a series up to date. The guests
+/// concerned are told with `send` once the changes are committed.
+#[derive(Default)]
+pub struct SyncResult {
+    pub created: Vec<String>,
+    pub removed: Vec<String>,
+    /// The cancellations for the guests of deleted occurrences, prepared while they still existed
+    cancelled: Vec<Notifications>,
+    /// The invitation of each invited guest to the earliest created occurrence, by guest ID
+    invited: HashMap<String, String>,
+}
+
+impl SyncResult {
+    /// Tells the guests of deleted occurrences they are cancelled, and invites the guests of
+    /// created ones once, to the next one
+    pub async fn send(
+        self,
+        db: actix_web::web::Data<r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>>,
+    ) {
+        for notifications in self.cancelled {
+            notifications.send(db.clone()).await;
+        }
+        notification::notify_guests(db, notification::invitation_message, self.invited).await;
+    }
+}
+
+pub fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(date, DATE_FORMAT))
+        .ok()
+}
+
+/// Turns `party` into the first occurrence of a new series, which copies its settings and guest list
+pub fn create_from_party(
+    conn: &Connection,
+    party: &Party,
+    rrule: &RRule,
+) -> rusqlite::Result<String> {
+    let series_id = Uuid::new_v4().to_string();
+    let defaults = SeriesDefaults::from_party(party);
+    conn.execute(
+        "INSERT INTO party_series (id, author, rrule, dtstart, name, invitation_blocks, duration, location, public, max_guests, waitlist, max_companions, ask_companion_names, language, time_zone)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        params![
+            &series_id,
+            &party.author,
+            rrule.to_string(),
+            &party.date,
+            &defaults.name,
+            block::blocks_to_json(&defaults.invitation_blocks),
+            defaults.duration,
+            &defaults.location,
+            defaults.public,
+            defaults.max_guests,
+            defaults.waitlist,
+            defaults.max_companions,
+            defaults.ask_companion_names,
+            &defaults.language,
+            &defaults.time_zone,
+        ],
+    )?;
+    conn.execute(
+        "INSERT INTO series_guests (series_id, guest_id, organizer)
+         SELECT ?1, guest_id, organizer FROM invitations WHERE party_id = ?2",
+        [&series_id, &party.id],
+    )?;
+    conn.execute(
+        "UPDATE parties SET series_id = ?1, series_date = date, series_overrides = '[]' WHERE id = ?2",
+        [&series_id, &party.id],
+    )?;
+    Ok(series_id)
+}
+
+pub fn update_defaults(
+    conn: &Connection,
+    series_id: &str,
+    defaults: &SeriesDefaults,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE party_series SET name = ?1, invitation_blocks = ?2, duration = ?3, location = ?4, public = ?5, max_guests = ?6, waitlist = ?7, max_companions = ?8, ask_companion_names = ?9, language = ?10, time_zone = ?11 WHERE id = ?12",
+        params![
+            &defaults.name,
+            block::blocks_to_json(&defaults.invitation_blocks),
+            defaults.duration,
+            &defaults.location,
+            defaults.public,
+            defaults.max_guests,
+            defaults.waitlist,
+            defaults.max_companions,
+            defaults.ask_companion_names,
+            &defaults.language,
+            &defaults.time_zone,
+            series_id,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn set_rule(conn: &Connection, series_id: &str, rrule: &RRule) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE party_series SET rrule = ?1 WHERE id = ?2",
+        [&rrule.to_string(), series_id],
+    )?;
+    Ok(())
+}
+
+pub fn set_overrides(
+    conn: &Connection,
+    party_id: &str,
+    overrides: &[String],
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE parties SET series_overrides = ?1 WHERE id = ?2",
+        [
+            &serde_json::to_string(overrides).unwrap_or_else(|_| "[]".to_string()),
+            party_id,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Remembers that an occurrence was deleted, so syncing doesn't create it again
+pub fn add_exdate(conn: &Connection, series_id: &str, series_date: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE party_series SET exdates = json_insert(exdates, '$[#]', ?1) WHERE id = ?2",
+        [series_date, series_id],
+    )?;
+    Ok(())
+}
+
+/// All occurrences of a series, ordered by date
+pub fn occurrences(conn: &Connection, series_id: &str) -> rusqlite::Result<Vec<Party>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM parties WHERE series_id = ?1 ORDER BY series_date",
+        PARTY_COLUMNS
+    ))?;
+    let parties = stmt.query_map([series_id], Party::from_row)?;
+    parties.collect()
+}
+
+/// The default guest list as (guest ID, organizer) pairs
+pub fn guests(conn: &Connection, series_id: &str) -> rusqlite::Result<Vec<(String, bool)>> {
+    let mut stmt =
+        conn.prepare("SELECT guest_id, organizer FROM series_guests WHERE series_id = ?1")?;
+    let guests = stmt.query_map([series_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    guests.collect()
+}
+
+/// Replaces the default guest list with the guests of `party_id`
+pub fn set_guests_from_party(
+    conn: &Connection,
+    series_id: &str,
+    party_id: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM series_guests WHERE series_id = ?1",
+        [series_id],
+    )?;
+    conn.execute(
+        "INSERT INTO series_guests (series_id, guest_id, organizer)
+         SELECT ?1, guest_id, organizer FROM invitations WHERE party_id = ?2",
+        [series_id, party_id],
+    )?;
+    Ok(())
+}
+
+/// Invites the default guests to an occurrence they aren't invited to yet.
+/// Returns the new invitations as (guest ID, invitation ID) pairs.
+pub fn invite_guests(
+    conn: &Connection,
+    series_id: &str,
+    party_id: &str,
+) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut invited = Vec::new();
+    for (guest_id, organizer) in guests(conn, series_id)? {
+        let invitation_id = Uuid::new_v4().to_string();
+        let inserted = conn.execute(
+            "INSERT INTO invitations (id, guest_id, party_id, organizer)
+             SELECT ?1, ?2, ?3, ?4 WHERE NOT EXISTS (SELECT 1 FROM invitations WHERE guest_id = ?2 AND party_id = ?3)",
+            params![&invitation_id, &guest_id, party_id, organizer],
+        )?;
+        if inserted > 0 {
+            invited.push((guest_id, invitation_id));
+        }
+    }
+    Ok(invited)
+}
+
+/// Copies the series settings to the occurrences starting at `from_date` or later, keeping what each
+/// one overrides. Returns, for each occurrence, the guests moved up from its waitlist and those
+/// who stopped waiting because it no longer has one.
+pub fn apply_defaults(
+    conn: &Connection,
+    series: &Series,
+    from_date: &str,
+) -> rusqlite::Result<Vec<(waitlist::TakenOff, waitlist::TakenOff)>> {
+    let mut changes = Vec::new();
+    for party in occurrences(conn, &series.id)? {
+        if party.series_date.as_deref().unwrap_or("") < from_date {
+            continue;
+        }
+        let merged = series.defaults.merged_with(&party, &party.series_overrides);
+        conn.execute(
+            "UPDATE parties SET name = ?1, invitation_blocks = ?2, duration = ?3, location = ?4, public = ?5, max_guests = ?6, waitlist = ?7, max_companions = ?8, ask_companion_names = ?9, has_rsvp_block = ?10, language = ?11, time_zone = ?12, sequence = sequence + 1 WHERE id = ?13",
+            params![
+                &merged.name,
+                block::blocks_to_json(&merged.invitation_blocks),
+                merged.duration,
+                &merged.location,
+                merged.public,
+                merged.max_guests,
+                merged.waitlist,
+                merged.max_companions,
+                merged.ask_companion_names,
+                block::attendance_block(&merged.invitation_blocks).is_some(),
+                &merged.language,
+                &merged.time_zone,
+                &party.id,
+            ],
+        )?;
+
+        // Same as editing the party directly: a raised limit lets waiting guests in
+        let party = Party::load(conn, &party.id)?;
+        if merged.waitlist {
+            changes.push((waitlist::promote(conn, &party)?, Vec::new()));
+        } else {
+            changes.push(waitlist::close(conn, &party)?);
+        }
+    }
+    Ok(changes)
+}
+
+/// Creates the upcoming occurrences the rule asks for and deletes upcoming ones it no longer does.
+/// Past occurrences are never touched.
+pub fn sync_occurrences(
+    conn: &Connection,
+    series: &Series,
+    now: NaiveDateTime,
+) -> rusqlite::Result<SyncResult> {
+    let conversion_error = |e: Box<dyn std::error::Error + Send + Sync>| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e)
+    };
+    let rule = RRule::parse(&series.rrule).map_err(|e| conversion_error(Box::new(e)))?;
+    let dtstart = parse_date(&series.dtstart).ok_or_else(|| {
+        conversion_error(format!("Invalid series start '{}'", series.dtstart).into())
+    })?;
+
+    let horizon = if rule.is_bounded() {
+        now + Duration::days(HORIZON_DAYS * 10)
+    } else {
+        now + Duration::days(HORIZON_DAYS)
+    };
+    let wanted: Vec<String> = rule
+        .occurrences(dtstart, now, horizon, MAX_UPCOMING)
+        .into_iter()
+        .map(|occurrence| occurrence.format(DATE_FORMAT).to_string())
+        .filter(|date| !series.exdates.contains(date))
+        .collect();
+
+    let mut result = SyncResult::default();
+    let mut existing = HashSet::new();
+    for party in occurrences(conn, &series.id)? {
+        let Some(series_date) = party.series_date else {
+            continue;
+        };
+        let upcoming = parse_date(&series_date).is_some_and(|date| date >= now);
+        if upcoming && !wanted.contains(&series_date) {
+            result.cancelled.push(notification::prepare(
+                conn,
+                notification::cancelled_message,
+                Party::guest_invitations(conn, &party.id)?,
+                Method::Cancel,
+            ));
+            Party::delete(conn, &party.id)?;
+            result.removed.push(party.id);
+        } else {
+            existing.insert(series_date);
+        }
+    }
+
+    for date in wanted.iter().filter(|date| !existing.contains(*date)) {
+        let party_id = Uuid::new_v4().to_string();
+        let defaults = &series.defaults;
+        conn.execute(
+            "INSERT INTO parties (id, name, invitation_blocks, author, date, duration, location, respond_until, frozen, public, max_guests, has_rsvp_block, waitlist, max_companions, ask_companion_names, series_id, series_date, language, time_zone)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, '', 0, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?5, ?15, ?16)",
+            params![
+                &party_id,
+                &defaults.name,
+                block::blocks_to_json(&defaults.invitation_blocks),
+                &series.author,
+                date,
+                defaults.duration,
+                &defaults.location,
+                defaults.public,
+                defaults.max_guests,
+                block::attendance_block(&defaults.invitation_blocks).is_some(),
+                defaults.waitlist,
+                defaults.max_companions,
+                defaults.ask_companion_names,
+                &series.id,
+                &defaults.language,
+                &defaults.time_zone,
+            ],
+        )?;
+        // Dates are in order, so each guest keeps the invitation to the earliest new occurrence
+        for (guest_id, invitation_id) in invite_guests(conn, &series.id, &party_id)? {
+            result.invited.entry(guest_id).or_insert(invitation_id);
+        }
+        result.created.push(party_id);
+    }
+    Ok(result)
+}
+
+/// Ends a series. Its occurrences stay as independent parties.
+pub fn delete(conn: &Connection, series_id: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE parties SET series_id = NULL, series_date = NULL, series_overrides = '[]' WHERE series_id = ?1",
+        [series_id],
+    )?;
+    conn.execute(
+        "DELETE FROM series_guests WHERE series_id = ?1",
+        [series_id],
+    )?;
+    conn.execute("DELETE FROM party_series WHERE id = ?1", [series_id])?;
+    Ok(())
+}
+
+/// Removes a deleted guest from the default guest lists
+pub fn delete_guest(conn: &Connection, guest_id: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM series_guests WHERE guest_id = ?1", [guest_id])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations;
+
+    #[test]
+    fn sync_leaves_out_deleted_occurrences() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::run_pending(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO authors (id, name, author_secret) VALUES ('a', 'Author', 's')",
+            (),
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO parties (id, name, author, invitation_blocks, date) VALUES ('p', 'Weekly', 'a', '[]', '2026-01-01T19:00')",
+            (),
+        )
+        .unwrap();
+        let party = Party::load(&conn, "p").unwrap();
+        let rule = RRule::parse("FREQ=WEEKLY;COUNT=4").unwrap();
+        let series_id = create_from_party(&conn, &party, &rule).unwrap();
+        let now = parse_date("2025-12-01T00:00").unwrap();
+        let dates = |conn: &Connection| -> Vec<String> {
+            occurrences(conn, &series_id)
+                .unwrap()
+                .into_iter()
+                .filter_map(|party| party.series_date)
+                .collect()
+        };
+
+        let series = Series::load(&conn, &series_id, "a").unwrap();
+        let result = sync_occurrences(&conn, &series, now).unwrap();
+        assert_eq!(result.created.len(), 3);
+        assert_eq!(
+            dates(&conn),
+            [
+                "2026-01-01T19:00",
+                "2026-01-08T19:00",
+                "2026-01-15T19:00",
+                "2026-01-22T19:00"
+            ]
+        );
+
+        // A deleted occurrence isn't created again, and an excluded one that still exists goes
+        let deleted = occurrences(&conn, &series_id).unwrap().remove(1);
+        Party::delete(&conn, &deleted.id).unwrap();
+        add_exdate(&conn, &series_id, "2026-01-08T19:00").unwrap();
+        add_exdate(&conn, &series_id, "2026-01-15T19:00").unwrap();
+        let series = Series::load(&conn, &series_id, "a").unwrap();
+        let result = sync_occurrences(&conn, &series, now).unwrap();
+        assert!(result.created.is_empty());
+        assert_eq!(result.removed.len(), 1);
+        assert_eq!(dates(&conn), ["2026-01-01T19:00", "2026-01-22T19:00"]);
+    }
+}
@@ -0,0 +1,301 @@
+use super::{Party, answers};
+use crate::block;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use serde_json::json;
+
+/// A guest waiting for a spot, in the order they will be promoted
+#[derive(Debug, Serialize)]
+pub struct WaitlistEntry {
+    pub invitation_id: String,
+    pub guest_id: String,
+    pub name: String,
+    pub companions: i64,
+    pub position: i64,
+}
+
+/// Guests taken off a waitlist as `(guest_id, invitation_id)` pairs
+pub type TakenOff = Vec<(String, String)>;
+
+/// Queues an invitation at the end of its party's waitlist. Keeps the current place
+/// if the invitation is already waiting.
+pub fn enqueue(conn: &Connection, party_id: &str, invitation_id: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO waitlist (invitation_id, party_id, position)
+         SELECT ?1, ?2, COALESCE(MAX(position), 0) + 1 FROM waitlist WHERE party_id = ?2",
+        [invitation_id, party_id],
+    )?;
+    Ok(())
+}
+
+pub fn remove(conn: &Connection, invitation_id: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM waitlist WHERE invitation_id = ?1", [invitation_id])?;
+    Ok(())
+}
+
+/// 1-based place of an invitation in its party's waitlist, `None` if it isn't waiting
+pub fn position(conn: &Connection, invitation_id: &str) -> rusqlite::Result<Option<i64>> {
+    conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM waitlist o WHERE o.party_id = w.party_id AND o.position <= w.position)
+         FROM waitlist w WHERE w.invitation_id = ?1",
+        [invitation_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Number of invitations waiting in front of `invitation_id`, or all waiting invitations
+/// of the party if it isn't on the waitlist itself
+pub fn waiting_ahead(conn: &Connection, party_id: &str, invitation_id: &str) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM waitlist WHERE party_id = ?1 AND invitation_id != ?2
+         AND position < COALESCE((SELECT position FROM waitlist WHERE invitation_id = ?2), 9223372036854775807)",
+        [party_id, invitation_id],
+        |row| row.get(0),
+    )
+}
+
+/// The waitlist of a party in promotion order, with 1-based positions
+pub fn list(conn: &Connection, party_id: &str) -> rusqlite::Result<Vec<WaitlistEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT w.invitation_id, i.guest_id, g.first, g.last, i.companions FROM waitlist w
+         INNER JOIN invitations i ON w.invitation_id = i.id
+         INNER JOIN guests g ON i.guest_id = g.id
+         WHERE w.party_id = ?1 ORDER BY w.position",
+    )?;
+    let rows = stmt.query_map([party_id], |row| {
+        let first: String = row.get(2)?;
+        let last: String = row.get(3)?;
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            format!("{} {}", first, last).trim().to_string(),
+            row.get::<_, i64>(4)?,
+        ))
+    })?;
+
+    let mut entries = Vec::new();
+    for (index, row) in rows.enumerate() {
+        let (invitation_id, guest_id, name, companions) = row?;
+        entries.push(WaitlistEntry {
+            invitation_id,
+            guest_id,
+            name,
+            companions,
+            position: index as i64 + 1,
+        });
+    }
+    Ok(entries)
+}
+
+/// Puts the waitlist in the given order. Returns `false` without changing anything
+/// if `invitation_ids` isn't exactly the set of waiting invitations.
+pub fn reorder(conn: &Connection, party_id: &str, invitation_ids: &[String]) -> rusqlite::Result<bool> {
+    let mut current: Vec<String> = list(conn, party_id)?
+        .into_iter()
+        .map(|entry| entry.invitation_id)
+        .collect();
+    let mut requested = invitation_ids.to_vec();
+    current.sort();
+    requested.sort();
+    if current != requested {
+        return Ok(false);
+    }
+
+    let mut stmt = conn.prepare("UPDATE waitlist SET position = ?1 WHERE invitation_id = ?2 AND party_id = ?3")?;
+    for (index, invitation_id) in invitation_ids.iter().enumerate() {
+        stmt.execute(params![index as i64 + 1, invitation_id, party_id])?;
+    }
+    Ok(true)
+}
+
+pub fn clear(conn: &Connection, party_id: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM waitlist WHERE party_id = ?1", [party_id])?;
+    Ok(())
+}
+
+/// Removes the waitlist entries of all invitations of a guest, optionally only for one party
+pub fn delete_for_guest(conn: &Connection, guest_id: &str, party_id: Option<&str>) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM waitlist WHERE invitation_id IN (SELECT id FROM invitations WHERE guest_id = ?1 AND (?2 IS NULL OR party_id = ?2))",
+        params![guest_id, party_id],
+    )?;
+    Ok(())
+}
+
+/// Moves guests from the front of the waitlist into free spots by answering "yes" for them.
+/// A guest only moves up together with their companions; nobody skips ahead of a guest
+/// whose group doesn't fit yet. Once the party has no limit, everyone waiting moves up.
+/// Returns the promoted `(guest_id, invitation_id)` pairs so they can be notified.
+/// Should run in the same transaction as the change that freed the spots.
+pub fn promote(conn: &Connection, party: &Party) -> rusqlite::Result<TakenOff> {
+    if !party.waitlist {
+        return Ok(Vec::new());
+    }
+    move_up(conn, party)
+}
+
+/// Closes the waitlist of a party that doesn't have one any more. Guests that fit into the
+/// free spots move up as with `promote`, everyone else stops waiting. Returns the promoted
+/// and the removed `(guest_id, invitation_id)` pairs so both can be notified.
+pub fn close(conn: &Connection, party: &Party) -> rusqlite::Result<(TakenOff, TakenOff)> {
+    let promoted = move_up(conn, party)?;
+    let removed = list(conn, &party.id)?
+        .into_iter()
+        .map(|entry| (entry.guest_id, entry.invitation_id))
+        .collect();
+    clear(conn, &party.id)?;
+    Ok((promoted, removed))
+}
+
+fn move_up(conn: &Connection, party: &Party) -> rusqlite::Result<TakenOff> {
+    let Some(attendance) = block::attendance_block(&party.invitation_blocks) else {
+        return Ok(Vec::new());
+    };
+
+    let yes = json!(block::ATTENDANCE_YES);
+    // Without a limit (any more) there is room for everyone who is waiting
+    let mut free_spots = if party.max_guests > 0 {
+        party.max_guests - answers::headcount(conn, &party.id, &attendance.id, None)?
+    } else {
+        i64::MAX
+    };
+
+    let mut promoted = Vec::new();
+    for entry in list(conn, &party.id)? {
+        let heads = 1 + entry.companions;
+        if heads > free_spots {
+            break;
+        }
+        answers::set_one(conn, &entry.invitation_id, &attendance.id, &yes)?;
+        remove(conn, &entry.invitation_id)?;
+        free_spots -= heads;
+        promoted.push((entry.guest_id, entry.invitation_id));
+    }
+    Ok(promoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations;
+
+    /// A party with a waitlist and room for `max_guests`, with a guest `g0`, `g1`, … for each
+    /// entry of `companions`, whose invitation `i0`, `i1`, … brings that many companions
+    fn setup(max_guests: i64, companions: &[i64]) -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::run_pending(&mut conn).unwrap();
+        let blocks = r#"[{"id":"rsvp","type":"attendance","label":"Coming?","options":["Yes","Maybe","No"]}]"#;
+        conn.execute("INSERT INTO authors (id, name, author_secret) VALUES ('a', 'Author', 's')", ()).unwrap();
+        conn.execute(
+            "INSERT INTO parties (id, name, author, invitation_blocks, max_guests, has_rsvp_block, waitlist) VALUES ('p', 'Party', 'a', ?1, ?2, 1, 1)",
+            params![blocks, max_guests],
+        )
+        .unwrap();
+        for (i, companions) in companions.iter().enumerate() {
+            conn.execute("INSERT INTO guests (id, first, author) VALUES (?1, 'Guest', 'a')", [format!("g{}", i)]).unwrap();
+            conn.execute(
+                "INSERT INTO invitations (id, guest_id, party_id, companions) VALUES (?1, ?2, 'p', ?3)",
+                params![format!("i{}", i), format!("g{}", i), companions],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn say_yes(conn: &Connection, invitation_id: &str) {
+        answers::set_one(conn, invitation_id, "rsvp", &json!(block::ATTENDANCE_YES)).unwrap();
+    }
+
+    fn waiting(conn: &Connection) -> Vec<String> {
+        list(conn, "p").unwrap().into_iter().map(|entry| entry.invitation_id).collect()
+    }
+
+    fn ids(pairs: &[(String, String)]) -> Vec<&str> {
+        pairs.iter().map(|(_, invitation_id)| invitation_id.as_str()).collect()
+    }
+
+    #[test]
+    fn enqueue_appends_and_keeps_places() {
+        let conn = setup(1, &[0, 0, 0]);
+        enqueue(&conn, "p", "i1").unwrap();
+        enqueue(&conn, "p", "i2").unwrap();
+        enqueue(&conn, "p", "i0").unwrap();
+        // Waiting again doesn't lose the place
+        enqueue(&conn, "p", "i1").unwrap();
+        assert_eq!(waiting(&conn), ["i1", "i2", "i0"]);
+        assert_eq!(position(&conn, "i2").unwrap(), Some(2));
+
+        // Places close up when someone leaves, new guests still go to the end
+        remove(&conn, "i1").unwrap();
+        enqueue(&conn, "p", "i1").unwrap();
+        assert_eq!(waiting(&conn), ["i2", "i0", "i1"]);
+        assert_eq!(position(&conn, "i2").unwrap(), Some(1));
+        assert_eq!(position(&conn, "i3").unwrap(), None);
+    }
+
+    #[test]
+    fn waiting_ahead_counts_the_guests_in_front() {
+        let conn = setup(1, &[0, 0, 0, 0]);
+        for invitation_id in ["i0", "i1", "i2"] {
+            enqueue(&conn, "p", invitation_id).unwrap();
+        }
+        assert_eq!(waiting_ahead(&conn, "p", "i0").unwrap(), 0);
+        assert_eq!(waiting_ahead(&conn, "p", "i2").unwrap(), 2);
+        // Guests who aren't waiting yet would queue behind everyone
+        assert_eq!(waiting_ahead(&conn, "p", "i3").unwrap(), 3);
+
+        assert!(reorder(&conn, "p", &["i2".to_string(), "i0".to_string(), "i1".to_string()]).unwrap());
+        assert_eq!(waiting_ahead(&conn, "p", "i1").unwrap(), 2);
+        assert!(!reorder(&conn, "p", &["i2".to_string(), "i0".to_string()]).unwrap());
+    }
+
+    #[test]
+    fn promote_fills_spots_in_order() {
+        // Two spots are taken, the others wait: g1 brings two companions, g2 and g3 none
+        let conn = setup(4, &[1, 2, 0, 0]);
+        say_yes(&conn, "i0");
+        for invitation_id in ["i1", "i2", "i3"] {
+            enqueue(&conn, "p", invitation_id).unwrap();
+        }
+
+        // g1's group of three doesn't fit and nobody skips ahead of them
+        let party = Party::load(&conn, "p").unwrap();
+        assert!(promote(&conn, &party).unwrap().is_empty());
+        assert_eq!(waiting(&conn), ["i1", "i2", "i3"]);
+
+        conn.execute("UPDATE parties SET max_guests = 6", ()).unwrap();
+        let party = Party::load(&conn, "p").unwrap();
+        let promoted = promote(&conn, &party).unwrap();
+        assert_eq!(ids(&promoted), ["i1", "i2"]);
+        assert_eq!(promoted[0].0, "g1");
+        assert_eq!(waiting(&conn), ["i3"]);
+        assert_eq!(answers::headcount(&conn, "p", "rsvp", None).unwrap(), 6);
+
+        // Without a limit everyone moves up
+        conn.execute("UPDATE parties SET max_guests = 0", ()).unwrap();
+        let party = Party::load(&conn, "p").unwrap();
+        assert_eq!(ids(&promote(&conn, &party).unwrap()), ["i3"]);
+        assert!(waiting(&conn).is_empty());
+    }
+
+    #[test]
+    fn close_promotes_who_fits_and_removes_the_rest() {
+        let conn = setup(2, &[0, 0, 1, 0]);
+        say_yes(&conn, "i0");
+        for invitation_id in ["i1", "i2", "i3"] {
+            enqueue(&conn, "p", invitation_id).unwrap();
+        }
+
+        conn.execute("UPDATE parties SET waitlist = 0", ()).unwrap();
+        let party = Party::load(&conn, "p").unwrap();
+        // A party without a waitlist doesn't promote on its own
+        assert!(promote(&conn, &party).unwrap().is_empty());
+
+        let (promoted, removed) = close(&conn, &party).unwrap();
+        assert_eq!(ids(&promoted), ["i1"]);
+        assert_eq!(ids(&removed), ["i2", "i3"]);
+        assert!(waiting(&conn).is_empty());
+        assert_eq!(answers::headcount(&conn, "p", "rsvp", None).unwrap(), 2);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::auth::is_authenticated_as_author;
+use crate::db::{Household, households};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, web};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::TransactionBehavior;
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+#[get("/households")]
+async fn get_households(
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let conn = pool.get().unwrap();
+
+        // Get all households of this author together with their members
+        let households_result = conn
+            .prepare("SELECT id, name, author FROM households WHERE author = ?1 ORDER BY name")
+            .and_then(|mut stmt| {
+                let household_iter = stmt.query_map([&author_id], Household::from_row)?;
+
+                let mut result = Vec::new();
+                for household in household_iter.flatten() {
+                    let members = households::members(&conn, &household.id)?;
+                    result.push(json!({
+                        "id": household.id,
+                        "name": household.name,
+                        "members": members.iter().map(|guest| guest.to_json()).collect::<Vec<_>>()
+                    }));
+                }
+                Ok(result)
+            });
+
+        match households_result {
+            Ok(households) => HttpResponse::Ok().json(households),
+            Err(e) => {
+                eprintln!("Database error fetching households: {}", e);
+                HttpResponse::InternalServerError()
+                    .json(json!({"error": "Failed to fetch households"}))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[post("/households/new")]
+async fn create_household(
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let conn = pool.get().unwrap();
+
+        // Create empty household with a default name
+        let household_id = Uuid::new_v4().to_string();
+        let result = conn
+            .prepare("INSERT INTO households (id, name, author) VALUES (?1, ?2, ?3)")
+            .and_then(|mut stmt| stmt.execute([&household_id, "New Household", &author_id]));
+
+        match result {
+            Ok(_) => HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Household created successfully",
+                "household_id": household_id
+            })),
+            Err(e) => {
+                eprintln!("Database error creating household: {}", e);
+                HttpResponse::InternalServerError().json(json!({
+                    "error": "Failed to create household"
+                }))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[get("/households/{household_id}")]
+async fn get_household_details(
+    path: web::Path<String>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let household_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let conn = pool.get().unwrap();
+
+        let household = match households::load(&conn, &household_id, &author_id) {
+            Ok(household) => household,
+            Err(_) => {
+                return HttpResponse::NotFound().json(json!({
+                    "error": "Household not found or access denied"
+                }));
+            }
+        };
+
+        match households::members(&conn, &household.id) {
+            Ok(members) => HttpResponse::Ok().json(json!({
+                "id": household.id,
+                "name": household.name,
+                "members": members.iter().map(|guest| guest.to_json()).collect::<Vec<_>>()
+            })),
+            Err(e) => {
+                eprintln!("Database error loading household members: {}", e);
+                HttpResponse::InternalServerError().json(json!({
+                    "error": "Failed to load household"
+                }))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[derive(Deserialize)]
+struct UpdateHouseholdForm {
+    name: String,
+    member_ids: Vec<String>,
+}
+
+#[post("/households/{household_id}/update")]
+async fn update_household(
+    path: web::Path<String>,
+    form: web::Json<UpdateHouseholdForm>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let household_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let mut conn = pool.get().unwrap();
+
+        // Rename and replace the members in one go
+        let result = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .and_then(|tx| {
+                let rows_affected = tx.execute(
+                    "UPDATE households SET name = ?1 WHERE id = ?2 AND author = ?3",
+                    [form.name.trim(), &household_id, &author_id],
+                )?;
+                if rows_affected > 0 {
+                    households::set_members(&tx, &household_id, &author_id, &form.member_ids)?;
+                }
+                tx.commit()?;
+                Ok(rows_affected)
+            });
+
+        match result {
+            Ok(rows_affected) => {
+                if rows_affected > 0 {
+                    HttpResponse::Ok().json(json!({
+                        "status": "success",
+                        "message": "Household updated successfully"
+                    }))
+                } else {
+                    HttpResponse::NotFound().json(json!({
+                        "error": "Household not found or access denied"
+                    }))
+                }
+            }
+            Err(e) => {
+                eprintln!("Database error updating household: {}", e);
+                HttpResponse::InternalServerError().json(json!({
+                    "error": "Failed to update household"
+                }))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[delete("/households/{household_id}/delete")]
+async fn delete_household(
+    path: web::Path<String>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let household_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let mut conn = pool.get().unwrap();
+
+        // Members and their invitations are kept, only the grouping and shared links go away
+        let result = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .and_then(|tx| {
+                if households::load(&tx, &household_id, &author_id).is_err() {
+                    return Ok(false);
+                }
+                households::delete(&tx, &household_id)?;
+                tx.commit()?;
+                Ok(true)
+            });
+
+        match result {
+            Ok(true) => HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Household deleted successfully"
+            })),
+            Ok(false) => HttpResponse::NotFound().json(json!({
+                "error": "Household not found or access denied"
+            })),
+            Err(e) => {
+                eprintln!("Database error deleting household: {}", e);
+                HttpResponse::InternalServerError().json(json!({
+                    "error": "Failed to delete household"
+                }))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
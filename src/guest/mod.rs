@@ -1,7 +1,12 @@
+mod household;
+
 use crate::auth::is_authenticated_as_author;
-use crate::db::Guest;
+use crate::block;
+use crate::db::{GUEST_COLUMNS, Guest, Party, answers, feeds, series, waitlist};
+use crate::i18n;
 use actix_web::{HttpRequest, HttpResponse, Responder, Scope, delete, get, post, web};
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::TransactionBehavior;
 use serde::Deserialize;
 use serde_json::json;
 use uuid::Uuid;
@@ -17,15 +22,13 @@ async fn get_guests(
 
         // Get all guests for this author
         let guests_result = conn
-            .prepare("SELECT id, salutation, first, last, email, note, author, selfcreated FROM guests WHERE author = ?1 ORDER BY last, first")
+            .prepare(&format!("SELECT {} FROM guests WHERE author = ?1 ORDER BY last, first", GUEST_COLUMNS))
             .and_then(|mut stmt| {
                 let guest_iter = stmt.query_map([&author_id], Guest::from_row)?;
 
                 let mut guests = Vec::new();
-                for guest_result in guest_iter {
-                    if let Ok(guest) = guest_result {
-                        guests.push(guest.to_json());
-                    }
+                for guest in guest_iter.flatten() {
+                    guests.push(guest.to_json());
                 }
                 Ok(guests)
             });
@@ -48,6 +51,8 @@ struct UpdateGuestForm {
     last: String,
     email: String,
     note: String,
+    #[serde(default)]
+    language: String, // Empty to use the language of the guest's browser
 }
 
 #[get("/{guest_id}")]
@@ -64,7 +69,7 @@ async fn get_guest_details(
 
         // Get guest details, ensuring it belongs to the authenticated author
         let guest = match conn
-            .prepare("SELECT id, salutation, first, last, email, note, author, selfcreated FROM guests WHERE id = ?1 AND author = ?2")
+            .prepare(&format!("SELECT {} FROM guests WHERE id = ?1 AND author = ?2", GUEST_COLUMNS))
             .and_then(|mut stmt| stmt.query_row([&guest_id, &author_id], Guest::from_row))
         {
             Ok(guest) => guest,
@@ -90,14 +95,18 @@ async fn update_guest(
 ) -> impl Responder {
     let guest_id = path.into_inner();
 
+    if !form.language.is_empty() && !i18n::is_supported(&form.language) {
+        return HttpResponse::BadRequest().json(json!({"error": "Unsupported language"}));
+    }
+
     // Check authentication
     if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
         let conn = pool.get().unwrap();
 
         // Update guest fields, ensuring it belongs to the authenticated author
         let result = conn
-            .prepare("UPDATE guests SET salutation = ?1, first = ?2, last = ?3, email = ?4, note = ?5 WHERE id = ?6 AND author = ?7")
-            .and_then(|mut stmt| stmt.execute([&form.salutation, &form.first, &form.last, &form.email, &form.note, &guest_id, &author_id]));
+            .prepare("UPDATE guests SET salutation = ?1, first = ?2, last = ?3, email = ?4, note = ?5, language = ?6 WHERE id = ?7 AND author = ?8")
+            .and_then(|mut stmt| stmt.execute([&form.salutation, &form.first, &form.last, &form.email, &form.note, &form.language, &guest_id, &author_id]));
 
         match result {
             Ok(rows_affected) => {
@@ -134,14 +143,28 @@ async fn delete_guest(
 
     // Check authentication
     if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
-        let conn = pool.get().unwrap();
+        let mut conn = pool.get().unwrap();
 
         // Start a transaction to delete guest and related invitations
-        let tx = conn.unchecked_transaction().unwrap();
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .unwrap();
 
-        // Delete all invitations for this guest first
-        let delete_invitations_result = tx
-            .prepare("DELETE FROM invitations WHERE guest_id = ?1")
+        // Remember the guest's parties, their spots may go to guests on the waitlist
+        let party_ids: Vec<String> = tx
+            .prepare("SELECT party_id FROM invitations WHERE guest_id = ?1")
+            .and_then(|mut stmt| {
+                stmt.query_map([&guest_id], |row| row.get(0))?
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Delete all answers, waitlist entries, series guest lists and invitations for this guest first
+        let delete_invitations_result = answers::delete_for_guest(&tx, &guest_id, None)
+            .and_then(|_| waitlist::delete_for_guest(&tx, &guest_id, None))
+            .and_then(|_| series::delete_guest(&tx, &guest_id))
+            .and_then(|_| feeds::delete_guest(&tx, &guest_id))
+            .and_then(|_| tx.prepare("DELETE FROM invitations WHERE guest_id = ?1"))
             .and_then(|mut stmt| stmt.execute([&guest_id]));
 
         // Delete the guest
@@ -152,7 +175,20 @@ async fn delete_guest(
         match (delete_invitations_result, delete_guest_result) {
             (Ok(_), Ok(rows_affected)) => {
                 if rows_affected > 0 {
+                    let mut promotions = Vec::new();
+                    for party_id in &party_ids {
+                        if let Ok(party) = Party::load(&tx, party_id) {
+                            match waitlist::promote(&tx, &party) {
+                                Ok(promoted) => promotions.push(promoted),
+                                Err(e) => eprintln!("Database error promoting waitlist: {}", e),
+                            }
+                        }
+                    }
                     tx.commit().unwrap();
+
+                    for promoted in promotions {
+                        crate::invitation::notify_promoted(pool.clone(), promoted).await;
+                    }
                     HttpResponse::Ok().json(json!({
                         "status": "success",
                         "message": "Guest deleted successfully"
@@ -235,123 +271,80 @@ async fn create_public_guest(
     req: actix_web::HttpRequest,
 ) -> impl Responder {
     let party_id = path.into_inner();
-    let language = crate::detect_language(&req);
+    let language = i18n::detect_language(&req);
 
-    let conn = match pool.get() {
+    let mut conn = match pool.get() {
         Ok(conn) => conn,
         Err(_) => return HttpResponse::InternalServerError().json(json!({
             "error": "Database connection failed"
         })),
     };
 
+    // Checking the party, counting and inserting happen in one IMMEDIATE transaction so
+    // concurrent sign-ups or party changes can't push the party past its guest limit
+    let tx = match conn.transaction_with_behavior(TransactionBehavior::Immediate) {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Database error starting transaction: {}", e);
+            return HttpResponse::InternalServerError().json(json!({
+                "error": "Failed to create guest"
+            }));
+        }
+    };
+
     // Verify party exists and is public
-    let (author_id, max_guests) = match conn
-        .prepare("SELECT author, public, max_guests FROM parties WHERE id = ?1")
-        .and_then(|mut stmt| {
-            stmt.query_row([&party_id], |row| {
-                let author: String = row.get(0)?;
-                let is_public: bool = row.get(1)?;
-                let max_guests: i64 = row.get(2)?;
-                Ok((author, is_public, max_guests))
-            })
-        }) {
-        Ok((author, is_public, max_guests)) => {
-            if !is_public {
-                let error_msg = match language.as_str() {
-                    "de" => "Diese Party ist nicht öffentlich",
-                    _ => "This party is not public"
-                };
+    let party = match Party::load(&tx, &party_id) {
+        Ok(party) => {
+            if !party.public {
                 return HttpResponse::Forbidden().json(json!({
-                    "error": error_msg
+                    "error": i18n::text(&language, "error-party-not-public")
                 }));
             }
-            (author, max_guests)
+            party
         }
         Err(_) => {
-            let error_msg = match language.as_str() {
-                "de" => "Party nicht gefunden",
-                _ => "Party not found"
-            };
             return HttpResponse::NotFound().json(json!({
-                "error": error_msg
+                "error": i18n::text(&language, "error-party-not-found")
             }));
         }
     };
+    let language = i18n::language_for_party(&req, &party.language);
+    let author_id = &party.author;
+    let max_guests = party.max_guests;
 
     // Check max guests limit if set
     // Only count guests who have RSVP'd "yes" (attendance answer = 0)
     if max_guests > 0 {
-        let guest_count = conn
-            .prepare("SELECT invitation_blocks FROM parties WHERE id = ?1")
-            .and_then(|mut stmt| {
-                stmt.query_row([&party_id], |row| {
-                    let invitation_blocks: String = row.get(0)?;
-                    Ok(invitation_blocks)
-                })
-            })
-            .ok()
-            .and_then(|blocks_json| {
-                // Parse invitation blocks to find attendance block ID
-                serde_json::from_str::<serde_json::Value>(&blocks_json)
-                    .ok()
-                    .and_then(|blocks| {
-                        blocks.as_array().and_then(|arr| {
-                            arr.iter().find_map(|block| {
-                                if block.get("template")?.as_str()? == "attendance" {
-                                    block.get("id")?.as_str().map(String::from)
-                                } else {
-                                    None
-                                }
-                            })
-                        })
-                    })
-            });
+        let attendance_block_id = block::attendance_block(&party.invitation_blocks).map(|block| &block.id);
 
-        let yes_count = if let Some(attendance_id) = guest_count {
-            // Count only guests who answered "yes" (0) to the attendance block
-            conn.prepare("SELECT invitation_block_answers FROM invitations WHERE party_id = ?1")
-                .and_then(|mut stmt| {
-                    let answer_iter = stmt.query_map([&party_id], |row| {
-                        let answers: String = row.get(0)?;
-                        Ok(answers)
-                    })?;
-
-                    let mut count = 0i64;
-                    for answer_result in answer_iter {
-                        if let Ok(answer_str) = answer_result {
-                            if let Ok(answer_json) = serde_json::from_str::<serde_json::Value>(&answer_str) {
-                                if let Some(attendance_answer) = answer_json.get(&attendance_id) {
-                                    if let Some(answer_val) = attendance_answer.as_i64() {
-                                        if answer_val == 0 {
-                                            count += 1;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Ok(count)
-                })
-                .unwrap_or(0)
+        let count_result = if let Some(attendance_id) = attendance_block_id {
+            // Count only guests who answered "yes" (0) to the attendance block, with their companions
+            answers::headcount(&tx, &party_id, attendance_id, None)
         } else {
             // No attendance block - count all invitations
-            conn.prepare("SELECT COUNT(*) FROM invitations WHERE party_id = ?1")
+            tx.prepare("SELECT COUNT(*) FROM invitations WHERE party_id = ?1")
                 .and_then(|mut stmt| {
                     stmt.query_row([&party_id], |row| {
                         let count: i64 = row.get(0)?;
                         Ok(count)
                     })
                 })
-                .unwrap_or(0)
+        };
+        let yes_count = match count_result {
+            Ok(count) => count,
+            Err(e) => {
+                eprintln!("Database error counting guests: {}", e);
+                return HttpResponse::InternalServerError().json(json!({
+                    "error": "Failed to create guest"
+                }));
+            }
         };
 
-        if yes_count >= max_guests {
-            let error_msg = match language.as_str() {
-                "de" => "Diese Party hat die maximale Anzahl an Gästen erreicht",
-                _ => "This party has reached its maximum number of guests"
-            };
+        // With a waitlist the guest can still sign up and gets queued once they answer "yes"
+        let can_join_waitlist = party.waitlist && attendance_block_id.is_some();
+        if yes_count >= max_guests && !can_join_waitlist {
             return HttpResponse::Forbidden().json(json!({
-                "error": error_msg
+                "error": i18n::text(&language, "error-party-full")
             }));
         }
     }
@@ -360,8 +353,8 @@ async fn create_public_guest(
     let guest_id = Uuid::new_v4().to_string();
     let invitation_id = Uuid::new_v4().to_string();
 
-    let guest_result = conn
-        .prepare("INSERT INTO guests (id, salutation, first, last, email, note, author, selfcreated) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")
+    let guest_result = tx
+        .prepare("INSERT INTO guests (id, salutation, first, last, email, note, author, selfcreated, detected_language) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)")
         .and_then(|mut stmt| {
             stmt.execute([
                 &guest_id,
@@ -370,8 +363,9 @@ async fn create_public_guest(
                 &form.last,
                 &form.email,
                 "",  // empty note
-                &author_id,
+                author_id,
                 "1", // selfcreated = true
+                &language,
             ])
         });
 
@@ -383,17 +377,17 @@ async fn create_public_guest(
     }
 
     // Create invitation for the guest (without answers - they'll be saved via save_answers endpoint)
-    let invitation_result = conn
-        .prepare("INSERT INTO invitations (id, guest_id, party_id, invitation_block_answers, organizer) VALUES (?1, ?2, ?3, ?4, ?5)")
+    let invitation_result = tx
+        .prepare("INSERT INTO invitations (id, guest_id, party_id, organizer) VALUES (?1, ?2, ?3, ?4)")
         .and_then(|mut stmt| {
             stmt.execute([
                 &invitation_id,
                 &guest_id,
                 &party_id,
-                "", // empty answers - will be populated by save_answers endpoint
                 "0", // organizer = false
             ])
-        });
+        })
+        .and_then(|_| tx.commit());
 
     match invitation_result {
         Ok(_) => HttpResponse::Ok().json(json!({
@@ -412,7 +406,13 @@ async fn create_public_guest(
 }
 
 pub fn subroutes() -> Scope {
+    // Household routes come first so `/households` isn't taken for a guest ID
     web::scope("/guest")
+        .service(household::get_households)
+        .service(household::create_household)
+        .service(household::get_household_details)
+        .service(household::update_household)
+        .service(household::delete_household)
         .service(get_guests)
         .service(create_guest)
         .service(get_guest_details)
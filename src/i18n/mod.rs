@@ -0,0 +1,264 @@
+use crate::templating::{self, Variables};
+use actix_web::cookie::{Cookie, SameSite, time::Duration};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::sync::LazyLock;
+
+// Every language has a message catalog `locales/<language>.catalog` with one `key = value`
+// line per message. Lines starting with `#` are comments and `\n` in a value is a line break.
+// Adding a language only takes a new catalog; missing messages fall back to English.
+//
+// Pages exist once under `pages/` and refer to messages with `{{t key}}`. Values are trusted
+// and may contain HTML. Messages used by handlers may contain template variables (see
+// `templating`), e.g. `{{max}}`.
+//
+// A page is shown in the language the visitor picked (`?lang=de`, remembered in a cookie),
+// otherwise in the party's language if the organizer set one, otherwise in the browser's.
+
+pub const LOCALES_DIR: &str = "locales";
+pub const DEFAULT_LANGUAGE: &str = "en";
+pub const LANGUAGE_COOKIE: &str = "language";
+
+type Catalog = HashMap<String, String>;
+
+/// All catalogs by language code, loaded on first use
+static CATALOGS: LazyLock<BTreeMap<String, Catalog>> = LazyLock::new(load_catalogs);
+
+fn parse_catalog(source: &str) -> Catalog {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().replace("\\n", "\n")))
+        .collect()
+}
+
+fn load_catalogs() -> BTreeMap<String, Catalog> {
+    let mut catalogs = BTreeMap::new();
+    let entries = match fs::read_dir(LOCALES_DIR) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("[I18N ERROR] Failed to read {}: {}", LOCALES_DIR, e);
+            return catalogs;
+        }
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path
+            .extension()
+            .is_none_or(|extension| extension != "catalog")
+        {
+            continue;
+        }
+        let Some(language) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        match fs::read_to_string(&path) {
+            Ok(source) => {
+                catalogs.insert(language.to_lowercase(), parse_catalog(&source));
+            }
+            Err(e) => eprintln!("[I18N ERROR] Failed to read {}: {}", path.display(), e),
+        }
+    }
+    catalogs
+}
+
+/// Loads the catalogs now, so problems show up at startup. Returns the available languages.
+pub fn init() -> Vec<&'static str> {
+    languages().collect()
+}
+
+/// Codes of all languages with a catalog, in alphabetical order
+pub fn languages() -> impl Iterator<Item = &'static str> {
+    CATALOGS.keys().map(String::as_str)
+}
+
+pub fn is_supported(language: &str) -> bool {
+    CATALOGS.contains_key(language)
+}
+
+/// The message `key` in `language`, falling back to English and then to the key itself
+pub fn text<'a>(language: &str, key: &'a str) -> &'a str {
+    [language, DEFAULT_LANGUAGE]
+        .iter()
+        .filter_map(|language| CATALOGS.get(*language)?.get(key))
+        .map(String::as_str)
+        .next()
+        .unwrap_or(key)
+}
+
+/// The message `key` in `language` with its template variables filled in
+pub fn text_with(language: &str, key: &str, variables: &Variables) -> String {
+    templating::render(text(language, key), variables)
+}
+
+/// The best supported language for an `Accept-Language` header (e.g. "de-DE,de;q=0.8,en;q=0.6").
+/// Tags are tried by descending quality; a region-specific tag also matches its base language.
+pub fn negotiate(accept_language: &str) -> Option<&'static str> {
+    let mut preferences: Vec<(&str, f32)> = accept_language
+        .split(',')
+        .filter_map(|part| {
+            let mut fields = part.split(';');
+            let tag = fields.next()?.trim();
+            let quality = fields
+                .filter_map(|field| field.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+    // Stable, so tags of equal quality keep the browser's order
+    preferences.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    preferences.into_iter().find_map(|(tag, _)| {
+        let tag = tag.to_lowercase();
+        let base = tag.split('-').next().unwrap_or_default();
+        languages().find(|&language| language == tag || language == base)
+    })
+}
+
+fn supported(language: &str) -> Option<&'static str> {
+    let language = language.trim().to_lowercase();
+    languages().find(|&supported| supported == language)
+}
+
+/// The language picked with the `lang` query parameter
+fn requested_language(req: &HttpRequest) -> Option<&'static str> {
+    req.query_string()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("lang="))
+        .and_then(supported)
+}
+
+/// The language the visitor picked, now or on an earlier visit
+fn chosen_language(req: &HttpRequest) -> Option<&'static str> {
+    requested_language(req).or_else(|| {
+        req.cookie(LANGUAGE_COOKIE)
+            .and_then(|cookie| supported(cookie.value()))
+    })
+}
+
+fn browser_language(req: &HttpRequest) -> &'static str {
+    req.headers()
+        .get("accept-language")
+        .and_then(|header| header.to_str().ok())
+        .and_then(negotiate)
+        .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+/// The visitor's language: the one they picked, else the browser's, English if none of
+/// theirs is supported
+pub fn detect_language(req: &HttpRequest) -> String {
+    chosen_language(req)
+        .unwrap_or_else(|| browser_language(req))
+        .to_string()
+}
+
+/// The language to show a party in: the one the visitor picked, else the party's language
+/// (empty if the organizer left it to the guests), else the browser's
+pub fn language_for_party(req: &HttpRequest, party_language: &str) -> String {
+    chosen_language(req)
+        .or_else(|| supported(party_language))
+        .unwrap_or_else(|| browser_language(req))
+        .to_string()
+}
+
+/// Replaces `{{t key}}` with messages, `{{language}}` with the language code and
+/// `{{language_options}}` with an `<option>` for every language, the current one selected
+fn fill_page(template: &str, language: &str) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find("}}") else {
+            break;
+        };
+        let tag = rest[2..end].trim();
+        match tag.strip_prefix("t ") {
+            Some(key) => output.push_str(text(language, key.trim())),
+            None if tag == "language" => output.push_str(language),
+            None if tag == "language_options" => {
+                for code in languages() {
+                    output.push_str(&format!(
+                        "<option value=\"{}\"{}>{}</option>",
+                        code,
+                        if code == language { " selected" } else { "" },
+                        text(code, "language-name")
+                    ));
+                }
+            }
+            None => output.push_str(&rest[..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// The page `pages/<name>.html` in `language`
+pub fn page(name: &str, language: &str) -> String {
+    match fs::read_to_string(format!("pages/{}.html", name)) {
+        Ok(template) => fill_page(&template, language),
+        Err(_) => "<h1>404: File Not Found</h1>".to_string(),
+    }
+}
+
+/// Responds with the page `name` in `language`. A language picked with `?lang=` is remembered
+/// for a year, so the visitor's other pages and later visits use it as well.
+pub fn page_response(
+    req: &HttpRequest,
+    mut response: HttpResponseBuilder,
+    name: &str,
+    language: &str,
+) -> HttpResponse {
+    if let Some(requested) = requested_language(req) {
+        response.cookie(
+            Cookie::build(LANGUAGE_COOKIE, requested)
+                .path("/")
+                .max_age(Duration::days(365))
+                .same_site(SameSite::Lax)
+                .finish(),
+        );
+    }
+    response.content_type("text/html").body(page(name, language))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_by_quality() {
+        assert_eq!(negotiate("de-DE,de;q=0.8,en;q=0.6"), Some("de"));
+        assert_eq!(negotiate("fr;q=0.9, en;q=0.5, de;q=0.7"), Some("de"));
+        assert_eq!(negotiate("en;q=0.4,de"), Some("de"));
+        // Spaces and other parameters don't get in the way
+        assert_eq!(negotiate(" en-GB ; foo=bar ; q=0.3 , de ; q=0.2 "), Some("en"));
+    }
+
+    #[test]
+    fn leaves_out_excluded_and_unknown_languages() {
+        assert_eq!(negotiate("fr,en;q=0"), None);
+        assert_eq!(negotiate("de;q=0.0,en;q=0.1"), Some("en"));
+        assert_eq!(negotiate("fr-CA,fr;q=0.9,*;q=0.5"), None);
+        assert_eq!(negotiate(""), None);
+    }
+
+    #[test]
+    fn matches_regions_to_their_language() {
+        assert_eq!(negotiate("EN-us"), Some("en"));
+        assert_eq!(negotiate("de-AT"), Some("de"));
+        assert_eq!(negotiate("de-CH-1901;q=0.9"), Some("de"));
+    }
+
+    #[test]
+    fn keeps_the_order_of_equal_qualities() {
+        assert_eq!(negotiate("en,de"), Some("en"));
+        assert_eq!(negotiate("de,en"), Some("de"));
+        assert_eq!(negotiate("en;q=0.5,de;q=0.5"), Some("en"));
+        // Unreadable qualities count as 1
+        assert_eq!(negotiate("en;q=0.9,de;q=high"), Some("de"));
+    }
+}
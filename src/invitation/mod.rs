@@ -1,57 +1,78 @@
-use crate::db::Invitation;
-use crate::detect_language;
+use crate::attachment;
+use crate::block::{self, BlockKind, answer, markdown, poll};
+use crate::calendar::{self, Calendar, Method};
+use crate::db::feeds::{self, FeedOwner};
+use crate::db::{Guest, Invitation, Party, answers, households, waitlist};
+use crate::i18n::{self, detect_language};
+use crate::db::series;
+use crate::templating;
+use crate::timezone;
+use chrono_tz::Tz;
 use actix_web::{HttpResponse, Responder, Scope, get, post, web};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::TransactionBehavior;
 use serde::Deserialize;
 use serde_json::json;
-use std::fs;
 
-// Helper function to format date and time based on language
+// Helper function to format date and time based on language, using the catalog's
+// `date-format` and `time-format` (chrono format strings)
 // Expects ISO datetime format: YYYY-MM-DD or YYYY-MM-DDTHH:MM or YYYY-MM-DDTHH:MM:SS
-fn format_date_time(date_str: &str, language: &str) -> (String, String) {
+pub(crate) fn format_date_time(date_str: &str, language: &str) -> (String, String) {
     if date_str.is_empty() {
         return (String::new(), String::new());
     }
 
-    // Try to parse as full datetime first (with seconds)
-    if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S") {
-        let formatted_date = match language {
-            "de" => datetime.format("%d.%m.%Y").to_string(), // DD.MM.YYYY
-            _ => datetime.format("%m/%d/%Y").to_string(),    // MM/DD/YYYY (English)
-        };
-        let formatted_time = match language {
-            "de" => datetime.format("%H:%M").to_string(), // 24-hour
-            _ => datetime.format("%I:%M %p").to_string(), // 12-hour with AM/PM
-        };
-        return (formatted_date, formatted_time);
-    }
-
-    // Try to parse as datetime without seconds (datetime-local format)
-    if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M") {
-        let formatted_date = match language {
-            "de" => datetime.format("%d.%m.%Y").to_string(),
-            _ => datetime.format("%m/%d/%Y").to_string(),
-        };
-        let formatted_time = match language {
-            "de" => datetime.format("%H:%M").to_string(),
-            _ => datetime.format("%I:%M %p").to_string(),
-        };
-        return (formatted_date, formatted_time);
+    // A broken format string in a catalog would make chrono panic, so fall back to ISO 8601
+    let valid = |format: &&str| {
+        chrono::format::StrftimeItems::new(format)
+            .all(|item| !matches!(item, chrono::format::Item::Error))
+    };
+    let date_format = Some(i18n::text(language, "date-format"))
+        .filter(valid)
+        .unwrap_or("%Y-%m-%d");
+    let time_format = Some(i18n::text(language, "time-format"))
+        .filter(valid)
+        .unwrap_or("%H:%M");
+
+    // Try to parse as full datetime first (with seconds), then without seconds (datetime-local format)
+    if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M"))
+    {
+        return (
+            datetime.format(date_format).to_string(),
+            datetime.format(time_format).to_string(),
+        );
     }
 
     // Fallback to date-only format
     if let Ok(date) = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-        let formatted_date = match language {
-            "de" => date.format("%d.%m.%Y").to_string(),
-            _ => date.format("%m/%d/%Y").to_string(),
-        };
-        return (formatted_date, String::new());
+        return (date.format(date_format).to_string(), String::new());
     }
 
     (String::new(), String::new())
 }
 
+/// Like `format_date_time` for a date in `party_zone`, shown in `viewer_zone`. Converted times
+/// carry the zone's abbreviation, so guests abroad can tell it isn't the local time at the party.
+pub(crate) fn format_date_time_in(
+    date_str: &str,
+    language: &str,
+    party_zone: Tz,
+    viewer_zone: Tz,
+) -> (String, String) {
+    match series::parse_date(date_str) {
+        Some(datetime) if party_zone != viewer_zone => {
+            let converted = timezone::convert(datetime, party_zone, viewer_zone);
+            let (date, time) =
+                format_date_time(&converted.format("%Y-%m-%dT%H:%M:%S").to_string(), language);
+            let abbreviation = timezone::abbreviation(converted, viewer_zone);
+            (date, format!("{} {}", time, abbreviation))
+        }
+        _ => format_date_time(date_str, language),
+    }
+}
+
 #[get("/{invitation_id}")]
 pub async fn invitation_page(
     path: web::Path<String>,
@@ -66,74 +87,50 @@ pub async fn invitation_page(
         Err(_) => return HttpResponse::InternalServerError().body("Database connection failed"),
     };
 
-    // First check if it's a public party ID
-    let is_public_party = conn
-        .prepare("SELECT public FROM parties WHERE id = ?1")
-        .and_then(|mut stmt| {
-            stmt.query_row([&id], |row| {
-                let is_public: bool = row.get(0)?;
-                Ok(is_public)
-            })
+    // First check if it's a public party ID, which is served for anonymous viewing
+    let public_party_language = conn
+        .prepare("SELECT language FROM parties WHERE id = ?1 AND public = 1")
+        .and_then(|mut stmt| stmt.query_row([&id], |row| row.get::<_, String>(0)));
+
+    // Then if it is an invitation, or a household's link that opens all of its members' invitations
+    let party_language = public_party_language
+        .or_else(|_| {
+            conn.query_row(
+                "SELECT p.language FROM invitations i INNER JOIN parties p ON i.party_id = p.id WHERE i.id = ?1",
+                [&id],
+                |row| row.get(0),
+            )
         })
-        .unwrap_or(false);
-
-    if is_public_party {
-        // This is a public party - serve the invitation page for anonymous viewing
-        let language = detect_language(&req);
-        let filename = match language.as_str() {
-            "de" => "pages/de/invitation_de.html",
-            _ => "pages/en/invitation_en.html",
-        };
-
-        let html_content = fs::read_to_string(filename)
-            .unwrap_or_else(|_| "<h1>404: File Not Found</h1>".to_string());
-        return HttpResponse::Ok()
-            .content_type("text/html")
-            .body(html_content);
-    }
-
-    // Check if invitation exists
-    let invitation_exists = conn
-        .prepare("SELECT COUNT(*) FROM invitations WHERE id = ?1")
-        .and_then(|mut stmt| {
-            stmt.query_row([&id], |row| {
-                let count: i32 = row.get(0)?;
-                Ok(count > 0)
-            })
-        })
-        .unwrap_or(false);
+        .or_else(|_| {
+            conn.query_row(
+                "SELECT p.language FROM household_invitations hi INNER JOIN parties p ON hi.party_id = p.id WHERE hi.id = ?1",
+                [&id],
+                |row| row.get(0),
+            )
+        });
 
-    if !invitation_exists {
-        let language = detect_language(&req);
-        let filename = match language.as_str() {
-            "de" => "pages/de/not_found_de.html",
-            _ => "pages/en/not_found_en.html",
-        };
-        let html_content =
-            fs::read_to_string(filename).unwrap_or_else(|_| "<h1>404: Not Found</h1>".to_string());
-        return HttpResponse::NotFound()
-            .content_type("text/html")
-            .body(html_content);
+    match party_language {
+        Ok(party_language) => {
+            let language = i18n::language_for_party(&req, &party_language);
+            i18n::page_response(&req, HttpResponse::Ok(), "invitation", &language)
+        }
+        Err(_) => {
+            let language = detect_language(&req);
+            i18n::page_response(&req, HttpResponse::NotFound(), "not_found", &language)
+        }
     }
+}
 
-    // Detect language from Accept-Language header
-    let language = detect_language(&req);
-
-    let filename = match language.as_str() {
-        "de" => "pages/de/invitation_de.html",
-        _ => "pages/en/invitation_en.html", // Default to English
-    };
-
-    let html_content =
-        fs::read_to_string(filename).unwrap_or_else(|_| "<h1>404: File Not Found</h1>".to_string());
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(html_content)
+#[derive(Deserialize)]
+struct DetailsQuery {
+    // The guest's own time zone, which dates are shown in
+    time_zone: Option<String>,
 }
 
 #[get("/{invitation_id}")]
 async fn details(
     path: web::Path<String>,
+    query: web::Query<DetailsQuery>,
     req: actix_web::HttpRequest,
     db: web::Data<Pool<SqliteConnectionManager>>,
 ) -> impl Responder {
@@ -145,51 +142,63 @@ async fn details(
     };
 
     // Check if this is a public party first
-    let public_party_check = conn
-        .prepare("SELECT id, invitation_blocks, date, name, author FROM parties WHERE id = ?1 AND public = 1")
-        .and_then(|mut stmt| {
-            stmt.query_row([&id], |row| {
-                let party_id: String = row.get(0)?;
-                let invitation_blocks: String = row.get(1)?;
-                let date: String = row.get(2)?;
-                let party_name: String = row.get(3)?;
-                let author_id: String = row.get(4)?;
-                Ok((party_id, invitation_blocks, date, party_name, author_id))
-            })
-        });
+    let public_party = match Party::load(&conn, &id) {
+        Ok(party) if party.public => Some(party),
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => {
+            eprintln!("Database error loading party: {}", e);
+            return HttpResponse::InternalServerError().body("Party data could not be loaded");
+        }
+    };
 
-    if let Ok((party_id, invitation_blocks, party_date, party_name, author_id)) = public_party_check
-    {
+    if let Some(party) = public_party {
         // This is a public party - return anonymous guest data
-        let language = detect_language(&req);
-        let (formatted_date, formatted_time) = format_date_time(&party_date, &language);
+        let language = i18n::language_for_party(&req, &party.language);
+        let viewer_zone = query
+            .time_zone
+            .as_deref()
+            .and_then(timezone::parse)
+            .unwrap_or(party.zone());
+        let (formatted_date, formatted_time) =
+            format_date_time_in(&party.date, &language, party.zone(), viewer_zone);
 
         // Get author name separately
         let author_name = conn
             .prepare("SELECT name FROM authors WHERE id = ?1")
             .and_then(|mut stmt| {
-                stmt.query_row([&author_id], |row| {
+                stmt.query_row([&party.author], |row| {
                     let name: String = row.get(0)?;
                     Ok(name)
                 })
             })
             .unwrap_or_else(|_| "Unknown".to_string());
 
+        let mut variables =
+            templating::Variables::for_party(&party, &author_name, &language, viewer_zone);
+        variables
+            .set_guest("", "Anonymous", "")
+            .set("invitation_url", templating::invitation_url(&party.id));
+
         let response = json!({
-            "invitation_blocks": serde_json::from_str::<serde_json::Value>(&invitation_blocks).unwrap_or(json!([])),
+            "invitation_blocks": markdown::render_blocks(&party.invitation_blocks, &variables),
             "invitation_block_answers": json!({}),
             "other_guests_answers": json!([]),
+            "date_polls": json!({}),
+            "attachments": attachment::for_blocks(&conn, &party.invitation_blocks),
             "guest_name": "Anonymous",
             "guest_salutation": "",
             "guest_first": "Anonymous",
             "guest_last": "",
-            "party_name": party_name,
+            "party_name": party.name,
             "party_date": formatted_date,
             "party_time": formatted_time,
+            "party_time_zone": party.zone().name(),
             "author_name": author_name,
             "is_organizer": false,
             "is_public_view": true,
-            "party_id": party_id,
+            "party_id": party.id,
+            "max_companions": party.max_companions,
+            "ask_companion_names": party.ask_companion_names,
         });
 
         return HttpResponse::Ok()
@@ -197,16 +206,52 @@ async fn details(
             .body(response.to_string());
     }
 
+    // A household link shows the invitation of its first member, the others can be switched to
+    let id = match households::resolve_link(&conn, &id) {
+        Ok(Some(link)) => {
+            match households::member_invitations(&conn, &link.household_id, &link.party_id) {
+                Ok(members) if !members.is_empty() => members[0].invitation_id.clone(),
+                Ok(_) => return HttpResponse::BadRequest().body("Invitation not found"),
+                Err(e) => {
+                    eprintln!("Database error loading household members: {}", e);
+                    return HttpResponse::InternalServerError()
+                        .body("Household could not be loaded");
+                }
+            }
+        }
+        Ok(None) => id,
+        Err(e) => {
+            eprintln!("Database error loading household invitation: {}", e);
+            return HttpResponse::InternalServerError().body("Household could not be loaded");
+        }
+    };
+
     // Not a public party, treat as regular invitation
-    let invitation = match conn
-        .prepare("SELECT id, guest_id, party_id, invitation_block_answers, organizer FROM invitations WHERE id = ?1")
-        .and_then(|mut stmt| {
-            stmt.query_row([&id], Invitation::from_row)
-        }) {
+    let invitation = match Invitation::load(&conn, &id) {
         Ok(invitation) => invitation,
         Err(_) => return HttpResponse::BadRequest().body("Invitation not found"),
     };
 
+    // Members of a household can switch between each other's invitations
+    let (household, invitation_url) = match households::link_for_invitation(&conn, &invitation.id) {
+        Ok(Some(link)) => {
+            let members =
+                households::member_invitations(&conn, &link.household_id, &link.party_id)
+                    .unwrap_or_default();
+            let household = json!({
+                "id": link.id,
+                "name": link.household_name,
+                "members": members,
+            });
+            (household, templating::invitation_url(&link.id))
+        }
+        Ok(None) => (serde_json::Value::Null, templating::invitation_url(&invitation.id)),
+        Err(e) => {
+            eprintln!("Database error loading household invitation: {}", e);
+            (serde_json::Value::Null, templating::invitation_url(&invitation.id))
+        }
+    };
+
     // Get guest information for personalization
     let (guest_salutation, guest_first, guest_last) = match conn
         .prepare("SELECT salutation, first, last FROM guests WHERE id = ?1")
@@ -225,98 +270,101 @@ async fn details(
     // Create full name for backward compatibility
     let guest_name = format!("{} {}", guest_first, guest_last).trim().to_string();
 
-    // Get invitation blocks, party date, party name, and has_rsvp_block flag
-    let (invitation_blocks, party_date, party_name, author_id, has_rsvp_block) = match conn
-        .prepare("SELECT invitation_blocks, date, name, author, has_rsvp_block FROM parties WHERE id = ?1")
-        .and_then(|mut stmt| {
-            stmt.query_row([&invitation.party_id], |row| {
-                let invitation_blocks: String = row.get(0)?;
-                let date: String = row.get(1)?;
-                let party_name: String = row.get(2)?;
-                let author_id: String = row.get(3)?;
-                let has_rsvp_block: bool = row.get(4)?;
-                Ok((invitation_blocks, date, party_name, author_id, has_rsvp_block))
-            })
-        }) {
-        Ok(data) => data,
-        Err(_) => return HttpResponse::InternalServerError().body("Party data not found"),
+    // Get the party this invitation belongs to
+    let party = match Party::load(&conn, &invitation.party_id) {
+        Ok(party) => party,
+        Err(e) => {
+            eprintln!("Database error loading party: {}", e);
+            return HttpResponse::InternalServerError().body("Party data not found");
+        }
     };
 
+    // Remember the language the guest sees their invitation in for emails and push notifications
+    let language = i18n::language_for_party(&req, &party.language);
+    if let Err(e) = Guest::set_detected_language(&conn, &invitation.guest_id, &language) {
+        eprintln!("Database error saving guest language: {}", e);
+    }
+
     // Get author name separately
     let author_name = conn
         .prepare("SELECT name FROM authors WHERE id = ?1")
         .and_then(|mut stmt| {
-            stmt.query_row([&author_id], |row| {
+            stmt.query_row([&party.author], |row| {
                 let name: String = row.get(0)?;
                 Ok(name)
             })
         })
         .unwrap_or_else(|_| "Unknown".to_string());
 
-    // Format date and time based on language
-    let language = detect_language(&req);
-    let (formatted_date, formatted_time) = format_date_time(&party_date, &language);
+    // Format date and time based on language, in the guest's time zone
+    let viewer_zone = query
+        .time_zone
+        .as_deref()
+        .and_then(timezone::parse)
+        .unwrap_or(party.zone());
+    let (formatted_date, formatted_time) =
+        format_date_time_in(&party.date, &language, party.zone(), viewer_zone);
 
     // Get all other guests' answers for the same party (excluding current invitation)
     // Include guest names for organizer view
-    let all_other_answers = match conn.prepare("SELECT i.invitation_block_answers, g.first, g.last FROM invitations i JOIN guests g ON i.guest_id = g.id WHERE i.party_id = ?1 AND i.id != ?2 AND i.invitation_block_answers != ''")
+    let mut party_answers = answers::load_for_party(&conn, &invitation.party_id).unwrap_or_default();
+    let all_other_answers = conn.prepare("SELECT i.id, g.first, g.last, i.companions, i.companion_names FROM invitations i JOIN guests g ON i.guest_id = g.id WHERE i.party_id = ?1 AND i.id != ?2")
         .and_then(|mut stmt| {
-            let answer_iter = stmt.query_map([&invitation.party_id, &id], |row| {
-                let answers: String = row.get(0)?;
+            let guest_iter = stmt.query_map([&invitation.party_id, &id], |row| {
+                let invitation_id: String = row.get(0)?;
                 let first: String = row.get(1)?;
                 let last: String = row.get(2)?;
                 let guest_name = format!("{} {}", first, last).trim().to_string();
-                Ok((answers, guest_name))
+                let companions: i64 = row.get(3)?;
+                let companion_names: String = row.get(4)?;
+                let companion_names: Vec<String> =
+                    serde_json::from_str(&companion_names).unwrap_or_default();
+                Ok((invitation_id, guest_name, companions, companion_names))
             })?;
 
             let mut all_answers = Vec::new();
-            for answer_result in answer_iter {
-                if let Ok((answer_str, guest_name)) = answer_result {
-                    if let Ok(answer_json) = serde_json::from_str::<serde_json::Value>(&answer_str) {
-                        all_answers.push((answer_json, guest_name));
-                    }
+            for (invitation_id, guest_name, companions, companion_names) in guest_iter.flatten() {
+                if let Some(answer_json) = party_answers.remove(&invitation_id) {
+                    let answer_json =
+                        answer::sanitize_stored_answers(&party.invitation_blocks, &answer_json);
+                    all_answers.push((answer_json, guest_name, companions, companion_names));
                 }
             }
             Ok(all_answers)
-        }) {
-        Ok(answers) => answers,
-        Err(_) => Vec::new(),
-    };
-
-    // Parse invitation blocks to determine which are public and find attendance block
-    let blocks_json =
-        serde_json::from_str::<serde_json::Value>(&invitation_blocks).unwrap_or(json!([]));
-    let mut public_block_ids = std::collections::HashSet::new();
-    let mut attendance_block_id: Option<String> = None;
-
-    if let Some(blocks_array) = blocks_json.as_array() {
-        for block in blocks_array.iter() {
-            // Get the block ID
-            if let Some(block_id) = block.get("id").and_then(|v| v.as_str()) {
-                // Check if this is an attendance block
-                if let Some(template) = block.get("template").and_then(|v| v.as_str()) {
-                    if template == "attendance" {
-                        attendance_block_id = Some(block_id.to_string());
-                    }
-                }
-
-                if let Some(content) = block.get("content") {
-                    // Try to parse content as JSON to check for public flag
-                    if let Ok(content_obj) =
-                        serde_json::from_str::<serde_json::Value>(content.as_str().unwrap_or("{}"))
-                    {
-                        if content_obj
-                            .get("public")
-                            .and_then(|v| v.as_bool())
-                            .unwrap_or(false)
-                        {
-                            public_block_ids.insert(block_id.to_string());
-                        }
-                    }
-                }
-            }
-        }
-    }
+        })
+        .unwrap_or_default();
+
+    // Determine which blocks are public and find the attendance block
+    let public_block_ids: std::collections::HashSet<&str> = party
+        .invitation_blocks
+        .iter()
+        .filter(|block| block.is_public())
+        .map(|block| block.id.as_str())
+        .collect();
+    let item_signup_block_ids: std::collections::HashSet<&str> = party
+        .invitation_blocks
+        .iter()
+        .filter(|block| block.is_item_signup())
+        .map(|block| block.id.as_str())
+        .collect();
+    let attendance_block_id =
+        block::attendance_block(&party.invitation_blocks).map(|block| block.id.clone());
+    let has_rsvp_block = party.has_rsvp_block;
+
+    let mut own_answers = invitation.load_answers(&conn);
+
+    // Date polls get a results matrix over everyone's answers, this guest's included
+    let poll_responses: Vec<(String, serde_json::Value)> = all_other_answers
+        .iter()
+        .map(|(guest_answers, guest_name, _, _)| (guest_name.clone(), guest_answers.clone()))
+        .chain(std::iter::once((
+            guest_name.clone(),
+            answer::sanitize_stored_answers(&party.invitation_blocks, &own_answers),
+        )))
+        .collect();
+    let date_polls = poll::results(&party.invitation_blocks, &poll_responses, |block| {
+        invitation.organizer || block.is_public()
+    });
 
     // Filter other guests' answers based on organizer status and visibility
     let filtered_other_answers: Vec<serde_json::Value> = if invitation.organizer {
@@ -324,7 +372,7 @@ async fn details(
         // Mark answers from guests who haven't RSVP'd yes with "(?)" (only if has_rsvp_block is true)
         all_other_answers
             .into_iter()
-            .map(|(guest_answers, guest_name)| {
+            .map(|(guest_answers, guest_name, companions, companion_names)| {
                 // Only check RSVP status if the party has an RSVP block
                 let has_rsvped_yes = if has_rsvp_block {
                     if let Some(attendance_id) = &attendance_block_id {
@@ -353,10 +401,15 @@ async fn details(
                             } else {
                                 format!("{} (?)", guest_name)
                             };
-                        let answer_with_name = json!({
+                        let mut answer_with_name = json!({
                             "answer": answer,
                             "guest_name": display_name
                         });
+                        // Attendance counts heads, so organizers also get the plus-ones
+                        if is_attendance_block {
+                            answer_with_name["companions"] = json!(companions);
+                            answer_with_name["companion_names"] = json!(companion_names);
+                        }
                         answer_with_names.insert(block_id.clone(), answer_with_name);
                     }
                 }
@@ -369,7 +422,7 @@ async fn details(
         // Exception: the attendance block itself should always show all responses if public
         all_other_answers
             .into_iter()
-            .filter_map(|(guest_answers, guest_name)| {
+            .filter_map(|(guest_answers, guest_name, companions, _)| {
                 // Only check RSVP status if the party has an RSVP block
                 let has_rsvped_yes = if has_rsvp_block {
                     if let Some(attendance_id) = &attendance_block_id {
@@ -388,18 +441,24 @@ async fn details(
                 let mut filtered_guest = serde_json::Map::new();
                 if let Some(answers_obj) = guest_answers.as_object() {
                     for (block_id, answer) in answers_obj {
-                        if public_block_ids.contains(block_id) {
+                        if public_block_ids.contains(block_id.as_str()) {
                             // For the attendance block itself, always show all responses
                             // For other blocks, only show if guest RSVP'd yes
                             let is_attendance_block =
                                 Some(block_id.as_str()) == attendance_block_id.as_deref();
+                            // Claimed items are taken whether or not the guest has said yes yet
+                            let is_item_signup = item_signup_block_ids.contains(block_id.as_str());
 
-                            if is_attendance_block || !has_rsvp_block || has_rsvped_yes {
+                            if is_attendance_block || is_item_signup || !has_rsvp_block || has_rsvped_yes {
                                 // Create answer object with guest name for public answers
-                                let answer_with_name = json!({
+                                let mut answer_with_name = json!({
                                     "answer": answer,
                                     "guest_name": guest_name
                                 });
+                                // Companion names stay private, only their number is shown
+                                if is_attendance_block {
+                                    answer_with_name["companions"] = json!(companions);
+                                }
                                 filtered_guest.insert(block_id.clone(), answer_with_name);
                             }
                         }
@@ -416,21 +475,59 @@ async fn details(
             .collect()
     };
 
+    let waitlist_position = waitlist::position(&conn, &invitation.id).ok().flatten();
+
+    // A waitlisted guest asked for "yes", so show that instead of the answer stored until promotion
+    if waitlist_position.is_some()
+        && let Some(attendance_id) = &attendance_block_id
+        && let Some(answers_obj) = own_answers.as_object_mut()
+    {
+        answers_obj.insert(attendance_id.clone(), json!(block::ATTENDANCE_YES));
+    }
+
+    let mut variables =
+        templating::Variables::for_party(&party, &author_name, &language, viewer_zone);
+    variables
+        .set_guest(&guest_salutation, &guest_first, &guest_last)
+        .set("invitation_url", invitation_url);
+
+    // Guests can subscribe to all of their invitations at once
+    let calendar_feed_url =
+        match feeds::token(&conn, &FeedOwner::Guest(invitation.guest_id.clone())) {
+            Ok(token) => Some(calendar::feed_url(&token)),
+            Err(e) => {
+                eprintln!("Database error loading calendar feed: {}", e);
+                None
+            }
+        };
+
     let response = json!({
-        "invitation_blocks": serde_json::from_str::<serde_json::Value>(&invitation_blocks).unwrap_or(json!([])),
-        "invitation_block_answers": invitation.get_answers_json(),
+        "invitation_blocks": markdown::render_blocks(&party.invitation_blocks, &variables),
+        "invitation_block_answers": answer::sanitize_stored_answers(&party.invitation_blocks, &own_answers),
+        "waitlist_position": waitlist_position,
+        "companions": invitation.companions,
+        "companion_names": invitation.companion_names,
+        "max_companions": party.max_companions,
+        "ask_companion_names": party.ask_companion_names,
+        "missing_required": answer::missing_required(&party.invitation_blocks, &own_answers),
         "other_guests_answers": filtered_other_answers,
+        "date_polls": date_polls,
+        "attachments": attachment::for_blocks(&conn, &party.invitation_blocks),
+        "invitation_id": invitation.id,
+        "household": household,
         "guest_id": invitation.guest_id,
         "guest_name": guest_name,
         "guest_salutation": guest_salutation,
         "guest_first": guest_first,
         "guest_last": guest_last,
-        "party_name": party_name,
+        "party_name": party.name,
         "party_date": formatted_date,
         "party_time": formatted_time,
+        "party_time_zone": party.zone().name(),
         "author_name": author_name,
         "is_organizer": invitation.organizer,
         "is_public_view": false,
+        "calendar_feed_url": calendar_feed_url,
     });
 
     HttpResponse::Ok()
@@ -441,6 +538,199 @@ async fn details(
 #[derive(Deserialize)]
 struct SaveAnswersRequest {
     answers: serde_json::Value,
+    // Left out by clients that don't know about plus-ones, which keeps the stored companions
+    companions: Option<i64>,
+    #[serde(default)]
+    companion_names: Vec<String>,
+}
+
+/// Whether guests can still answer: `Err` with the catalog key of the reason if the party is
+/// frozen or its deadline (in the party's time zone) has passed
+pub(crate) fn check_open(party: &Party) -> Result<(), &'static str> {
+    if party.frozen {
+        return Err("error-party-frozen");
+    }
+
+    let respond_until = &party.respond_until;
+    if !respond_until.is_empty() {
+        let now = timezone::now_in(party.zone());
+
+        // Try to parse as datetime first (with or without seconds)
+        let deadline_passed = if let Ok(deadline) =
+            chrono::NaiveDateTime::parse_from_str(respond_until, "%Y-%m-%dT%H:%M:%S")
+        {
+            now > deadline
+        } else if let Ok(deadline) =
+            chrono::NaiveDateTime::parse_from_str(respond_until, "%Y-%m-%dT%H:%M")
+        {
+            now > deadline
+        } else if let Ok(deadline_date) =
+            chrono::NaiveDate::parse_from_str(respond_until, "%Y-%m-%d")
+        {
+            // If only date, consider deadline as end of day
+            let deadline = deadline_date
+                .and_hms_opt(23, 59, 59)
+                .unwrap_or(deadline_date.and_hms_opt(0, 0, 0).unwrap());
+            now > deadline
+        } else {
+            false
+        };
+
+        if deadline_passed {
+            return Err("error-deadline-passed");
+        }
+    }
+    Ok(())
+}
+
+/// Why answers weren't saved
+pub(crate) enum SaveError {
+    /// No spot is left for the guest (or their companions); the catalog key of the reason
+    Refused(&'static str),
+    /// Items were claimed by others in the meantime, as a message for each item signup block
+    ItemsClaimed(serde_json::Map<String, serde_json::Value>),
+    Database(rusqlite::Error),
+}
+
+impl From<rusqlite::Error> for SaveError {
+    fn from(e: rusqlite::Error) -> Self {
+        SaveError::Database(e)
+    }
+}
+
+pub(crate) struct Saved {
+    /// The guest's place on the waitlist, if they asked for a spot and have to wait for one
+    pub waitlisted: Option<i64>,
+    /// Guests that moved up from the waitlist, to be told with `notify_promoted`
+    pub promoted: Vec<(String, String)>,
+}
+
+/// Saves validated answers (replacing all previous ones) and `companions` if given, after
+/// checking the party's capacity and item signups. A guest asking for a spot in a full party
+/// goes on the waitlist if it has one; a guest giving up a spot hands it on.
+pub(crate) fn store_answers(
+    conn: &mut rusqlite::Connection,
+    party: &Party,
+    id: &str,
+    mut validated_answers: serde_json::Map<String, serde_json::Value>,
+    companions: Option<(i64, Vec<String>)>,
+    language: &str,
+) -> Result<Saved, SaveError> {
+    let party_id = &party.id;
+    let attendance_block_id = block::attendance_block(&party.invitation_blocks).map(|block| &block.id);
+    let max_guests = party.max_guests;
+
+    // The capacity check and the write happen in one IMMEDIATE transaction, which takes the
+    // database write lock up front, so two guests saying "yes" at once can't both see a free spot
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    let current_companions = Invitation::load(&tx, id)?.companions;
+    let new_companions = companions.as_ref().map_or(current_companions, |(count, _)| *count);
+    let current_attendance_answer = attendance_block_id.and_then(|attendance_id| {
+        answers::load_one(&tx, id, attendance_id)
+            .ok()
+            .flatten()
+    });
+    let was_attending = current_attendance_answer.as_ref().and_then(|ans| ans.as_i64())
+        == Some(block::ATTENDANCE_YES);
+    let wants_to_attend = attendance_block_id
+        .and_then(|attendance_id| validated_answers.get(attendance_id))
+        .and_then(|v| v.as_i64())
+        == Some(block::ATTENDANCE_YES);
+    let mut waitlisted = false;
+
+    // Items are claimed in the same transaction, so two guests can't both take the last one.
+    // Claims that don't grow are kept, even if the organizer has lowered the number needed since.
+    let mut claim_errors = serde_json::Map::new();
+    for block in &party.invitation_blocks {
+        let BlockKind::ItemSignup(signup) = &block.kind else {
+            continue;
+        };
+        let Some(new_claims) = validated_answers.get(&block.id).and_then(|v| v.as_array()) else {
+            continue;
+        };
+        let current_claims = answers::load_one(&tx, id, &block.id).ok().flatten();
+        let others = answers::claimed_items(&tx, party_id, &block.id, signup.items.len(), Some(id))?;
+        for (index, (item, claimed_by_others)) in signup.items.iter().zip(others).enumerate() {
+            let wanted = new_claims.get(index).and_then(|v| v.as_i64()).unwrap_or(0);
+            let had = current_claims
+                .as_ref()
+                .and_then(|claims| claims.get(index))
+                .and_then(|v| v.as_i64())
+                .unwrap_or(0);
+            if wanted > had && claimed_by_others + wanted > item.capacity {
+                let e = answer::AnswerError::ItemUnavailable {
+                    item: item.name.clone(),
+                    left: (item.capacity - claimed_by_others).max(0),
+                };
+                claim_errors.insert(block.id.clone(), json!(e.message(language)));
+                break;
+            }
+        }
+    }
+    if !claim_errors.is_empty() {
+        return Err(SaveError::ItemsClaimed(claim_errors));
+    }
+
+    // Check if user is trying to RSVP "yes" (answer = 0) and max_guests limit is reached
+    // Note: "no" (2) and "maybe" (1) responses are always allowed to let people free up space
+    // Only enforce this if there's an attendance block AND max_guests is set
+    // Guests who already said yes can update other answers, but bringing more companions needs room
+    if max_guests > 0
+        && let Some(attendance_id) = attendance_block_id
+        && wants_to_attend
+        && (!was_attending || new_companions > current_companions)
+    {
+        // Count the people coming with OTHER invitations
+        let other_heads = answers::headcount(&tx, party_id, attendance_id, Some(id))?;
+
+        // Newcomers don't skip guests who are already waiting for a spot
+        let others_waiting = if party.waitlist && !was_attending {
+            waitlist::waiting_ahead(&tx, party_id, id)?
+        } else {
+            0
+        };
+
+        if other_heads + 1 + new_companions > max_guests || others_waiting > 0 {
+            // Guests who already have a spot keep it instead of moving to the waitlist
+            if was_attending || !party.waitlist {
+                return Err(SaveError::Refused(if other_heads < max_guests {
+                    "error-no-spots-for-companions"
+                } else {
+                    "error-party-full"
+                }));
+            }
+
+            // Queue the guest and keep their previous attendance answer until they are promoted
+            waitlist::enqueue(&tx, party_id, id)?;
+            match current_attendance_answer {
+                Some(previous) => validated_answers.insert(attendance_id.clone(), previous),
+                None => validated_answers.remove(attendance_id),
+            };
+            waitlisted = true;
+        }
+    }
+
+    // Getting a spot or answering anything but "yes" leaves the waitlist,
+    // and giving up a spot hands it on to the next guest in line
+    let mut promoted = Vec::new();
+    answers::replace(&tx, id, &validated_answers)?;
+    if let Some((count, names)) = &companions {
+        Invitation::set_companions(&tx, id, *count, names)?;
+    }
+    if !waitlisted {
+        waitlist::remove(&tx, id)?;
+    }
+    if was_attending && (!wants_to_attend || new_companions < current_companions) {
+        promoted = waitlist::promote(&tx, party)?;
+    }
+    let position = waitlist::position(&tx, id)?;
+    tx.commit()?;
+
+    Ok(Saved {
+        waitlisted: if waitlisted { position } else { None },
+        promoted,
+    })
 }
 
 #[post("/{invitation_id}")]
@@ -451,10 +741,8 @@ async fn save_answers(
     req: actix_web::HttpRequest,
 ) -> impl Responder {
     let id = path.into_inner();
-    let answers = &json.answers;
-    let language = detect_language(&req);
 
-    let conn = match db.get() {
+    let mut conn = match db.get() {
         Ok(conn) => conn,
         Err(_) => return HttpResponse::InternalServerError().body("Database connection failed"),
     };
@@ -495,213 +783,85 @@ async fn save_answers(
         }
     };
 
-    // Check if party is frozen or deadline has passed
-    let (frozen, respond_until) = match conn
-        .prepare("SELECT frozen, respond_until FROM parties WHERE id = ?1")
-        .and_then(|mut stmt| {
-            stmt.query_row([&party_id], |row| {
-                let frozen: bool = row.get(0)?;
-                let respond_until: String = row.get(1)?;
-                Ok((frozen, respond_until))
-            })
-        }) {
-        Ok(data) => data,
-        Err(_) => {
+    // Load the party to check if it is frozen or the deadline has passed
+    let party = match Party::load(&conn, &party_id) {
+        Ok(party) => party,
+        Err(e) => {
+            eprintln!("Database error loading party: {}", e);
             return HttpResponse::InternalServerError().json(json!({
                 "error": "Failed to get party status"
             }));
         }
     };
+    let language = i18n::language_for_party(&req, &party.language);
 
-    // Check if party is frozen
-    if frozen {
-        let error_msg = match language.as_str() {
-            "de" => "Diese Party ist eingefroren und akzeptiert keine Antworten mehr",
-            _ => "This party is frozen and no longer accepting responses",
-        };
+    if let Err(key) = check_open(&party) {
         return HttpResponse::Forbidden().json(json!({
-            "error": error_msg
+            "error": i18n::text(&language, key)
         }));
     }
 
-    // Check if respond_until deadline has passed
-    if !respond_until.is_empty() {
-        let now = chrono::Local::now().naive_local();
+    let attendance_block_id = block::attendance_block(&party.invitation_blocks).map(|block| &block.id);
 
-        // Try to parse as datetime first (with or without seconds)
-        let deadline_passed = if let Ok(deadline) =
-            chrono::NaiveDateTime::parse_from_str(&respond_until, "%Y-%m-%dT%H:%M:%S")
-        {
-            now > deadline
-        } else if let Ok(deadline) =
-            chrono::NaiveDateTime::parse_from_str(&respond_until, "%Y-%m-%dT%H:%M")
-        {
-            now > deadline
-        } else if let Ok(deadline_date) =
-            chrono::NaiveDate::parse_from_str(&respond_until, "%Y-%m-%d")
-        {
-            // If only date, consider deadline as end of day
-            let deadline = deadline_date
-                .and_hms_opt(23, 59, 59)
-                .unwrap_or(deadline_date.and_hms_opt(0, 0, 0).unwrap());
-            now > deadline
-        } else {
-            false
+    // Check every answer against its block; answers to unknown blocks are dropped.
+    // Companions are collected by the attendance block, so their errors are shown there.
+    let (validated_answers, mut errors) =
+        match answer::validate_answers(&party.invitation_blocks, &json.answers) {
+            Ok(answers) => (answers, std::collections::HashMap::new()),
+            Err(errors) => (serde_json::Map::new(), errors),
         };
-
-        if deadline_passed {
-            let error_msg = match language.as_str() {
-                "de" => "Die Frist zum Antworten auf diese Einladung ist abgelaufen",
-                _ => "The deadline for responding to this invitation has passed",
-            };
-            return HttpResponse::Forbidden().json(json!({
-                "error": error_msg
-            }));
-        }
-    }
-
-    // Get valid block IDs from the party's invitation blocks
-    let (valid_block_ids, attendance_block_id, max_guests) = match conn
-        .prepare("SELECT invitation_blocks, max_guests FROM parties WHERE id = ?1")
-        .and_then(|mut stmt| {
-            stmt.query_row([&party_id], |row| {
-                let invitation_blocks: String = row.get(0)?;
-                let max_guests: i64 = row.get(1)?;
-                Ok((invitation_blocks, max_guests))
-            })
-        }) {
-        Ok((invitation_blocks, max_guests)) => {
-            // Parse invitation blocks to extract valid block IDs and attendance block ID
-            let blocks_json =
-                serde_json::from_str::<serde_json::Value>(&invitation_blocks).unwrap_or(json!([]));
-
-            let mut valid_ids = std::collections::HashSet::new();
-            let mut attendance_id: Option<String> = None;
-            if let Some(blocks_array) = blocks_json.as_array() {
-                for block in blocks_array.iter() {
-                    if let Some(block_id) = block.get("id").and_then(|v| v.as_str()) {
-                        valid_ids.insert(block_id.to_string());
-                        // Check if this is an attendance block
-                        if block.get("template").and_then(|v| v.as_str()) == Some("attendance") {
-                            attendance_id = Some(block_id.to_string());
-                        }
-                    }
+    let companions = match (attendance_block_id, json.companions) {
+        (Some(attendance_id), Some(count)) => {
+            match answer::validate_companions(count, &json.companion_names, party.max_companions) {
+                Ok((count, names)) if party.ask_companion_names => Some((count, names)),
+                Ok((count, _)) => Some((count, Vec::new())),
+                Err(e) => {
+                    errors.insert(attendance_id.clone(), e);
+                    None
                 }
             }
-            (valid_ids, attendance_id, max_guests)
-        }
-        Err(_) => {
-            return HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to get party data"
-            }));
         }
+        _ => None,
     };
-
-    // Check if user is trying to RSVP "yes" (answer = 0) and max_guests limit is reached
-    // Note: "no" (2) and "maybe" (1) responses are always allowed to let people free up space
-    // Only enforce this if there's an attendance block AND max_guests is set
-    if max_guests > 0 && attendance_block_id.is_some() {
-        let attendance_id = attendance_block_id.as_ref().unwrap();
-        if let Some(new_answer) = answers.get(attendance_id) {
-            if new_answer.as_i64() == Some(0) {
-                // User is trying to RSVP yes - check if they currently have "yes"
-                // If they already have yes, allow them to keep it or update other answers
-                let current_attendance_answer = conn
-                    .prepare("SELECT invitation_block_answers FROM invitations WHERE id = ?1")
-                    .and_then(|mut stmt| {
-                        stmt.query_row([&id], |row| {
-                            let current_answers: String = row.get(0)?;
-                            Ok(current_answers)
-                        })
-                    })
-                    .ok()
-                    .and_then(|ans_str| {
-                        if ans_str.is_empty() {
-                            None
-                        } else {
-                            serde_json::from_str::<serde_json::Value>(&ans_str).ok()
-                        }
-                    })
-                    .and_then(|ans_json| ans_json.get(attendance_id).cloned())
-                    .and_then(|ans| ans.as_i64());
-
-                // Only check limit if they're NOT currently "yes" (changing from no/maybe/unanswered to yes)
-                let is_changing_to_yes = current_attendance_answer != Some(0);
-
-                if is_changing_to_yes {
-                    // Count current yes responses from OTHER invitations
-                    let yes_count = conn
-                        .prepare("SELECT invitation_block_answers FROM invitations WHERE party_id = ?1 AND id != ?2")
-                        .and_then(|mut stmt| {
-                            let answer_iter = stmt.query_map([&party_id, &id], |row| {
-                                let answers: String = row.get(0)?;
-                                Ok(answers)
-                            })?;
-
-                            let mut count = 0i64;
-                            for answer_result in answer_iter {
-                                if let Ok(answer_str) = answer_result {
-                                    // Skip empty answer strings
-                                    if answer_str.is_empty() {
-                                        continue;
-                                    }
-                                    if let Ok(answer_json) = serde_json::from_str::<serde_json::Value>(&answer_str) {
-                                        if let Some(att_answer) = answer_json.get(attendance_id) {
-                                            if let Some(answer_val) = att_answer.as_i64() {
-                                                if answer_val == 0 {
-                                                    count += 1;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Ok(count)
-                        })
-                        .unwrap_or(0);
-
-                    if yes_count >= max_guests {
-                        let error_msg = match language.as_str() {
-                            "de" => "Diese Party hat die maximale Anzahl an Gästen erreicht",
-                            _ => "This party has reached its maximum number of guests",
-                        };
-                        return HttpResponse::Forbidden().json(json!({
-                            "error": error_msg
-                        }));
-                    }
-                }
-            }
-        }
+    if !errors.is_empty() {
+        let block_errors: serde_json::Map<String, serde_json::Value> = errors
+            .iter()
+            .map(|(block_id, e)| (block_id.clone(), json!(e.message(&language))))
+            .collect();
+        return HttpResponse::BadRequest().json(json!({
+            "error": i18n::text(&language, "error-invalid-answers"),
+            "block_errors": block_errors
+        }));
     }
 
-    // Filter answers to only include valid block IDs
-    let filtered_answers = if let Some(answers_obj) = answers.as_object() {
-        let mut filtered = serde_json::Map::new();
-        for (block_id, answer) in answers_obj {
-            if valid_block_ids.contains(block_id) {
-                filtered.insert(block_id.clone(), answer.clone());
+    match store_answers(&mut conn, &party, &id, validated_answers, companions, &language) {
+        Ok(saved) => {
+            notify_promoted(db.clone(), saved.promoted).await;
+
+            if let Some(position) = saved.waitlisted {
+                let message = i18n::text(&language, "waitlisted");
+                return HttpResponse::Ok().json(json!({
+                    "success": true,
+                    "waitlisted": true,
+                    "waitlist_position": position,
+                    "message": message
+                }));
             }
-        }
-        serde_json::Value::Object(filtered)
-    } else {
-        json!({})
-    };
-
-    // Convert filtered answers to JSON string
-    let answers_json = filtered_answers.to_string();
-
-    // Update the invitation with filtered answers using prepared statement
-    let update_result = conn
-        .prepare("UPDATE invitations SET invitation_block_answers = ?1 WHERE id = ?2")
-        .and_then(|mut stmt| stmt.execute([&answers_json, &id]));
 
-    match update_result {
-        Ok(_) => HttpResponse::Ok().json(json!({
-            "success": true,
-            "message": "Answers saved successfully"
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "message": "Answers saved successfully"
+            }))
+        }
+        Err(SaveError::Refused(key)) => HttpResponse::Forbidden().json(json!({
+            "error": i18n::text(&language, key)
         })),
-        Err(e) => {
-            eprintln!("Database error: {}", e);
+        Err(SaveError::ItemsClaimed(claim_errors)) => HttpResponse::Conflict().json(json!({
+            "error": i18n::text(&language, "error-items-claimed"),
+            "block_errors": claim_errors
+        })),
+        Err(SaveError::Database(e)) => {
+            eprintln!("Database error saving answers: {}", e);
             HttpResponse::InternalServerError().json(json!({
                 "error": "Failed to save answers"
             }))
@@ -709,19 +869,55 @@ async fn save_answers(
     }
 }
 
-#[get("/register")]
-pub async fn register(req: actix_web::HttpRequest) -> impl Responder {
-    let language = detect_language(&req);
-    let filename = match language.as_str() {
-        "de" => "pages/de/public_guest_de.html",
-        _ => "pages/en/public_guest_en.html",
-    };
+/// Tells guests that moved up from the waitlist that they now have a spot
+pub async fn notify_promoted(
+    db: web::Data<Pool<SqliteConnectionManager>>,
+    promoted: Vec<(String, String)>,
+) {
+    crate::notification::notify_guests(
+        db,
+        crate::notification::promoted_message,
+        promoted.into_iter().collect(),
+    )
+    .await;
+}
 
-    let html_content =
-        fs::read_to_string(filename).unwrap_or_else(|_| "<h1>404: File Not Found</h1>".to_string());
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(html_content)
+/// Tells guests that were still waiting when the waitlist was turned off that they no longer are
+pub async fn notify_waitlist_closed(
+    db: web::Data<Pool<SqliteConnectionManager>>,
+    removed: Vec<(String, String)>,
+) {
+    crate::notification::notify_guests(
+        db,
+        crate::notification::waitlist_closed_message,
+        removed.into_iter().collect(),
+    )
+    .await;
+}
+
+#[derive(Deserialize)]
+struct RegisterQuery {
+    party: Option<String>,
+}
+
+#[get("/register")]
+pub async fn register(
+    query: web::Query<RegisterQuery>,
+    req: actix_web::HttpRequest,
+    db: web::Data<Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    // Shown in the language of the public party the guest is registering for
+    let party_language = query
+        .party
+        .as_ref()
+        .and_then(|party_id| {
+            let conn = db.get().ok()?;
+            Party::load(&conn, party_id).ok()
+        })
+        .map(|party| party.language)
+        .unwrap_or_default();
+    let language = i18n::language_for_party(&req, &party_language);
+    i18n::page_response(&req, HttpResponse::Ok(), "public_guest", &language)
 }
 
 #[get("/{invitation_id}/ics")]
@@ -736,66 +932,19 @@ async fn download_calendar(
         Err(_) => return HttpResponse::InternalServerError().body("Database connection failed"),
     };
 
-    // Get party details for this invitation
-    let party_info = match conn
-        .prepare("SELECT p.name, p.date, p.duration, p.location FROM parties p JOIN invitations i ON p.id = i.party_id WHERE i.id = ?1")
-        .and_then(|mut stmt| {
-            stmt.query_row([&invitation_id], |row| {
-                let name: String = row.get(0)?;
-                let date: String = row.get(1)?;
-                let duration: f64 = row.get(2)?;
-                let location: String = row.get(3)?;
-                Ok((name, date, duration, location))
-            })
-        }) {
-        Ok(info) => info,
+    let invitation_url = templating::invitation_url(&invitation_id);
+    let event = match calendar::invitation_event(&conn, &invitation_id, &invitation_url) {
+        Ok(Some(event)) => event,
+        Ok(None) => return HttpResponse::BadRequest().body("Invalid party date format"),
         Err(_) => return HttpResponse::NotFound().body("Invitation not found"),
     };
-
-    let (party_name, party_date, duration_hours, location) = party_info;
-
-    // Parse the party date
-    let start_dt = if let Ok(dt) =
-        chrono::NaiveDateTime::parse_from_str(&party_date, "%Y-%m-%dT%H:%M:%S")
-    {
-        dt
-    } else if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(&party_date, "%Y-%m-%dT%H:%M") {
-        dt
-    } else {
-        return HttpResponse::BadRequest().body("Invalid party date format");
-    };
-
-    // Calculate end time (convert hours to minutes)
-    let duration_minutes = (duration_hours * 60.0).round() as i64;
-    let end_dt = start_dt + chrono::Duration::minutes(duration_minutes);
-
-    // Generate unique ID for the event
-    let event_uid = format!("{}@party-hub", invitation_id);
-
-    // Get current timestamp for DTSTAMP
-    let now = chrono::Utc::now();
-
-    // Format dates for iCalendar (UTC format)
-    let dtstart = start_dt.format("%Y%m%dT%H%M%S").to_string();
-    let dtend = end_dt.format("%Y%m%dT%H%M%S").to_string();
-    let dtstamp = now.format("%Y%m%dT%H%M%SZ").to_string();
-
-    // Build invitation URL
-    let base_url = std::env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
-    let invitation_url = format!("{}/{}", base_url, invitation_id);
-
-    // Generate iCalendar file
-    let ics_content = format!(
-        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Party Hub//EN\r\nCALSCALE:GREGORIAN\r\nMETHOD:PUBLISH\r\nBEGIN:VEVENT\r\nUID:{}\r\nDTSTAMP:{}\r\nDTSTART:{}\r\nDTEND:{}\r\nSUMMARY:{}\r\nLOCATION:{}\r\nDESCRIPTION:Invitation link: {}\r\nURL:{}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
-        event_uid,
-        dtstamp,
-        dtstart,
-        dtend,
-        party_name.replace(",", "\\,").replace("\n", "\\n"),
-        location.replace(",", "\\,").replace("\n", "\\n"),
-        invitation_url,
-        invitation_url
-    );
+    let party_name = event.summary.clone();
+    let ics_content = Calendar {
+        method: Method::Publish,
+        name: None,
+        events: vec![event],
+    }
+    .render();
 
     HttpResponse::Ok()
         .content_type("text/calendar; charset=utf-8")
@@ -809,9 +958,223 @@ async fn download_calendar(
         .body(ics_content)
 }
 
+/// A guest's or author's feed of all their parties, for calendar apps to subscribe to.
+/// The secret token is what grants access, like invitation links do.
+#[get("/feed/{token}.ics")]
+async fn calendar_feed(
+    path: web::Path<String>,
+    req: actix_web::HttpRequest,
+    db: web::Data<Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    let token = path.into_inner();
+
+    let conn = match db.get() {
+        Ok(conn) => conn,
+        Err(_) => return HttpResponse::InternalServerError().body("Database connection failed"),
+    };
+
+    let result = match feeds::owner(&conn, &token) {
+        Ok(Some(FeedOwner::Guest(guest_id))) => calendar::guest_feed(&conn, &guest_id),
+        Ok(Some(FeedOwner::Author(author_id))) => {
+            calendar::author_feed(&conn, &author_id, &detect_language(&req))
+        }
+        Ok(None) => return HttpResponse::NotFound().body("Calendar feed not found"),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(calendar) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .body(calendar.render()),
+        Err(e) => {
+            eprintln!("Database error loading calendar feed: {}", e);
+            HttpResponse::InternalServerError().body("Calendar feed could not be loaded")
+        }
+    }
+}
+
 pub fn subroutes() -> Scope {
     web::scope("/invitation")
         .service(details)
         .service(save_answers)
         .service(download_calendar)
+        .service(calendar_feed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::migrations;
+    use actix_web::{App, HttpServer, web};
+    use r2d2::Pool;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rusqlite::Connection;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use uuid::Uuid;
+
+    const MAX_GUESTS: i64 = 5;
+    const CONTENDERS: usize = 24;
+
+    /// Creates a migrated database file with one party limited to `MAX_GUESTS` attendees
+    /// and `CONTENDERS` invited guests, returning the path and the invitation IDs
+    fn setup_db() -> (PathBuf, Vec<String>) {
+        let path = std::env::temp_dir().join(format!("party-hub-test-{}.db", Uuid::new_v4()));
+        let mut conn = Connection::open(&path).unwrap();
+        migrations::run_pending(&mut conn).unwrap();
+
+        let blocks = r#"[{"id":"rsvp","type":"attendance","label":"Coming?","options":["Yes","Maybe","No"]}]"#;
+        conn.execute("INSERT INTO authors (id, name, author_secret) VALUES ('a', 'Author', 's')", ()).unwrap();
+        conn.execute(
+            "INSERT INTO parties (id, name, author, invitation_blocks, public, max_guests, has_rsvp_block) VALUES ('p', 'Party', 'a', ?1, 1, ?2, 1)",
+            rusqlite::params![blocks, MAX_GUESTS],
+        )
+        .unwrap();
+
+        let mut invitation_ids = Vec::new();
+        for i in 0..CONTENDERS {
+            let guest_id = format!("g{}", i);
+            let invitation_id = format!("i{}", i);
+            conn.execute("INSERT INTO guests (id, first, author) VALUES (?1, 'Guest', 'a')", [&guest_id]).unwrap();
+            conn.execute(
+                "INSERT INTO invitations (id, guest_id, party_id) VALUES (?1, ?2, 'p')",
+                [&invitation_id, &guest_id],
+            )
+            .unwrap();
+            invitation_ids.push(invitation_id);
+        }
+
+        (path, invitation_ids)
+    }
+
+    /// Runs the invitation and guest routes on a multi-worker server in a background thread
+    fn start_server(path: &PathBuf) -> (SocketAddr, actix_web::dev::ServerHandle) {
+        let pool = Pool::builder()
+            .max_size(16)
+            .build(SqliteConnectionManager::file(path))
+            .unwrap();
+
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .service(super::subroutes())
+                .service(crate::guest::subroutes())
+        })
+        .workers(8)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        thread::spawn(move || actix_web::rt::System::new().block_on(server));
+
+        (addr, handle)
+    }
+
+    /// Sends a JSON POST request and returns the response status code
+    fn post_json(addr: SocketAddr, path: &str, body: &str) -> u16 {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// Fires all requests at once and returns how many succeeded
+    fn hammer(addr: SocketAddr, requests: Vec<(String, String)>) -> usize {
+        let barrier = Arc::new(Barrier::new(requests.len()));
+        let threads: Vec<_> = requests
+            .into_iter()
+            .map(|(path, body)| {
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    post_json(addr, &path, &body)
+                })
+            })
+            .collect();
+
+        threads
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .filter(|&status| status == 200)
+            .count()
+    }
+
+    fn yes_count(path: &PathBuf) -> i64 {
+        let conn = Connection::open(path).unwrap();
+        conn.query_row(
+            "SELECT COUNT(*) FROM answers WHERE block_id = 'rsvp' AND value = '0'",
+            (),
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn concurrent_rsvps_respect_max_guests() {
+        let (path, invitation_ids) = setup_db();
+        let (addr, handle) = start_server(&path);
+
+        let requests = invitation_ids
+            .iter()
+            .map(|id| (format!("/invitation/{}", id), r#"{"answers":{"rsvp":0}}"#.to_string()))
+            .collect();
+        let accepted = hammer(addr, requests);
+
+        actix_web::rt::System::new().block_on(handle.stop(true));
+        let stored = yes_count(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(accepted, MAX_GUESTS as usize);
+        assert_eq!(stored, MAX_GUESTS);
+    }
+
+    #[test]
+    fn concurrent_public_sign_ups_respect_max_guests() {
+        let (path, _) = setup_db();
+        // Without an attendance block, every invitation counts towards the limit
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute("DELETE FROM invitations", ()).unwrap();
+            conn.execute("UPDATE parties SET invitation_blocks = '[]', has_rsvp_block = 0", ()).unwrap();
+        }
+        let (addr, handle) = start_server(&path);
+
+        let requests = (0..CONTENDERS)
+            .map(|i| {
+                let body = format!(
+                    r#"{{"salutation":"","first":"Guest {}","last":"","email":""}}"#,
+                    i
+                );
+                ("/guest/public_guest/p".to_string(), body)
+            })
+            .collect();
+        let accepted = hammer(addr, requests);
+
+        actix_web::rt::System::new().block_on(handle.stop(true));
+        let conn = Connection::open(&path).unwrap();
+        let invitations: i64 = conn
+            .query_row("SELECT COUNT(*) FROM invitations WHERE party_id = 'p'", (), |row| row.get(0))
+            .unwrap();
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(accepted, MAX_GUESTS as usize);
+        assert_eq!(invitations, MAX_GUESTS);
+    }
 }
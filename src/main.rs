@@ -1,15 +1,23 @@
 use actix_files::NamedFile;
 use actix_web::{App, HttpResponse, HttpServer, get, web};
-use db::prepare_db;
+use db::{DB_PATH, migrations, prepare_db};
 use r2d2_sqlite::SqliteConnectionManager;
 use std::env;
 use std::fs;
+mod attachment;
 mod auth;
+mod block;
+mod caldav;
+mod calendar;
 mod db;
 mod guest;
+mod i18n;
 mod invitation;
 mod notification;
 mod party;
+mod series;
+mod templating;
+mod timezone;
 
 #[get("/static/{filename:.*}")]
 async fn serve_static(path: web::Path<String>) -> actix_web::Result<NamedFile> {
@@ -43,28 +51,59 @@ async fn serve_manifest() -> actix_web::Result<NamedFile> {
     Ok(NamedFile::open("static/manifest.json")?)
 }
 
-fn detect_language(req: &actix_web::HttpRequest) -> String {
-    if let Some(accept_lang) = req.headers().get("accept-language") {
-        if let Ok(lang_str) = accept_lang.to_str() {
-            // Parse Accept-Language header (e.g., "de-DE,de;q=0.8,en;q=0.6")
-            for lang_part in lang_str.split(',') {
-                let lang_code = lang_part.split(';').next().unwrap_or("").trim();
+/// Handles `party-hub migrate [status]` without starting the server
+fn run_migrate_command(subcommand: Option<&str>) -> std::io::Result<()> {
+    let mut conn = rusqlite::Connection::open(DB_PATH).map_err(std::io::Error::other)?;
 
-                // Check for German variants
-                if lang_code.starts_with("de") {
-                    return "de".to_string();
+    match subcommand {
+        None => match migrations::run_pending(&mut conn) {
+            Ok(applied) if applied.is_empty() => {
+                println!("INFO: Database schema is up to date.");
+            }
+            Ok(applied) => {
+                for name in applied {
+                    println!("INFO: Applied database migration {}", name);
                 }
-                // Default to English for any other language
             }
+            Err(e) => {
+                eprintln!("FATAL: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Some("status") => {
+            let status = migrations::status(&conn).map_err(std::io::Error::other)?;
+            let db_version = migrations::current_version(&conn).map_err(std::io::Error::other)?;
+            println!(
+                "Database schema version: {} (binary supports up to {})",
+                db_version,
+                migrations::latest_version()
+            );
+            for (version, name, applied) in status {
+                let state = if applied { "applied" } else { "pending" };
+                println!("  {:04}_{:<40} {}", version, name, state);
+            }
+        }
+        Some(other) => {
+            eprintln!("Unknown migrate subcommand '{}'. Usage: party-hub migrate [status]", other);
+            std::process::exit(2);
         }
     }
-    "en".to_string() // Default to English
+
+    Ok(())
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    prepare_db().expect("FATAL: Unable to set up DB!");
-    let manager = SqliteConnectionManager::file("party.db");
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        return run_migrate_command(args.get(1).map(String::as_str));
+    }
+
+    if let Err(e) = prepare_db() {
+        eprintln!("FATAL: Unable to set up DB: {}", e);
+        std::process::exit(1);
+    }
+    let manager = SqliteConnectionManager::file(DB_PATH);
     let pool = r2d2::Pool::new(manager).unwrap();
 
     // Get port from environment variable, default to 8080
@@ -77,6 +116,15 @@ async fn main() -> std::io::Result<()> {
         "INFO: Starting Party Hub server on http://127.0.0.1:{}",
         port
     );
+    let languages = i18n::init();
+    if languages.is_empty() {
+        println!(
+            "WARNING: No message catalogs found in {}/. Pages will show message keys.",
+            i18n::LOCALES_DIR
+        );
+    } else {
+        println!("INFO: Languages: {}", languages.join(", "));
+    }
     match env::var("ENV") {
         Ok(val) => {
             println!("INFO: Running in {val} mode.")
@@ -147,6 +195,11 @@ async fn main() -> std::io::Result<()> {
         }
     }
 
+    if let Some(dir) = calendar::reply::maildir() {
+        println!("INFO: Reading calendar replies from {}", dir.display());
+        actix_web::rt::spawn(calendar::reply::watch(dir, web::Data::new(pool.clone())));
+    }
+
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
@@ -154,10 +207,14 @@ async fn main() -> std::io::Result<()> {
             .service(serve_manifest)
             .service(serve_favicon)
             .service(serve_static)
+            .service(attachment::subroutes())
             .service(auth::subroutes())
+            .service(caldav::subroutes())
+            .service(caldav::well_known)
             .service(guest::subroutes())
             .service(notification::subroutes())
             .service(party::subroutes())
+            .service(series::subroutes())
             .service(party::home)
             .service(party::dashboard)
             .service(invitation::subroutes())
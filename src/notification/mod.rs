@@ -2,7 +2,15 @@ mod web_push;
 mod smtp_client;
 mod smtp_server;
 
+use crate::calendar::{self, Calendar, Method};
+use crate::db::households;
+use crate::i18n;
+use crate::templating::{self, Variables};
 use actix_web::{Scope, web};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, MultiPart, MessageBuilder, SinglePart};
+use rusqlite::Connection;
+use std::collections::HashMap;
 
 pub use web_push::{get_vapid_public_key, web_push_subscribe, associate_guest, send_push};
 pub use smtp_client::{send_emails_via_client, is_smtp_client_configured};
@@ -10,31 +18,288 @@ pub use smtp_server::{send_emails_direct, is_smtp_direct_configured};
 
 /// Main email sending function that chooses between client and direct sending
 /// Respects MAIL_SENDTYPE environment variable ("client" or "direct")
+/// Emails may carry a calendar, which mail clients offer to add to (or update in) the calendar
 pub async fn send_emails(
     db: web::Data<r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>>,
     subject: String,
     body: String,
+    calendar: Option<Calendar>,
     guest_ids: Vec<String>,
 ) -> Result<(), String> {
     // Check MAIL_SENDTYPE preference
     match std::env::var("MAIL_SENDTYPE").as_deref() {
         Ok("client") => {
-            send_emails_via_client(db, subject, body, guest_ids).await
+            send_emails_via_client(db, subject, body, calendar, guest_ids).await
         }
         Ok("direct") => {
-            send_emails_direct(db, subject, body, guest_ids).await
+            send_emails_direct(db, subject, body, calendar, guest_ids).await
         }
         _ => {
             // No preference set, try client first, fall back to direct
             if is_smtp_client_configured() {
-                send_emails_via_client(db, subject, body, guest_ids).await
+                send_emails_via_client(db, subject, body, calendar, guest_ids).await
             } else {
-                send_emails_direct(db, subject, body, guest_ids).await
+                send_emails_direct(db, subject, body, calendar, guest_ids).await
             }
         }
     }
 }
 
+/// Finishes an email with its text and, if there is one, the calendar: as an alternative to
+/// the text for mail clients that handle invitations, and as an `.ics` file for the others
+fn build_email(
+    builder: MessageBuilder,
+    body: &str,
+    calendar: Option<&Calendar>,
+) -> Result<lettre::Message, String> {
+    let Some(calendar) = calendar else {
+        return builder
+            .body(body.to_string())
+            .map_err(|e| format!("Failed to build email: {}", e));
+    };
+    let content = calendar.render();
+    let calendar_type = ContentType::parse(&format!(
+        "text/calendar; charset=utf-8; method={}",
+        calendar.method.as_str()
+    ))
+    .map_err(|e| format!("Invalid calendar content type: {}", e))?;
+    let file_type = ContentType::parse("application/ics")
+        .map_err(|e| format!("Invalid calendar content type: {}", e))?;
+
+    builder
+        .multipart(
+            MultiPart::mixed()
+                .multipart(
+                    MultiPart::alternative()
+                        .singlepart(SinglePart::plain(body.to_string()))
+                        .singlepart(
+                            SinglePart::builder()
+                                .header(calendar_type)
+                                .body(content.clone()),
+                        ),
+                )
+                .singlepart(Attachment::new("invite.ics".to_string()).body(content, file_type)),
+        )
+        .map_err(|e| format!("Failed to build email: {}", e))
+}
+
+/// A notification in one language. All texts are templates, filled in with each recipient's
+/// variables (see `templating`).
+pub struct Message {
+    pub push: String,
+    pub subject: String,
+    pub email: String,
+}
+
+/// A notification from the catalog messages `<name>-push`, `<name>-subject` and `<name>-email`.
+/// Emails start with the `notification-greeting`.
+fn catalog_message(language: &str, name: &str) -> Message {
+    Message {
+        push: i18n::text(language, &format!("{}-push", name)).to_string(),
+        subject: i18n::text(language, &format!("{}-subject", name)).to_string(),
+        email: format!(
+            "{}\n\n{}",
+            i18n::text(language, "notification-greeting"),
+            i18n::text(language, &format!("{}-email", name))
+        ),
+    }
+}
+
+/// The notification for a new invitation
+pub fn invitation_message(language: &str) -> Message {
+    catalog_message(language, "notification-invitation")
+}
+
+/// The notification for a guest who moved up from the waitlist
+pub fn promoted_message(language: &str) -> Message {
+    catalog_message(language, "notification-promoted")
+}
+
+/// The notification for a guest who stopped waiting because the waitlist was turned off
+pub fn waitlist_closed_message(language: &str) -> Message {
+    catalog_message(language, "notification-waitlist-closed")
+}
+
+/// The notification for a change the organizer describes in `changelog`
+pub fn changelog_message(language: &str, changelog: &str) -> Message {
+    // Only `{{changelog}}` is filled in here, the other variables are left for each guest
+    let mut variables = Variables::default();
+    variables.set("changelog", changelog);
+    Message {
+        push: i18n::text_with(language, "notification-changelog-push", &variables),
+        subject: i18n::text(language, "notification-changelog-subject").to_string(),
+        email: changelog.to_string(),
+    }
+}
+
+/// The notification for the date picked in a date poll. The date is the party's by now, so
+/// it is filled in for each guest in their language like the other variables.
+pub fn date_adopted_message(language: &str) -> Message {
+    changelog_message(language, i18n::text(language, "notification-date-adopted"))
+}
+
+/// The notification for guests of a party that was deleted
+pub fn cancelled_message(language: &str) -> Message {
+    catalog_message(language, "notification-cancelled")
+}
+
+/// The notification for a guest whose answer from their calendar app wasn't taken as is;
+/// `reason` is the catalog key of the reason
+pub fn reply_not_taken_message(language: &str, reason: &str) -> Message {
+    // Only `{{reason}}` is filled in here; rendering would drop the greeting's conditionals,
+    // which need each guest's variables
+    let reason = i18n::text(language, reason);
+    let message = catalog_message(language, "notification-reply-not-taken");
+    Message {
+        push: message.push.replace("{{reason}}", reason),
+        subject: message.subject,
+        email: message.email.replace("{{reason}}", reason),
+    }
+}
+
+/// An email to a guest, ready to be sent
+struct Email {
+    guest_id: String,
+    subject: String,
+    body: String,
+    calendar: Option<Calendar>,
+}
+
+/// Notifications that are filled in and ready to be sent. They are prepared while the party
+/// still exists, so a deleted party's guests can be told about it afterwards.
+pub struct Notifications {
+    push_contents: HashMap<String, String>,
+    guest_invitation_map: HashMap<String, String>,
+    emails: Vec<Email>,
+}
+
+/// Prepares push notifications and emails, each in the guest's own language (see
+/// `Guest::notification_language`). Each email ends with a link to the guest's own invitation
+/// (unless the party is cancelled) and carries the party as a calendar event with the given `method`.
+/// Members of a household that share an invitation link get a single email with that link instead.
+/// `guest_invitation_map` maps guest IDs to their invitation IDs.
+pub fn prepare(
+    conn: &Connection,
+    message: impl Fn(&str) -> Message,
+    guest_invitation_map: HashMap<String, String>,
+    method: Method,
+) -> Notifications {
+    let recipients: HashMap<String, (Variables, String)> = guest_invitation_map
+        .iter()
+        .map(|(guest_id, invitation_id)| {
+            let recipient = templating::load(conn, invitation_id).unwrap_or_else(|e| {
+                eprintln!("[NOTIFICATION ERROR] Failed to load invitation {}: {}", invitation_id, e);
+                (Variables::default(), "en".to_string())
+            });
+            (guest_id.clone(), recipient)
+        })
+        .collect();
+
+    // (guest ID to write to, variables, language) for every email to send
+    let mut recipient_emails: Vec<(String, Variables, String)> = Vec::new();
+    let mut household_members: HashMap<String, Vec<String>> = HashMap::new();
+    for (guest_id, invitation_id) in &guest_invitation_map {
+        match households::link_for_invitation(conn, invitation_id) {
+            Ok(Some(link)) => household_members.entry(link.id).or_default().push(guest_id.clone()),
+            _ => {
+                let (variables, language) = recipients[guest_id].clone();
+                recipient_emails.push((guest_id.clone(), variables, language));
+            }
+        }
+    }
+    for (link_id, guest_ids) in household_members {
+        if let Ok(Some(contact)) = households::email_contact(conn, &guest_ids) {
+            let (mut variables, language) = recipients[&contact].clone();
+            variables.set("invitation_url", templating::invitation_url(&link_id));
+            recipient_emails.push((contact, variables, language));
+        }
+    }
+
+    let emails = recipient_emails
+        .into_iter()
+        .map(|(guest_id, variables, language)| {
+            let message = message(&language);
+            let invitation_url = variables.get("invitation_url").unwrap_or_default();
+            let mut body = templating::render(&message.email, &variables);
+            // The invitation of a cancelled party is gone
+            if method != Method::Cancel {
+                let footer = i18n::text(&language, "notification-invitation-link");
+                body = format!("{}\n\n{} {}", body, footer, invitation_url);
+            }
+            let event = calendar::invitation_event(
+                conn,
+                &guest_invitation_map[&guest_id],
+                invitation_url,
+            )
+            .unwrap_or_else(|e| {
+                eprintln!("[NOTIFICATION ERROR] Failed to load calendar event: {}", e);
+                None
+            });
+            Email {
+                subject: templating::render(&message.subject, &variables),
+                body,
+                calendar: event.map(|event| Calendar {
+                    method,
+                    name: None,
+                    events: vec![event],
+                }),
+                guest_id,
+            }
+        })
+        .collect();
+
+    let push_contents = recipients
+        .iter()
+        .map(|(guest_id, (variables, language))| {
+            (guest_id.clone(), templating::render(&message(language).push, variables))
+        })
+        .collect();
+
+    Notifications {
+        push_contents,
+        guest_invitation_map,
+        emails,
+    }
+}
+
+impl Notifications {
+    pub async fn send(self, db: web::Data<r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>>) {
+        let _ = send_push(db.clone(), self.push_contents, self.guest_invitation_map).await;
+        for email in self.emails {
+            let _ = send_emails(
+                db.clone(),
+                email.subject,
+                email.body,
+                email.calendar,
+                vec![email.guest_id],
+            )
+            .await;
+        }
+    }
+}
+
+/// Notifies guests via push and email about their invitation (see `prepare`)
+pub async fn notify_guests(
+    db: web::Data<r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>>,
+    message: impl Fn(&str) -> Message,
+    guest_invitation_map: HashMap<String, String>,
+) {
+    if guest_invitation_map.is_empty() {
+        return;
+    }
+
+    let conn = match db.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("[NOTIFICATION ERROR] Database connection failed: {}", e);
+            return;
+        }
+    };
+    let notifications = prepare(&conn, message, guest_invitation_map, Method::Request);
+    drop(conn);
+    notifications.send(db).await;
+}
+
 pub fn subroutes() -> Scope {
     web::scope("/notification")
         .service(get_vapid_public_key)
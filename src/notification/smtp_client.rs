@@ -1,3 +1,5 @@
+use super::build_email;
+use crate::calendar::Calendar;
 use actix_web::web;
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::{Message, SmtpTransport, Transport};
@@ -23,6 +25,7 @@ pub async fn send_emails_via_client(
     db: web::Data<Pool<SqliteConnectionManager>>,
     subject: String,
     body: String,
+    calendar: Option<Calendar>,
     guest_ids: Vec<String>,
 ) -> Result<(), String> {
     // Check if SMTP is configured - if not, skip silently
@@ -81,7 +84,7 @@ pub async fn send_emails_via_client(
     for (email, first, last) in guest_emails {
         let recipient_name = format!("{} {}", first, last).trim().to_string();
 
-        let message = Message::builder()
+        let builder = Message::builder()
             .from(
                 smtp_from
                     .parse()
@@ -90,9 +93,8 @@ pub async fn send_emails_via_client(
             .to(format!("{} <{}>", recipient_name, email)
                 .parse()
                 .map_err(|e| format!("Invalid to address: {}", e))?)
-            .subject(&subject)
-            .body(body.clone())
-            .map_err(|e| format!("Failed to build email: {}", e))?;
+            .subject(&subject);
+        let message = build_email(builder, &body, calendar.as_ref())?;
 
         match mailer.send(&message) {
             Ok(_) => {}
@@ -1,3 +1,5 @@
+use super::build_email;
+use crate::calendar::Calendar;
 use actix_web::web;
 use lettre::{Message, SmtpTransport, Transport};
 use lettre::transport::smtp::client::{Tls, TlsParameters};
@@ -42,6 +44,7 @@ async fn send_email_direct(
     to_email: &str,
     subject: &str,
     body: &str,
+    calendar: Option<&Calendar>,
 ) -> Result<(), String> {
     // Extract domain from recipient email
     let to_domain = to_email.split('@').nth(1)
@@ -68,13 +71,12 @@ async fn send_email_direct(
     );
 
     // Create the email message with Message-ID header
-    let message = Message::builder()
+    let builder = Message::builder()
         .from(from_addr.parse().map_err(|e| format!("Invalid from address: {}", e))?)
         .to(to_addr.parse().map_err(|e| format!("Invalid to address: {}", e))?)
         .subject(subject)
-        .message_id(Some(message_id))
-        .body(body.to_string())
-        .map_err(|e| format!("Failed to build email: {}", e))?;
+        .message_id(Some(message_id));
+    let message = build_email(builder, body, calendar)?;
 
     // Try each MX server in order of priority
     for (_priority, mx_host) in mx_records {
@@ -124,6 +126,7 @@ pub async fn send_emails_direct(
     db: web::Data<Pool<SqliteConnectionManager>>,
     subject: String,
     body: String,
+    calendar: Option<Calendar>,
     guest_ids: Vec<String>,
 ) -> Result<(), String> {
     // Get from address from environment
@@ -173,7 +176,7 @@ pub async fn send_emails_direct(
         let recipient_name = format!("{} {}", first, last).trim().to_string();
         let to_addr = format!("{} <{}>", recipient_name, email);
 
-        match send_email_direct(&smtp_from, &to_addr, &email, &subject, &body).await {
+        match send_email_direct(&smtp_from, &to_addr, &email, &subject, &body, calendar.as_ref()).await {
             Ok(_) => {
             }
             Err(e) => {
@@ -153,9 +153,10 @@ pub async fn associate_guest(
 
 /// Sends push notifications to guests with invitation links
 /// A single device (subscription) can receive notifications for multiple guests
+/// `contents` maps guest IDs to the message for that guest
 pub async fn send_push(
     db: web::Data<Pool<SqliteConnectionManager>>,
-    content: String,
+    contents: std::collections::HashMap<String, String>,
     guest_invitation_map: std::collections::HashMap<String, String>,
 ) -> impl Responder {
     let conn = match db.get() {
@@ -228,11 +229,12 @@ pub async fn send_push(
             }
         };
 
-        // Get the invitation_id for this guest
-        let invitation_id = match guest_invitation_map.get(&guest_id) {
-            Some(id) => id,
-            None => continue,
-        };
+        // Get the invitation_id and message for this guest
+        let (invitation_id, content) =
+            match (guest_invitation_map.get(&guest_id), contents.get(&guest_id)) {
+                (Some(id), Some(content)) => (id, content),
+                _ => continue,
+            };
 
         let subscription_info = SubscriptionInfo::new(&endpoint, &p256dh, &auth);
 
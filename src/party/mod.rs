@@ -1,12 +1,20 @@
+mod template;
+
+use crate::attachment;
 use crate::auth::is_authenticated_as_author;
-use crate::db::Party;
-use crate::detect_language;
+use crate::block::{self, BlockKind, answer, poll};
+use crate::calendar::{self, Method};
+use crate::db::feeds::{self, FeedOwner};
+use crate::db::series::{self, Series, SeriesDefaults};
+use crate::db::{BlockTemplate, PARTY_COLUMNS, Party, answers, households, waitlist};
+use crate::i18n::{self, detect_language};
+use crate::timezone;
 use actix_web::{HttpRequest, HttpResponse, Responder, Scope, delete, get, post, web};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::TransactionBehavior;
 use serde::Deserialize;
 use serde_json::json;
-use std::fs;
 use uuid::Uuid;
 
 #[get("/")]
@@ -16,16 +24,7 @@ pub async fn home(
     // Detect language from Accept-Language header
     let language = detect_language(&req);
 
-    let filename = match language.as_str() {
-        "de" => "pages/de/index_de.html",
-        _ => "pages/en/index_en.html", // Default to English
-    };
-
-    let html_content =
-        fs::read_to_string(filename).unwrap_or_else(|_| "<h1>404: File Not Found</h1>".to_string());
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(html_content)
+    i18n::page_response(&req, HttpResponse::Ok(), "index", &language)
 }
 
 #[get("/dashboard")]
@@ -44,16 +43,7 @@ pub async fn dashboard(
     // Detect language from Accept-Language header
     let language = detect_language(&req);
 
-    let filename = match language.as_str() {
-        "de" => "pages/de/manage_de.html",
-        _ => "pages/en/manage_en.html", // Default to English
-    };
-
-    let html_content =
-        fs::read_to_string(filename).unwrap_or_else(|_| "<h1>404: File Not Found</h1>".to_string());
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(html_content)
+    i18n::page_response(&req, HttpResponse::Ok(), "manage", &language)
 }
 
 #[get("")]
@@ -82,14 +72,15 @@ async fn get_parties(
 
     // Get all parties for this author
     let parties_result = conn
-        .prepare("SELECT id, name, author, invitation_blocks, date, duration, location, respond_until, frozen, public, max_guests, has_rsvp_block FROM parties WHERE author = ?1")
+        .prepare(&format!("SELECT {} FROM parties WHERE author = ?1", PARTY_COLUMNS))
         .and_then(|mut stmt| {
             let party_iter = stmt.query_map([&author_id], Party::from_row)?;
 
             let mut parties = Vec::new();
             for party_result in party_iter {
-                if let Ok(party) = party_result {
-                    parties.push(party.to_summary_json());
+                match party_result {
+                    Ok(party) => parties.push(party.to_summary_json()),
+                    Err(e) => eprintln!("Skipping unreadable party: {}", e),
                 }
             }
             Ok(parties)
@@ -106,8 +97,61 @@ async fn get_parties(
     }
 }
 
+/// The link to the feed of all the author's parties
+#[get("/calendar_feed")]
+async fn get_calendar_feed(
+    req: HttpRequest,
+    db: web::Data<Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    calendar_feed_response(&req, &db, feeds::token)
+}
+
+/// Replaces the link to the author's feed, for when it was shared by mistake
+#[post("/calendar_feed/reset")]
+async fn reset_calendar_feed(
+    req: HttpRequest,
+    db: web::Data<Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    calendar_feed_response(&req, &db, feeds::reset)
+}
+
+fn calendar_feed_response(
+    req: &HttpRequest,
+    db: &Pool<SqliteConnectionManager>,
+    token: fn(&rusqlite::Connection, &FeedOwner) -> rusqlite::Result<String>,
+) -> HttpResponse {
+    let Some(author_id) = is_authenticated_as_author(req, db) else {
+        return HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        }));
+    };
+
+    let result = db
+        .get()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| token(&conn, &FeedOwner::Author(author_id)).map_err(|e| e.to_string()));
+    match result {
+        Ok(token) => HttpResponse::Ok().json(json!({
+            "url": calendar::feed_url(&token)
+        })),
+        Err(e) => {
+            eprintln!("Database error: {}", e);
+            HttpResponse::InternalServerError().json(json!({
+                "error": "Failed to load calendar feed"
+            }))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CreatePartyForm {
+    // Block template to start from instead of an empty invitation
+    template_id: Option<String>,
+}
+
 #[post("/new")]
 async fn create_party(
+    form: Option<web::Json<CreatePartyForm>>,
     pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
     req: HttpRequest,
 ) -> impl Responder {
@@ -118,9 +162,24 @@ async fn create_party(
         // Create new party ID
         let party_id = Uuid::new_v4().to_string();
 
+        let template = match form.as_ref().and_then(|form| form.template_id.as_deref()) {
+            Some(template_id) => match BlockTemplate::load(&conn, template_id, &author_id) {
+                Ok(template) => Some(template),
+                Err(_) => {
+                    return HttpResponse::NotFound().json(json!({
+                        "error": "Template not found or access denied"
+                    }));
+                }
+            },
+            None => None,
+        };
+
         // Create empty party with default values
         let default_name = "New Party";
-        let default_invitation_blocks = "[]";
+        let default_invitation_blocks = template
+            .as_ref()
+            .map(|template| block::blocks_to_json(&template.invitation_blocks))
+            .unwrap_or_else(|| "[]".to_string());
         let default_date = "";
         let default_duration = 1.0;
         let default_location = "";
@@ -128,7 +187,9 @@ async fn create_party(
         let default_frozen = false;
         let default_public = false;
         let default_max_guests = 0;
-        let default_has_rsvp_block = false;
+        let default_has_rsvp_block = template
+            .as_ref()
+            .is_some_and(|template| block::attendance_block(&template.invitation_blocks).is_some());
 
         let result = conn
             .prepare(
@@ -138,7 +199,7 @@ async fn create_party(
                 stmt.execute(rusqlite::params![
                     &party_id,
                     default_name,
-                    default_invitation_blocks,
+                    &default_invitation_blocks,
                     &author_id,
                     default_date,
                     default_duration,
@@ -200,24 +261,30 @@ async fn get_party_details(
 
     // Get party details, ensuring it belongs to the authenticated author
     let party = match conn
-        .prepare(
-            "SELECT id, name, author, invitation_blocks, date, duration, location, respond_until, frozen, public, max_guests, has_rsvp_block FROM parties WHERE id = ?1 AND author = ?2",
-        )
+        .prepare(&format!(
+            "SELECT {} FROM parties WHERE id = ?1 AND author = ?2",
+            PARTY_COLUMNS
+        ))
         .and_then(|mut stmt| stmt.query_row([&party_id, &author_id], Party::from_row))
     {
         Ok(party) => party,
-        Err(_) => {
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
             return HttpResponse::NotFound().json(json!({
                 "error": "Party not found or access denied"
             }));
         }
+        Err(e) => {
+            eprintln!("Database error loading party: {}", e);
+            return HttpResponse::InternalServerError().json(json!({
+                "error": "Failed to load party"
+            }));
+        }
     };
 
-    // Parse invitation blocks JSON
-    let invitation_blocks = party.get_invitation_blocks_json();
-
     // Get all guests for this party
-    let guests_result = conn.prepare("SELECT g.id, g.salutation, g.first, g.last, i.organizer, i.id, g.selfcreated FROM guests g INNER JOIN invitations i ON g.id = i.guest_id WHERE i.party_id = ?1")
+    let party_answers = answers::load_for_party(&conn, &party_id).unwrap_or_default();
+    let no_answers = json!({});
+    let guests_result = conn.prepare("SELECT g.id, g.salutation, g.first, g.last, i.organizer, i.id, g.selfcreated, i.companions, i.companion_names, g.household_id, h.name, hi.id FROM guests g INNER JOIN invitations i ON g.id = i.guest_id LEFT JOIN households h ON g.household_id = h.id LEFT JOIN household_invitations hi ON hi.household_id = g.household_id AND hi.party_id = i.party_id WHERE i.party_id = ?1")
         .and_then(|mut stmt| {
             let guest_iter = stmt.query_map([&party_id], |row| {
                 let guest_id: String = row.get(0)?;
@@ -227,6 +294,16 @@ async fn get_party_details(
                 let organizer: bool = row.get(4)?;
                 let invitation_id: String = row.get(5)?;
                 let selfcreated: bool = row.get(6)?;
+                let companions: i64 = row.get(7)?;
+                let companion_names: String = row.get(8)?;
+                let companion_names: Vec<String> =
+                    serde_json::from_str(&companion_names).unwrap_or_default();
+                let household_id: Option<String> = row.get(9)?;
+                let household_name: Option<String> = row.get(10)?;
+                let household_invitation_id: Option<String> = row.get(11)?;
+                // Required questions this guest still has to answer
+                let guest_answers = party_answers.get(&invitation_id).unwrap_or(&no_answers);
+                let missing_required = answer::missing_required(&party.invitation_blocks, guest_answers);
                 Ok(json!({
                     "id": guest_id,
                     "salutation": salutation,
@@ -235,21 +312,54 @@ async fn get_party_details(
                     "name": format!("{} {}", first, last).trim(),
                     "organizer": organizer,
                     "invitation_id": invitation_id,
-                    "selfcreated": selfcreated
+                    "selfcreated": selfcreated,
+                    "companions": companions,
+                    "companion_names": companion_names,
+                    "household_id": household_id,
+                    "household_name": household_name,
+                    "household_invitation_id": household_invitation_id,
+                    "missing_required": missing_required
                 }))
             })?;
 
             let mut guests = Vec::new();
-            for guest_result in guest_iter {
-                if let Ok(guest) = guest_result {
-                    guests.push(guest);
-                }
+            for guest in guest_iter.flatten() {
+                guests.push(guest);
             }
             Ok(guests)
         });
 
     let guests = guests_result.unwrap_or_else(|_| Vec::new());
 
+    // Organizers see the results of every date poll
+    let poll_responses: Vec<(String, serde_json::Value)> = guests
+        .iter()
+        .filter_map(|guest| {
+            let answers = party_answers.get(guest["invitation_id"].as_str()?)?;
+            Some((
+                guest["name"].as_str().unwrap_or("").to_string(),
+                answer::sanitize_stored_answers(&party.invitation_blocks, answers),
+            ))
+        })
+        .collect();
+    let date_polls = poll::results(&party.invitation_blocks, &poll_responses, |_| true);
+
+    // Total number of people attending, companions included
+    let headcount = block::attendance_block(&party.invitation_blocks)
+        .and_then(|attendance| answers::headcount(&conn, &party.id, &attendance.id, None).ok());
+
+    // Occurrences link to their series so the editor can offer series-wide changes
+    let series = party.series_id.as_ref().and_then(|series_id| {
+        Series::load(&conn, series_id, &author_id).ok().map(|series| {
+            json!({
+                "id": series.id,
+                "rrule": series.rrule,
+                "series_date": party.series_date,
+                "overrides": party.series_overrides
+            })
+        })
+    });
+
     let response = json!({
         "id": party.id,
         "name": party.name,
@@ -261,8 +371,18 @@ async fn get_party_details(
         "public": party.public,
         "max_guests": party.max_guests,
         "has_rsvp_block": party.has_rsvp_block,
-        "invitation_blocks": invitation_blocks,
-        "guests": guests
+        "waitlist": party.waitlist,
+        "max_companions": party.max_companions,
+        "ask_companion_names": party.ask_companion_names,
+        "language": party.language,
+        "time_zone": party.time_zone,
+        "default_time_zone": timezone::default_time_zone().name(),
+        "headcount": headcount,
+        "invitation_blocks": party.invitation_blocks,
+        "guests": guests,
+        "waitlist_entries": waitlist::list(&conn, &party.id).unwrap_or_default(),
+        "date_polls": date_polls,
+        "series": series
     });
 
     HttpResponse::Ok().json(response)
@@ -279,7 +399,13 @@ struct SavePartyForm {
     frozen: Option<bool>,
     public: Option<bool>,
     max_guests: Option<i64>,
+    waitlist: Option<bool>,
+    max_companions: Option<i64>,
+    ask_companion_names: Option<bool>,
+    language: Option<String>,
+    time_zone: Option<String>,
     changelog: Option<String>,
+    apply_to_series: Option<bool>,
 }
 
 #[post("/{party_id}/update")]
@@ -296,8 +422,7 @@ async fn update_party(
         // Verify party ownership
         match verify_party_ownership(&pool, &party_id, &author_id) {
             Ok(true) => {
-                let conn = pool.get().unwrap();
-                let invitation_blocks = form.invitation_blocks.as_deref().unwrap_or("[]");
+                let mut conn = pool.get().unwrap();
                 let date = form.date.as_deref().unwrap_or("");
                 let duration = form.duration.unwrap_or(0.0);
                 let location = form.location.as_deref().unwrap_or("");
@@ -305,95 +430,99 @@ async fn update_party(
                 let frozen = form.frozen.unwrap_or(false);
                 let public = form.public.unwrap_or(false);
                 let max_guests = form.max_guests.unwrap_or(0);
+                let waitlist_enabled = form.waitlist.unwrap_or(false);
+                let max_companions = form.max_companions.unwrap_or(0).max(0);
+                let ask_companion_names = form.ask_companion_names.unwrap_or(false);
+                let language = form.language.as_deref().unwrap_or("");
+                if !language.is_empty() && !i18n::is_supported(language) {
+                    return HttpResponse::BadRequest().json(json!({
+                        "error": "Unsupported language"
+                    }));
+                }
+                let time_zone = form.time_zone.as_deref().unwrap_or("").trim();
+                if !time_zone.is_empty() && timezone::parse(time_zone).is_none() {
+                    return HttpResponse::BadRequest().json(json!({
+                        "error": "Unknown time zone"
+                    }));
+                }
 
-                // Validate attendance blocks: parse the JSON and count attendance blocks
-                let has_rsvp_block = match serde_json::from_str::<Vec<serde_json::Value>>(invitation_blocks) {
-                    Ok(blocks) => {
-                        let attendance_count = blocks.iter()
-                            .filter(|block| {
-                                block.get("template")
-                                    .and_then(|t| t.as_str())
-                                    .map(|t| t == "attendance")
-                                    .unwrap_or(false)
-                            })
-                            .count();
-
-                        if attendance_count > 1 {
-                            return HttpResponse::BadRequest().json(json!({
-                                "error": "Only one attendance block is allowed per party"
-                            }));
-                        }
-
-                        attendance_count == 1
-                    }
-                    Err(_) => {
+                // Parse and validate the submitted blocks (also accepts the legacy format)
+                let blocks = match block::parse_blocks(form.invitation_blocks.as_deref().unwrap_or("[]"))
+                    .and_then(|blocks| block::validate_blocks(&blocks).map(|_| blocks))
+                {
+                    Ok(blocks) => blocks,
+                    Err(e) => {
                         return HttpResponse::BadRequest().json(json!({
-                            "error": "Invalid invitation_blocks JSON"
+                            "error": e.to_string()
                         }));
                     }
                 };
+                if let Err(e) = attachment::check_blocks(&conn, &author_id, &blocks) {
+                    return HttpResponse::BadRequest().json(json!({
+                        "error": e
+                    }));
+                }
+                let invitation_blocks = block::blocks_to_json(&blocks);
+                let has_rsvp_block = block::attendance_block(&blocks).is_some();
 
+                // A raised limit or a removed waitlist is applied in the same transaction as the update
                 let result = conn
-                    .prepare("UPDATE parties SET name = ?1, invitation_blocks = ?2, date = ?3, duration = ?4, location = ?5, respond_until = ?6, frozen = ?7, public = ?8, max_guests = ?9, has_rsvp_block = ?10 WHERE id = ?11 AND author = ?12")
-                    .and_then(|mut stmt| stmt.execute(rusqlite::params![&form.name, invitation_blocks, date, duration, location, respond_until, frozen, public, max_guests, has_rsvp_block, &party_id, &author_id]));
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .and_then(|tx| {
+                        let rows_affected = tx
+                            .prepare("UPDATE parties SET name = ?1, invitation_blocks = ?2, date = ?3, duration = ?4, location = ?5, respond_until = ?6, frozen = ?7, public = ?8, max_guests = ?9, has_rsvp_block = ?10, waitlist = ?11, max_companions = ?12, ask_companion_names = ?13, language = ?14, time_zone = ?15, sequence = sequence + 1 WHERE id = ?16 AND author = ?17")
+                            .and_then(|mut stmt| stmt.execute(rusqlite::params![&form.name, &invitation_blocks, date, duration, location, respond_until, frozen, public, max_guests, has_rsvp_block, waitlist_enabled, max_companions, ask_companion_names, language, time_zone, &party_id, &author_id]))?;
+                        let (promoted, removed) = if rows_affected == 0 {
+                            (Vec::new(), Vec::new())
+                        } else if waitlist_enabled {
+                            (waitlist::promote(&tx, &Party::load(&tx, &party_id)?)?, Vec::new())
+                        } else {
+                            waitlist::close(&tx, &Party::load(&tx, &party_id)?)?
+                        };
+
+                        // Occurrences of a series either pass the changes on to the upcoming
+                        // occurrences or remember which settings they now handle differently
+                        let mut series_changes = Vec::new();
+                        let party = Party::load(&tx, &party_id)?;
+                        if let Some(series_id) = &party.series_id {
+                            let mut overrides = if form.apply_to_series.unwrap_or(false) {
+                                series::update_defaults(&tx, series_id, &SeriesDefaults::from_party(&party))?;
+                                Vec::new()
+                            } else {
+                                let series = Series::load(&tx, series_id, &author_id)?;
+                                series.defaults.differing_fields(&SeriesDefaults::from_party(&party))
+                            };
+                            if party.series_date.as_deref() != Some(date) {
+                                overrides.push("date".to_string());
+                            }
+                            series::set_overrides(&tx, &party_id, &overrides)?;
+
+                            if form.apply_to_series.unwrap_or(false) {
+                                let series = Series::load(&tx, series_id, &author_id)?;
+                                series_changes = series::apply_defaults(
+                                    &tx,
+                                    &series,
+                                    party.series_date.as_deref().unwrap_or(""),
+                                )?;
+                            }
+                        }
+                        tx.commit()?;
+                        Ok((rows_affected, promoted, removed, series_changes))
+                    });
 
                 match result {
-                    Ok(rows_affected) => {
+                    Ok((rows_affected, promoted, removed, series_changes)) => {
                         if rows_affected > 0 {
+                            crate::invitation::notify_promoted(pool.clone(), promoted).await;
+                            crate::invitation::notify_waitlist_closed(pool.clone(), removed).await;
+                            for (promoted, removed) in series_changes {
+                                crate::invitation::notify_promoted(pool.clone(), promoted).await;
+                                crate::invitation::notify_waitlist_closed(pool.clone(), removed).await;
+                            }
+
                             // If changelog is provided, send notifications to all guests
                             if let Some(changelog) = &form.changelog {
-                                let changelog_trimmed = changelog.trim();
-                                if !changelog_trimmed.is_empty() {
-                                    // Truncate to 2000 characters if needed
-                                    let changelog_limited = if changelog_trimmed.len() > 2000 {
-                                        &changelog_trimmed[..2000]
-                                    } else {
-                                        changelog_trimmed
-                                    };
-
-                                    // Get all guest IDs and invitation IDs for this party
-                                    let guest_invitation_map: std::collections::HashMap<String, String> = conn
-                                        .prepare("SELECT guest_id, id FROM invitations WHERE party_id = ?1")
-                                        .and_then(|mut stmt| {
-                                            let map = stmt.query_map([&party_id], |row| {
-                                                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-                                            })?
-                                            .filter_map(|r| r.ok())
-                                            .collect();
-                                            Ok(map)
-                                        })
-                                        .unwrap_or_default();
-
-                                    // Send push notifications
-                                    if !guest_invitation_map.is_empty() {
-                                        let notification_content = format!("Update regarding {}: {}", form.name, changelog_limited);
-                                        
-                                        // Send push notifications
-                                        let _ = crate::notification::send_push(
-                                            pool.clone(),
-                                            notification_content.clone(),
-                                            guest_invitation_map.clone(),
-                                        ).await;
-                                        
-                                        // Send emails individually with correct invitation links
-                                        let email_subject = format!("Party Update: {}", form.name);
-                                        let base_url = std::env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
-                                        
-                                        for (guest_id, invitation_id) in guest_invitation_map {
-                                            let email_body = format!("{}\n\nView your invitation at: {}/{}", 
-                                                changelog_limited,
-                                                base_url,
-                                                invitation_id
-                                            );
-                                            let _ = crate::notification::send_emails(
-                                                pool.clone(),
-                                                email_subject.clone(),
-                                                email_body,
-                                                vec![guest_id],
-                                            ).await;
-                                        }
-                                    }
-                                }
+                                notify_changelog(&pool, &party_id, changelog).await;
                             }
 
                             HttpResponse::Ok().json(json!({
@@ -428,6 +557,165 @@ async fn update_party(
     }
 }
 
+/// Sends a party update to all of its guests, unless the changelog is empty
+async fn notify_changelog(
+    pool: &web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    party_id: &str,
+    changelog: &str,
+) {
+    let changelog_trimmed = changelog.trim();
+    if changelog_trimmed.is_empty() {
+        return;
+    }
+
+    // Truncate to 2000 characters if needed
+    let changelog_limited = if changelog_trimmed.len() > 2000 {
+        &changelog_trimmed[..2000]
+    } else {
+        changelog_trimmed
+    };
+
+    // The changelog may use template variables as well
+    notify_party_guests(pool, party_id, |language| {
+        crate::notification::changelog_message(language, changelog_limited)
+    })
+    .await;
+}
+
+/// Sends a notification to all guests of a party
+async fn notify_party_guests(
+    pool: &web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    party_id: &str,
+    message: impl Fn(&str) -> crate::notification::Message,
+) {
+    // Get all guest IDs and invitation IDs for this party
+    let guest_invitation_map: std::collections::HashMap<String, String> = match pool.get() {
+        Ok(conn) => conn
+            .prepare("SELECT guest_id, id FROM invitations WHERE party_id = ?1")
+            .and_then(|mut stmt| {
+                let map = stmt
+                    .query_map([party_id], |row| {
+                        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                    })?
+                    .filter_map(|r| r.ok())
+                    .collect();
+                Ok(map)
+            })
+            .unwrap_or_default(),
+        Err(_) => return,
+    };
+
+    crate::notification::notify_guests(pool.clone(), message, guest_invitation_map).await;
+}
+
+#[derive(Deserialize)]
+struct AdoptSlotForm {
+    block_id: String,
+    // Defaults to the slot the most guests can make
+    slot: Option<usize>,
+}
+
+#[post("/{party_id}/adopt_slot")]
+async fn adopt_slot(
+    path: web::Path<String>,
+    form: web::Json<AdoptSlotForm>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let party_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let mut conn = pool.get().unwrap();
+        let party = match Party::load(&conn, &party_id) {
+            Ok(party) if party.author == author_id => party,
+            _ => {
+                return HttpResponse::Forbidden().json(json!({
+                    "error": "Party not found or access denied"
+                }));
+            }
+        };
+
+        let poll = party.invitation_blocks.iter().find_map(|block| match &block.kind {
+            BlockKind::DatePoll(poll) if block.id == form.block_id => Some(poll),
+            _ => None,
+        });
+        let Some(poll) = poll else {
+            return HttpResponse::NotFound().json(json!({
+                "error": "Date poll not found"
+            }));
+        };
+
+        let slot = match form.slot {
+            Some(slot) if slot >= poll.slots.len() => {
+                return HttpResponse::BadRequest().json(json!({
+                    "error": "Slot not found"
+                }));
+            }
+            Some(slot) => Some(slot),
+            None => {
+                let party_answers = answers::load_for_party(&conn, &party_id).unwrap_or_default();
+                let poll_answers: Vec<&serde_json::Value> = party_answers
+                    .values()
+                    .filter_map(|answers| answers.get(&form.block_id))
+                    .collect();
+                poll::winning_slot(poll, &poll_answers)
+            }
+        };
+        let Some(date) = slot.and_then(|slot| poll.slots.get(slot)).cloned() else {
+            return HttpResponse::BadRequest().json(json!({
+                "error": "No slot to adopt yet, nobody can make any of the proposed dates"
+            }));
+        };
+
+        // An occurrence moved to another date than its series planned remembers that
+        let result = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .and_then(|tx| {
+                tx.execute(
+                    "UPDATE parties SET date = ?1, sequence = sequence + 1 WHERE id = ?2",
+                    [&date, &party_id],
+                )?;
+                if party.series_id.is_some() {
+                    let mut overrides: Vec<String> = party
+                        .series_overrides
+                        .iter()
+                        .filter(|field| field.as_str() != "date")
+                        .cloned()
+                        .collect();
+                    if party.series_date.as_deref() != Some(date.as_str()) {
+                        overrides.push("date".to_string());
+                    }
+                    series::set_overrides(&tx, &party_id, &overrides)?;
+                }
+                tx.commit()
+            });
+
+        match result {
+            Ok(()) => {
+                notify_party_guests(&pool, &party_id, crate::notification::date_adopted_message)
+                    .await;
+
+                HttpResponse::Ok().json(json!({
+                    "status": "success",
+                    "message": "Date adopted successfully",
+                    "date": date
+                }))
+            }
+            Err(e) => {
+                eprintln!("Database error adopting date poll slot: {}", e);
+                HttpResponse::InternalServerError().json(json!({
+                    "error": "Failed to adopt date"
+                }))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        }))
+    }
+}
+
 #[delete("/{party_id}/delete")]
 async fn delete_party(
     path: web::Path<String>,
@@ -446,9 +734,30 @@ async fn delete_party(
                 // Start a transaction to delete party and related invitations
                 let tx = conn.unchecked_transaction().unwrap();
 
-                // Delete all invitations for this party first
-                let delete_invitations_result = tx
-                    .prepare("DELETE FROM invitations WHERE party_id = ?1")
+                // Guests are told with their invitation details, which are deleted below
+                let notifications = crate::notification::prepare(
+                    &tx,
+                    crate::notification::cancelled_message,
+                    Party::guest_invitations(&tx, &party_id).unwrap_or_default(),
+                    Method::Cancel,
+                );
+
+                // A deleted occurrence must not be recreated by its series
+                let exdate_result = Party::load(&tx, &party_id).and_then(|party| {
+                    match (party.series_id, party.series_date) {
+                        (Some(series_id), Some(series_date)) => {
+                            series::add_exdate(&tx, &series_id, &series_date)
+                        }
+                        _ => Ok(()),
+                    }
+                });
+
+                // Delete all answers, waitlist entries, household links and invitations for this party first
+                let delete_invitations_result = exdate_result
+                    .and_then(|_| answers::delete_for_party(&tx, &party_id))
+                    .and_then(|_| waitlist::clear(&tx, &party_id))
+                    .and_then(|_| households::delete_links_for_party(&tx, &party_id))
+                    .and_then(|_| tx.prepare("DELETE FROM invitations WHERE party_id = ?1"))
                     .and_then(|mut stmt| stmt.execute([&party_id]));
 
                 // Delete the party
@@ -460,6 +769,8 @@ async fn delete_party(
                     (Ok(_), Ok(rows_affected)) => {
                         if rows_affected > 0 {
                             tx.commit().unwrap();
+                            drop(conn);
+                            notifications.send(pool.clone()).await;
                             HttpResponse::Ok().json(json!({
                                 "status": "success",
                                 "message": "Party deleted successfully"
@@ -493,6 +804,106 @@ async fn delete_party(
     }
 }
 
+#[derive(Deserialize)]
+struct ClonePartyForm {
+    #[serde(default)]
+    include_guests: bool,
+}
+
+#[post("/{party_id}/clone")]
+async fn clone_party(
+    path: web::Path<String>,
+    form: Option<web::Json<ClonePartyForm>>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let party_id = path.into_inner();
+    let include_guests = form.is_some_and(|form| form.include_guests);
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        // Verify party ownership
+        match verify_party_ownership(&pool, &party_id, &author_id) {
+            Ok(true) => {
+                let mut conn = pool.get().unwrap();
+                let new_party_id = Uuid::new_v4().to_string();
+
+                // The copy gets no date and starts unfrozen; guests get fresh invitations without answers
+                let result = conn.transaction().and_then(|tx| {
+                    let party = Party::load(&tx, &party_id)?;
+                    tx.execute(
+                        "INSERT INTO parties (id, name, invitation_blocks, author, date, duration, location, respond_until, frozen, public, max_guests, has_rsvp_block, waitlist, max_companions, ask_companion_names, language, time_zone) VALUES (?1, ?2, ?3, ?4, '', ?5, ?6, '', 0, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                        rusqlite::params![
+                            &new_party_id,
+                            &party.name,
+                            block::blocks_to_json(&party.invitation_blocks),
+                            &author_id,
+                            party.duration,
+                            &party.location,
+                            party.public,
+                            party.max_guests,
+                            party.has_rsvp_block,
+                            party.waitlist,
+                            party.max_companions,
+                            party.ask_companion_names,
+                            &party.language,
+                            &party.time_zone,
+                        ],
+                    )?;
+
+                    if include_guests {
+                        let invitations: Vec<(String, bool)> = tx
+                            .prepare("SELECT guest_id, organizer FROM invitations WHERE party_id = ?1")?
+                            .query_map([&party_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                            .collect::<rusqlite::Result<_>>()?;
+                        for (guest_id, organizer) in invitations {
+                            tx.execute(
+                                "INSERT INTO invitations (id, guest_id, party_id, organizer) VALUES (?1, ?2, ?3, ?4)",
+                                rusqlite::params![Uuid::new_v4().to_string(), guest_id, &new_party_id, organizer],
+                            )?;
+                        }
+
+                        // Households that shared a link keep sharing one
+                        let household_ids: Vec<String> = tx
+                            .prepare("SELECT household_id FROM household_invitations WHERE party_id = ?1")?
+                            .query_map([&party_id], |row| row.get(0))?
+                            .collect::<rusqlite::Result<_>>()?;
+                        for household_id in household_ids {
+                            households::link_for(&tx, &household_id, &new_party_id)?;
+                        }
+                    }
+
+                    tx.commit()
+                });
+
+                match result {
+                    Ok(_) => HttpResponse::Ok().json(json!({
+                        "status": "success",
+                        "message": "Party cloned successfully",
+                        "party_id": new_party_id
+                    })),
+                    Err(e) => {
+                        eprintln!("Database error cloning party: {}", e);
+                        HttpResponse::InternalServerError().json(json!({
+                            "error": "Failed to clone party"
+                        }))
+                    }
+                }
+            }
+            Ok(false) => HttpResponse::Forbidden().json(json!({
+                "error": "Party not found or access denied"
+            })),
+            Err(_) => HttpResponse::InternalServerError().json(json!({
+                "error": "Database error"
+            })),
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        }))
+    }
+}
+
 fn verify_party_ownership(
     pool: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>,
     party_id: &str,
@@ -548,42 +959,20 @@ async fn add_guest_to_party(
                                 // Create new invitation
                                 let invitation_id = Uuid::new_v4().to_string();
                                 let result = conn
-                                    .prepare("INSERT INTO invitations (id, guest_id, party_id, invitation_block_answers, organizer) VALUES (?1, ?2, ?3, '{}', 0)")
+                                    .prepare("INSERT INTO invitations (id, guest_id, party_id, organizer) VALUES (?1, ?2, ?3, 0)")
                                     .and_then(|mut stmt| stmt.execute([&invitation_id, &guest_id, &party_id]));
 
                                 match result {
                                     Ok(_) => {
-                                        // Get party name for notification
-                                        let party_name: String = conn
-                                            .prepare("SELECT name FROM parties WHERE id = ?1")
-                                            .and_then(|mut stmt| stmt.query_row([&party_id], |row| row.get(0)))
-                                            .unwrap_or_else(|_| "a party".to_string());
-
-                                        // Send push notification to the guest
+                                        // Notify the guest via push and email
                                         let mut guest_invitation_map = std::collections::HashMap::new();
                                         guest_invitation_map.insert(guest_id.clone(), invitation_id.clone());
-                                        
-                                        let notification_content = format!("You've been invited to {}!", party_name);
-                                        let _ = crate::notification::send_push(
+                                        crate::notification::notify_guests(
                                             pool.clone(),
-                                            notification_content.clone(),
+                                            crate::notification::invitation_message,
                                             guest_invitation_map,
-                                        ).await;
-
-                                        // Send email notification
-                                        let email_subject = format!("You've been invited to {}", party_name);
-                                        let email_body = format!(
-                                            "You've been invited to {}!\n\nView your invitation at: {}/{}", 
-                                            party_name,
-                                            std::env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string()),
-                                            invitation_id
-                                        );
-                                        let _ = crate::notification::send_emails(
-                                            pool.clone(),
-                                            email_subject,
-                                            email_body,
-                                            vec![guest_id.clone()],
-                                        ).await;
+                                        )
+                                        .await;
 
                                         HttpResponse::Ok().json(json!({"status": "success", "message": "Guest added to party"}))
                                     },
@@ -613,6 +1002,88 @@ async fn add_guest_to_party(
     }
 }
 
+#[post("/{party_id}/add_household/{household_id}")]
+async fn add_household_to_party(
+    path: web::Path<(String, String)>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let (party_id, household_id) = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        // Verify party ownership
+        match verify_party_ownership(&pool, &party_id, &author_id) {
+            Ok(true) => {
+                let mut conn = pool.get().unwrap();
+                if households::load(&conn, &household_id, &author_id).is_err() {
+                    return HttpResponse::NotFound()
+                        .json(json!({"error": "Household not found or does not belong to you"}));
+                }
+
+                // Invite every member that isn't invited yet and create the shared link.
+                // Members that were invited on their own before are included in the link as well.
+                let result = conn
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .and_then(|tx| {
+                        let members = households::members(&tx, &household_id)?;
+                        if members.is_empty() {
+                            return Ok(None);
+                        }
+                        let mut guest_invitation_map = std::collections::HashMap::new();
+                        for member in members {
+                            let invitation_id = Uuid::new_v4().to_string();
+                            let inserted = tx.execute(
+                                "INSERT INTO invitations (id, guest_id, party_id, organizer)
+                                 SELECT ?1, ?2, ?3, 0 WHERE NOT EXISTS (SELECT 1 FROM invitations WHERE guest_id = ?2 AND party_id = ?3)",
+                                [&invitation_id, &member.id, &party_id],
+                            )?;
+                            if inserted > 0 {
+                                guest_invitation_map.insert(member.id, invitation_id);
+                            }
+                        }
+                        households::link_for(&tx, &household_id, &party_id)?;
+                        tx.commit()?;
+                        Ok(Some(guest_invitation_map))
+                    });
+
+                match result {
+                    Ok(None) => HttpResponse::BadRequest()
+                        .json(json!({"error": "This household has no members"})),
+                    Ok(Some(guest_invitation_map)) => {
+                        let added = guest_invitation_map.len();
+
+                        // The household gets one email with the shared link
+                        crate::notification::notify_guests(
+                            pool.clone(),
+                            crate::notification::invitation_message,
+                            guest_invitation_map,
+                        )
+                        .await;
+
+                        HttpResponse::Ok().json(json!({
+                            "status": "success",
+                            "message": "Household added to party",
+                            "added": added
+                        }))
+                    }
+                    Err(e) => {
+                        eprintln!("Database error adding household to party: {}", e);
+                        HttpResponse::InternalServerError()
+                            .json(json!({"error": "Failed to add household to party"}))
+                    }
+                }
+            }
+            Ok(false) => {
+                HttpResponse::Forbidden().json(json!({"error": "Party not found or access denied"}))
+            }
+            Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Database error"})),
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
 #[delete("/{party_id}/remove/{guest_id}")]
 async fn remove_guest_from_party(
     path: web::Path<(String, String)>,
@@ -626,14 +1097,27 @@ async fn remove_guest_from_party(
         // Verify party ownership
         match verify_party_ownership(&pool, &party_id, &author_id) {
             Ok(true) => {
-                let conn = pool.get().unwrap();
-                // Remove invitation
+                let mut conn = pool.get().unwrap();
+                // Remove invitation together with its answers and waitlist entry,
+                // then give a freed spot to the next guest on the waitlist
                 let result = conn
-                    .prepare("DELETE FROM invitations WHERE guest_id = ?1 AND party_id = ?2")
-                    .and_then(|mut stmt| stmt.execute([&guest_id, &party_id]));
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .and_then(|tx| {
+                        answers::delete_for_guest(&tx, &guest_id, Some(&party_id))?;
+                        waitlist::delete_for_guest(&tx, &guest_id, Some(&party_id))?;
+                        let rows_affected = tx.execute(
+                            "DELETE FROM invitations WHERE guest_id = ?1 AND party_id = ?2",
+                            [&guest_id, &party_id],
+                        )?;
+                        let party = Party::load(&tx, &party_id)?;
+                        let promoted = waitlist::promote(&tx, &party)?;
+                        tx.commit()?;
+                        Ok((rows_affected, promoted))
+                    });
 
                 match result {
-                    Ok(rows_affected) => {
+                    Ok((rows_affected, promoted)) => {
+                        crate::invitation::notify_promoted(pool.clone(), promoted).await;
                         if rows_affected > 0 {
                             HttpResponse::Ok().json(
                                 json!({"status": "success", "message": "Guest removed from party"}),
@@ -741,15 +1225,76 @@ async fn demote_organizer_to_guest(
     }
 }
 
+#[derive(Deserialize)]
+struct ReorderWaitlistForm {
+    invitation_ids: Vec<String>,
+}
+
+#[post("/{party_id}/waitlist")]
+async fn reorder_waitlist(
+    path: web::Path<String>,
+    form: web::Json<ReorderWaitlistForm>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let party_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        // Verify party ownership
+        match verify_party_ownership(&pool, &party_id, &author_id) {
+            Ok(true) => {
+                let mut conn = pool.get().unwrap();
+                // The new order must contain every waiting guest exactly once
+                let result = conn
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .and_then(|tx| {
+                        let reordered = waitlist::reorder(&tx, &party_id, &form.invitation_ids)?;
+                        tx.commit()?;
+                        Ok(reordered)
+                    });
+
+                match result {
+                    Ok(true) => HttpResponse::Ok()
+                        .json(json!({"status": "success", "message": "Waitlist reordered"})),
+                    Ok(false) => HttpResponse::BadRequest()
+                        .json(json!({"error": "The new order must list every guest on the waitlist exactly once"})),
+                    Err(e) => {
+                        eprintln!("Database error reordering waitlist: {}", e);
+                        HttpResponse::InternalServerError()
+                            .json(json!({"error": "Failed to reorder waitlist"}))
+                    }
+                }
+            }
+            Ok(false) => {
+                HttpResponse::Forbidden().json(json!({"error": "Party not found or access denied"}))
+            }
+            Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Database error"})),
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
 pub fn subroutes() -> Scope {
+    // Template routes come first so `/templates` isn't taken for a party ID
     web::scope("/party")
+        .service(template::get_templates)
+        .service(template::save_template)
+        .service(template::delete_template)
         .service(create_party)
         .service(update_party)
         .service(delete_party)
         .service(get_parties)
+        .service(get_calendar_feed)
+        .service(reset_calendar_feed)
         .service(get_party_details)
         .service(add_guest_to_party)
+        .service(add_household_to_party)
         .service(remove_guest_from_party)
         .service(promote_guest_to_organizer)
         .service(demote_organizer_to_guest)
+        .service(reorder_waitlist)
+        .service(clone_party)
+        .service(adopt_slot)
 }
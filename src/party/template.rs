@@ -0,0 +1,149 @@
+use super::verify_party_ownership;
+use crate::auth::is_authenticated_as_author;
+use crate::block;
+use crate::db::{BlockTemplate, Party};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, web};
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+#[get("/templates")]
+async fn get_templates(
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let conn = pool.get().unwrap();
+
+        let templates_result = conn
+            .prepare("SELECT id, name, author, invitation_blocks FROM block_templates WHERE author = ?1 ORDER BY name")
+            .and_then(|mut stmt| {
+                let template_iter = stmt.query_map([&author_id], BlockTemplate::from_row)?;
+
+                let mut templates = Vec::new();
+                for template_result in template_iter {
+                    match template_result {
+                        Ok(template) => templates.push(json!({
+                            "id": template.id,
+                            "name": template.name,
+                            "block_count": template.invitation_blocks.len()
+                        })),
+                        Err(e) => eprintln!("Skipping unreadable template: {}", e),
+                    }
+                }
+                Ok(templates)
+            });
+
+        match templates_result {
+            Ok(templates) => HttpResponse::Ok().json(templates),
+            Err(e) => {
+                eprintln!("Database error fetching templates: {}", e);
+                HttpResponse::InternalServerError()
+                    .json(json!({"error": "Failed to fetch templates"}))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[derive(Deserialize)]
+struct SaveTemplateForm {
+    name: String,
+}
+
+#[post("/{party_id}/save_template")]
+async fn save_template(
+    path: web::Path<String>,
+    form: web::Json<SaveTemplateForm>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let party_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let name = form.name.trim();
+        if name.is_empty() {
+            return HttpResponse::BadRequest().json(json!({"error": "Template name is required"}));
+        }
+
+        // Verify party ownership
+        match verify_party_ownership(&pool, &party_id, &author_id) {
+            Ok(true) => {
+                let conn = pool.get().unwrap();
+
+                // Only the block layout is saved; dates, limits and guests stay with the party
+                let template_id = Uuid::new_v4().to_string();
+                let result = Party::load(&conn, &party_id).and_then(|party| {
+                    conn.execute(
+                        "INSERT INTO block_templates (id, name, author, invitation_blocks) VALUES (?1, ?2, ?3, ?4)",
+                        [
+                            &template_id,
+                            name,
+                            &author_id,
+                            &block::blocks_to_json(&party.invitation_blocks),
+                        ],
+                    )
+                });
+
+                match result {
+                    Ok(_) => HttpResponse::Ok().json(json!({
+                        "status": "success",
+                        "message": "Template saved successfully",
+                        "template_id": template_id
+                    })),
+                    Err(e) => {
+                        eprintln!("Database error saving template: {}", e);
+                        HttpResponse::InternalServerError()
+                            .json(json!({"error": "Failed to save template"}))
+                    }
+                }
+            }
+            Ok(false) => {
+                HttpResponse::Forbidden().json(json!({"error": "Party not found or access denied"}))
+            }
+            Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Database error"})),
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[delete("/templates/{template_id}/delete")]
+async fn delete_template(
+    path: web::Path<String>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let template_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let conn = pool.get().unwrap();
+
+        // Parties created from the template keep their own copy of the blocks
+        let result = conn.execute(
+            "DELETE FROM block_templates WHERE id = ?1 AND author = ?2",
+            [&template_id, &author_id],
+        );
+
+        match result {
+            Ok(rows_affected) if rows_affected > 0 => HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Template deleted successfully"
+            })),
+            Ok(_) => HttpResponse::NotFound()
+                .json(json!({"error": "Template not found or access denied"})),
+            Err(e) => {
+                eprintln!("Database error deleting template: {}", e);
+                HttpResponse::InternalServerError()
+                    .json(json!({"error": "Failed to delete template"}))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
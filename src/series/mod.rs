@@ -0,0 +1,421 @@
+pub mod rrule;
+
+use crate::auth::is_authenticated_as_author;
+use crate::calendar::{Calendar, Event, Method};
+use crate::db::Party;
+use crate::db::series::{self, Series};
+use crate::timezone;
+use actix_web::{HttpRequest, HttpResponse, Responder, Scope, delete, get, post, web};
+use r2d2_sqlite::SqliteConnectionManager;
+use rrule::RRule;
+use rusqlite::TransactionBehavior;
+use serde::Deserialize;
+use serde_json::json;
+
+/// The current time in the series' time zone, which its dates are in
+fn now(series: &Series) -> chrono::NaiveDateTime {
+    timezone::now_in(series.zone())
+}
+
+#[derive(Deserialize)]
+struct RuleForm {
+    rrule: String,
+}
+
+#[post("/from_party/{party_id}")]
+async fn create_series(
+    path: web::Path<String>,
+    form: web::Json<RuleForm>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let party_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let rule = match RRule::parse(&form.rrule) {
+            Ok(rule) => rule,
+            Err(e) => return HttpResponse::BadRequest().json(json!({"error": e.to_string()})),
+        };
+
+        let mut conn = pool.get().unwrap();
+        let party = match Party::load(&conn, &party_id) {
+            Ok(party) if party.author == author_id => party,
+            _ => {
+                return HttpResponse::Forbidden()
+                    .json(json!({"error": "Party not found or access denied"}));
+            }
+        };
+        if party.series_id.is_some() {
+            return HttpResponse::Conflict()
+                .json(json!({"error": "Party is already part of a series"}));
+        }
+        if series::parse_date(&party.date).is_none() {
+            return HttpResponse::BadRequest()
+                .json(json!({"error": "Party needs a date and time before it can recur"}));
+        }
+
+        // The party becomes the first occurrence, the following ones are created right away
+        let result = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .and_then(|tx| {
+                let series_id = series::create_from_party(&tx, &party, &rule)?;
+                let series = Series::load(&tx, &series_id, &author_id)?;
+                let synced = series::sync_occurrences(&tx, &series, now(&series))?;
+                tx.commit()?;
+                Ok((series_id, synced))
+            });
+        drop(conn);
+
+        match result {
+            Ok((series_id, synced)) => {
+                let created = synced.created.len();
+                synced.send(pool.clone()).await;
+                HttpResponse::Ok().json(json!({
+                    "status": "success",
+                    "message": "Series created successfully",
+                    "series_id": series_id,
+                    "created": created
+                }))
+            }
+            Err(e) => {
+                eprintln!("Database error creating series: {}", e);
+                HttpResponse::InternalServerError()
+                    .json(json!({"error": "Failed to create series"}))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[get("/{series_id}")]
+async fn get_series_details(
+    path: web::Path<String>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let series_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let mut conn = pool.get().unwrap();
+
+        // Series without an end only exist a year ahead, so looking at one tops it up and invites
+        // the guests to the new occurrences
+        let result = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .and_then(|tx| {
+                let series = Series::load(&tx, &series_id, &author_id)?;
+                let synced = series::sync_occurrences(&tx, &series, now(&series))?;
+                let occurrences = series::occurrences(&tx, &series_id)?;
+                let guest_count = series::guests(&tx, &series_id)?.len();
+                tx.commit()?;
+                Ok((series, occurrences, guest_count, synced))
+            });
+        drop(conn);
+
+        match result {
+            Ok((series, occurrences, guest_count, synced)) => {
+                synced.send(pool.clone()).await;
+                HttpResponse::Ok().json(json!({
+                    "id": series.id,
+                    "name": series.defaults.name,
+                    "rrule": series.rrule,
+                    "dtstart": series.dtstart,
+                    "guest_count": guest_count,
+                    "occurrences": occurrences.iter().map(|party| json!({
+                        "party_id": party.id,
+                        "name": party.name,
+                        "date": party.date,
+                        "series_date": party.series_date,
+                        "overrides": party.series_overrides,
+                        "frozen": party.frozen
+                    })).collect::<Vec<_>>()
+                }))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                HttpResponse::NotFound().json(json!({"error": "Series not found or access denied"}))
+            }
+            Err(e) => {
+                eprintln!("Database error loading series: {}", e);
+                HttpResponse::InternalServerError().json(json!({"error": "Failed to load series"}))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[post("/{series_id}/rule")]
+async fn update_rule(
+    path: web::Path<String>,
+    form: web::Json<RuleForm>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let series_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let rule = match RRule::parse(&form.rrule) {
+            Ok(rule) => rule,
+            Err(e) => return HttpResponse::BadRequest().json(json!({"error": e.to_string()})),
+        };
+
+        let mut conn = pool.get().unwrap();
+
+        // Upcoming occurrences the new rule doesn't produce are deleted together with their answers.
+        // Their guests are told, and the guests of added ones are invited.
+        let result = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .and_then(|tx| {
+                Series::load(&tx, &series_id, &author_id)?;
+                series::set_rule(&tx, &series_id, &rule)?;
+                let series = Series::load(&tx, &series_id, &author_id)?;
+                let synced = series::sync_occurrences(&tx, &series, now(&series))?;
+                tx.commit()?;
+                Ok(synced)
+            });
+        drop(conn);
+
+        match result {
+            Ok(synced) => {
+                let (created, removed) = (synced.created.len(), synced.removed.len());
+                synced.send(pool.clone()).await;
+                HttpResponse::Ok().json(json!({
+                    "status": "success",
+                    "message": "Series rule updated successfully",
+                    "created": created,
+                    "removed": removed
+                }))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                HttpResponse::NotFound().json(json!({"error": "Series not found or access denied"}))
+            }
+            Err(e) => {
+                eprintln!("Database error updating series rule: {}", e);
+                HttpResponse::InternalServerError()
+                    .json(json!({"error": "Failed to update series rule"}))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[derive(Deserialize)]
+struct SeriesGuestsForm {
+    party_id: String,
+}
+
+#[post("/{series_id}/guests")]
+async fn update_guests(
+    path: web::Path<String>,
+    form: web::Json<SeriesGuestsForm>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let series_id = path.into_inner();
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let mut conn = pool.get().unwrap();
+
+        // The guest list of one occurrence becomes the default. Guests missing from upcoming
+        // occurrences are invited; nobody is uninvited, since they may have answered already.
+        let result = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .and_then(|tx| {
+                let series = Series::load(&tx, &series_id, &author_id)?;
+                let party = Party::load(&tx, &form.party_id)?;
+                if party.series_id.as_deref() != Some(series.id.as_str()) {
+                    return Ok(None);
+                }
+                series::set_guests_from_party(&tx, &series_id, &party.id)?;
+
+                // Each new guest is notified once, about the next occurrence
+                let now = now(&series);
+                let mut guest_invitation_map = std::collections::HashMap::new();
+                for occurrence in series::occurrences(&tx, &series_id)? {
+                    let upcoming = occurrence
+                        .series_date
+                        .as_deref()
+                        .and_then(series::parse_date)
+                        .is_some_and(|date| date >= now);
+                    if upcoming {
+                        for (guest_id, invitation_id) in
+                            series::invite_guests(&tx, &series_id, &occurrence.id)?
+                        {
+                            guest_invitation_map
+                                .entry(guest_id)
+                                .or_insert(invitation_id);
+                        }
+                    }
+                }
+                tx.commit()?;
+                Ok(Some(guest_invitation_map))
+            });
+
+        match result {
+            Ok(Some(guest_invitation_map)) => {
+                let invited = guest_invitation_map.len();
+                if invited > 0 {
+                    crate::notification::notify_guests(
+                        pool.clone(),
+                        crate::notification::invitation_message,
+                        guest_invitation_map,
+                    )
+                    .await;
+                }
+
+                HttpResponse::Ok().json(json!({
+                    "status": "success",
+                    "message": "Series guest list updated successfully",
+                    "invited": invited
+                }))
+            }
+            Ok(None) => HttpResponse::BadRequest()
+                .json(json!({"error": "Party is not an occurrence of this series"})),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                HttpResponse::NotFound().json(json!({"error": "Series not found or access denied"}))
+            }
+            Err(e) => {
+                eprintln!("Database error updating series guests: {}", e);
+                HttpResponse::InternalServerError()
+                    .json(json!({"error": "Failed to update series guest list"}))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteSeriesForm {
+    #[serde(default)]
+    delete_upcoming: bool,
+}
+
+#[delete("/{series_id}/delete")]
+async fn delete_series(
+    path: web::Path<String>,
+    form: Option<web::Json<DeleteSeriesForm>>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> impl Responder {
+    let series_id = path.into_inner();
+    let delete_upcoming = form.is_some_and(|form| form.delete_upcoming);
+
+    // Check authentication
+    if let Some(author_id) = is_authenticated_as_author(&req, &pool) {
+        let mut conn = pool.get().unwrap();
+
+        // Past occurrences always stay as regular parties
+        let result = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .and_then(|tx| {
+                let series = Series::load(&tx, &series_id, &author_id)?;
+                let mut removed = 0;
+                let mut notifications = Vec::new();
+                if delete_upcoming {
+                    let now = now(&series);
+                    for occurrence in series::occurrences(&tx, &series_id)? {
+                        let upcoming = occurrence
+                            .series_date
+                            .as_deref()
+                            .and_then(series::parse_date)
+                            .is_some_and(|date| date >= now);
+                        if upcoming {
+                            notifications.push(crate::notification::prepare(
+                                &tx,
+                                crate::notification::cancelled_message,
+                                Party::guest_invitations(&tx, &occurrence.id)?,
+                                Method::Cancel,
+                            ));
+                            removed += Party::delete(&tx, &occurrence.id)?;
+                        }
+                    }
+                }
+                series::delete(&tx, &series_id)?;
+                tx.commit()?;
+                Ok((removed, notifications))
+            });
+        drop(conn);
+
+        match result {
+            Ok((removed, notifications)) => {
+                for notifications in notifications {
+                    notifications.send(pool.clone()).await;
+                }
+                HttpResponse::Ok().json(json!({
+                    "status": "success",
+                    "message": "Series ended successfully",
+                    "removed": removed
+                }))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                HttpResponse::NotFound().json(json!({"error": "Series not found or access denied"}))
+            }
+            Err(e) => {
+                eprintln!("Database error deleting series: {}", e);
+                HttpResponse::InternalServerError().json(json!({"error": "Failed to end series"}))
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().json(json!({"error": "Authentication required"}))
+    }
+}
+
+#[get("/{series_id}/ics")]
+async fn series_calendar(
+    path: web::Path<String>,
+    pool: web::Data<r2d2::Pool<SqliteConnectionManager>>,
+) -> impl Responder {
+    let series_id = path.into_inner();
+
+    let conn = match pool.get() {
+        Ok(conn) => conn,
+        Err(_) => return HttpResponse::InternalServerError().body("Database connection failed"),
+    };
+
+    // Like invitation links, the unguessable series ID is what grants access to the feed
+    let (series, occurrences) = match Series::load_public(&conn, &series_id)
+        .and_then(|series| Ok((series::occurrences(&conn, &series_id)?, series)))
+    {
+        Ok((occurrences, series)) => (series, occurrences),
+        Err(_) => return HttpResponse::NotFound().body("Series not found"),
+    };
+
+    // Every occurrence is its own event, so changes to a single one show up as they are
+    let ics_content = Calendar {
+        method: Method::Publish,
+        name: Some(series.defaults.name.clone()),
+        events: occurrences
+            .iter()
+            .filter_map(|party| Event::for_party(party, format!("{}@party-hub", party.id)))
+            .collect(),
+    }
+    .render();
+
+    HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .append_header((
+            "Content-Disposition",
+            format!(
+                "inline; filename=\"{}.ics\"",
+                series.defaults.name.replace("/", "-").replace("\\", "-")
+            ),
+        ))
+        .body(ics_content)
+}
+
+pub fn subroutes() -> Scope {
+    web::scope("/series")
+        .service(create_series)
+        .service(get_series_details)
+        .service(update_rule)
+        .service(update_guests)
+        .service(delete_series)
+        .service(series_calendar)
+}
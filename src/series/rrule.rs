@@ -0,0 +1,583 @@
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, Weekday};
+use std::fmt;
+
+// Supported subset of RFC 5545 recurrence rules:
+// FREQ (DAILY, WEEKLY, MONTHLY, YEARLY), INTERVAL, COUNT, UNTIL, WKST (ignored),
+// BYDAY (weekdays; with an ordinal like 2TH or -1FR for monthly rules) and BYMONTHDAY (monthly rules).
+
+/// Upper bound for periods looked at when expanding a rule, so broken rules can't loop forever
+const MAX_PERIODS: u32 = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDateTime>,
+    pub by_day: Vec<(Option<i32>, Weekday)>,
+    pub by_month_day: Vec<i32>,
+}
+
+#[derive(Debug)]
+pub enum RRuleError {
+    MissingFrequency,
+    InvalidValue { part: String, value: String },
+    UnsupportedPart(String),
+    CountAndUntil,
+}
+
+impl fmt::Display for RRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RRuleError::MissingFrequency => write!(f, "Recurrence rule needs a FREQ"),
+            RRuleError::InvalidValue { part, value } => {
+                write!(
+                    f,
+                    "Invalid value '{}' for {} in recurrence rule",
+                    value, part
+                )
+            }
+            RRuleError::UnsupportedPart(part) => {
+                write!(f, "Recurrence rule part '{}' is not supported", part)
+            }
+            RRuleError::CountAndUntil => {
+                write!(f, "Recurrence rule can't have both COUNT and UNTIL")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RRuleError {}
+
+impl RRule {
+    /// Parses a rule like `FREQ=MONTHLY;BYDAY=2TH;COUNT=12` (an `RRULE:` prefix is allowed)
+    pub fn parse(raw: &str) -> Result<RRule, RRuleError> {
+        let raw = raw.trim();
+        let raw = raw.strip_prefix("RRULE:").unwrap_or(raw);
+
+        let mut frequency = None;
+        let mut rule = RRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+        };
+
+        for part in raw.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| RRuleError::UnsupportedPart(part.to_string()))?;
+            let key = key.trim().to_ascii_uppercase();
+            let value = value.trim().to_ascii_uppercase();
+            let invalid = || RRuleError::InvalidValue {
+                part: key.clone(),
+                value: value.clone(),
+            };
+
+            match key.as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(invalid()),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value.parse().map_err(|_| invalid())?;
+                    if rule.interval == 0 || rule.interval > 1000 {
+                        return Err(invalid());
+                    }
+                }
+                "COUNT" => {
+                    let count: u32 = value.parse().map_err(|_| invalid())?;
+                    if count == 0 {
+                        return Err(invalid());
+                    }
+                    rule.count = Some(count);
+                }
+                "UNTIL" => rule.until = Some(parse_until(&value).ok_or_else(invalid)?),
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        rule.by_day.push(parse_by_day(day).ok_or_else(invalid)?);
+                    }
+                }
+                "BYMONTHDAY" => {
+                    for day in value.split(',') {
+                        let day: i32 = day.parse().map_err(|_| invalid())?;
+                        if day == 0 || !(-31..=31).contains(&day) {
+                            return Err(invalid());
+                        }
+                        rule.by_month_day.push(day);
+                    }
+                }
+                // Weeks always start on Monday here, which is what WKST defaults to
+                "WKST" => {}
+                _ => return Err(RRuleError::UnsupportedPart(key)),
+            }
+        }
+
+        rule.frequency = frequency.ok_or(RRuleError::MissingFrequency)?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(RRuleError::CountAndUntil);
+        }
+
+        // Ordinal weekdays and month days only make sense within a month
+        let has_ordinal = rule.by_day.iter().any(|(ordinal, _)| ordinal.is_some());
+        if rule.frequency != Frequency::Monthly {
+            if has_ordinal {
+                return Err(RRuleError::UnsupportedPart("BYDAY".to_string()));
+            }
+            if !rule.by_month_day.is_empty() {
+                return Err(RRuleError::UnsupportedPart("BYMONTHDAY".to_string()));
+            }
+        }
+        if matches!(rule.frequency, Frequency::Daily | Frequency::Yearly) && !rule.by_day.is_empty()
+        {
+            return Err(RRuleError::UnsupportedPart("BYDAY".to_string()));
+        }
+
+        Ok(rule)
+    }
+
+    /// Whether the rule ends by itself (through COUNT or UNTIL)
+    pub fn is_bounded(&self) -> bool {
+        self.count.is_some() || self.until.is_some()
+    }
+
+    /// Start times of the occurrences between `from` and `to` (both inclusive), at most `max` of them.
+    /// `dtstart` is always the first occurrence and counts towards COUNT, like in RFC 5545.
+    pub fn occurrences(
+        &self,
+        dtstart: NaiveDateTime,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        max: usize,
+    ) -> Vec<NaiveDateTime> {
+        let end = match self.until {
+            Some(until) => until.min(to),
+            None => to,
+        };
+        let mut result = Vec::new();
+        let mut emitted: u32 = 0;
+
+        let mut emit = |occurrence: NaiveDateTime, result: &mut Vec<NaiveDateTime>| -> bool {
+            if occurrence > end || self.count.is_some_and(|count| emitted >= count) {
+                return false;
+            }
+            emitted += 1;
+            if occurrence >= from {
+                result.push(occurrence);
+            }
+            result.len() < max
+        };
+
+        if !emit(dtstart, &mut result) {
+            return result;
+        }
+
+        for period in 0..MAX_PERIODS {
+            let Some(period_start) = self.period_start(dtstart.date(), period) else {
+                break;
+            };
+            if period_start > end.date() {
+                break;
+            }
+            for date in self.period_dates(period_start, dtstart.date()) {
+                let occurrence = date.and_time(dtstart.time());
+                if occurrence <= dtstart {
+                    continue;
+                }
+                if !emit(occurrence, &mut result) {
+                    return result;
+                }
+            }
+        }
+        result
+    }
+
+    /// First day of the period (day, week, month or year) with the given index
+    fn period_start(&self, start: NaiveDate, period: u32) -> Option<NaiveDate> {
+        let step = period.checked_mul(self.interval)?;
+        match self.frequency {
+            Frequency::Daily => start.checked_add_days(Days::new(step as u64)),
+            Frequency::Weekly => {
+                let monday = start
+                    .checked_sub_days(Days::new(start.weekday().num_days_from_monday() as u64))?;
+                monday.checked_add_days(Days::new(step as u64 * 7))
+            }
+            Frequency::Monthly => start.with_day(1)?.checked_add_months(Months::new(step)),
+            Frequency::Yearly => start
+                .with_day(1)?
+                .with_month(1)?
+                .checked_add_months(Months::new(step.checked_mul(12)?)),
+        }
+    }
+
+    /// Sorted candidate dates within the period starting at `period_start`
+    fn period_dates(&self, period_start: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates = match self.frequency {
+            Frequency::Daily => vec![period_start],
+            Frequency::Weekly => {
+                let weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|(_, weekday)| *weekday).collect()
+                };
+                weekdays
+                    .into_iter()
+                    .filter_map(|weekday| {
+                        period_start
+                            .checked_add_days(Days::new(weekday.num_days_from_monday() as u64))
+                    })
+                    .collect()
+            }
+            Frequency::Monthly => self.month_dates(period_start, start),
+            Frequency::Yearly => {
+                NaiveDate::from_ymd_opt(period_start.year(), start.month(), start.day())
+                    .into_iter()
+                    .collect()
+            }
+        };
+        dates.sort();
+        dates.dedup();
+        dates
+    }
+
+    /// Dates matching BYMONTHDAY and BYDAY within the month starting at `first`
+    fn month_dates(&self, first: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        let days_in_month = first
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .map(|last| last.day() as i32)
+            .unwrap_or(28);
+        let day_to_date = |day: i32| {
+            let day = if day < 0 {
+                days_in_month + 1 + day
+            } else {
+                day
+            };
+            if day < 1 || day > days_in_month {
+                None
+            } else {
+                first.with_day(day as u32)
+            }
+        };
+
+        let by_month_day: Vec<NaiveDate> = self
+            .by_month_day
+            .iter()
+            .filter_map(|day| day_to_date(*day))
+            .collect();
+        let by_day: Vec<NaiveDate> = self
+            .by_day
+            .iter()
+            .flat_map(|(ordinal, weekday)| {
+                let matching: Vec<NaiveDate> = (1..=days_in_month)
+                    .filter_map(day_to_date)
+                    .filter(|date| date.weekday() == *weekday)
+                    .collect();
+                match ordinal {
+                    Some(n) if *n > 0 => {
+                        matching.get(*n as usize - 1).copied().into_iter().collect()
+                    }
+                    Some(n) => matching
+                        .len()
+                        .checked_sub(n.unsigned_abs() as usize)
+                        .and_then(|index| matching.get(index).copied())
+                        .into_iter()
+                        .collect(),
+                    None => matching,
+                }
+            })
+            .collect();
+
+        match (self.by_month_day.is_empty(), self.by_day.is_empty()) {
+            (true, true) => day_to_date(start.day() as i32).into_iter().collect(),
+            (false, true) => by_month_day,
+            (true, false) => by_day,
+            // Both given: only days matching both, as in RFC 5545
+            (false, false) => by_month_day
+                .into_iter()
+                .filter(|date| by_day.contains(date))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for RRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%S"))?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|(ordinal, weekday)| {
+                    let ordinal = ordinal.map(|n| n.to_string()).unwrap_or_default();
+                    format!("{}{}", ordinal, weekday_code(*weekday))
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self
+                .by_month_day
+                .iter()
+                .map(|day| day.to_string())
+                .collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_until(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim_end_matches('Z');
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Some(datetime);
+    }
+    // A plain date includes the whole day
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+}
+
+fn parse_by_day(value: &str) -> Option<(Option<i32>, Weekday)> {
+    let value = value.trim();
+    // Only ASCII is valid here, and splitting anything else by bytes could cut a character
+    if value.len() < 2 || !value.is_ascii() {
+        return None;
+    }
+    let (ordinal, code) = value.split_at(value.len() - 2);
+    let weekday = match code {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    if ordinal.is_empty() {
+        return Some((None, weekday));
+    }
+    let ordinal: i32 = ordinal.trim_start_matches('+').parse().ok()?;
+    if ordinal == 0 || !(-5..=5).contains(&ordinal) {
+        return None;
+    }
+    Some((Some(ordinal), weekday))
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(19, 0, 0)
+            .unwrap()
+    }
+
+    /// All occurrences of `rule` from `dtstart` on, up to the end of 2040
+    fn expand(rule: &str, dtstart: NaiveDateTime) -> Vec<NaiveDateTime> {
+        RRule::parse(rule)
+            .unwrap()
+            .occurrences(dtstart, dtstart, at(2040, 12, 31), 100)
+    }
+
+    #[test]
+    fn parses_and_writes_rules() {
+        let rule =
+            RRule::parse("RRULE:freq=monthly;interval=2;byday=2TH,-1FR;count=6;wkst=MO").unwrap();
+        assert_eq!(rule.frequency, Frequency::Monthly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.count, Some(6));
+        assert_eq!(
+            rule.by_day,
+            vec![(Some(2), Weekday::Thu), (Some(-1), Weekday::Fri)]
+        );
+        assert_eq!(
+            rule.to_string(),
+            "FREQ=MONTHLY;INTERVAL=2;COUNT=6;BYDAY=2TH,-1FR"
+        );
+        assert_eq!(RRule::parse(&rule.to_string()).unwrap(), rule);
+
+        let rule = RRule::parse("FREQ=WEEKLY;UNTIL=20260301").unwrap();
+        assert_eq!(
+            rule.until,
+            NaiveDate::from_ymd_opt(2026, 3, 1)
+                .unwrap()
+                .and_hms_opt(23, 59, 59)
+        );
+        assert!(rule.is_bounded());
+        assert!(!RRule::parse("FREQ=DAILY").unwrap().is_bounded());
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(matches!(
+            RRule::parse(""),
+            Err(RRuleError::MissingFrequency)
+        ));
+        assert!(matches!(
+            RRule::parse("FREQ=WEEKLY;COUNT=2;UNTIL=20260101"),
+            Err(RRuleError::CountAndUntil)
+        ));
+        for rule in [
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=0",
+            "FREQ=DAILY;UNTIL=tomorrow",
+            "FREQ=MONTHLY;BYDAY=6MO",
+            "FREQ=MONTHLY;BYDAY=XX",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+        ] {
+            assert!(
+                matches!(RRule::parse(rule), Err(RRuleError::InvalidValue { .. })),
+                "{}",
+                rule
+            );
+        }
+        for rule in [
+            "FREQ=DAILY;BYSETPOS=1",
+            "FREQ=WEEKLY;BYDAY=2TH",
+            "FREQ=DAILY;BYDAY=MO",
+            "FREQ=WEEKLY;BYMONTHDAY=3",
+        ] {
+            assert!(
+                matches!(RRule::parse(rule), Err(RRuleError::UnsupportedPart(_))),
+                "{}",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_non_ascii_weekdays() {
+        for rule in [
+            "FREQ=WEEKLY;BYDAY=ÄX",
+            "FREQ=WEEKLY;BYDAY=Ä",
+            "FREQ=MONTHLY;BYDAY=1ÄÖ",
+            "FREQ=MONTHLY;BYDAY=MO,ÜTU",
+        ] {
+            assert!(
+                matches!(RRule::parse(rule), Err(RRuleError::InvalidValue { .. })),
+                "{}",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn expands_daily_and_weekly_rules() {
+        // 2026-01-01 is a Thursday
+        assert_eq!(
+            expand("FREQ=DAILY;INTERVAL=2;COUNT=3", at(2026, 1, 1)),
+            [at(2026, 1, 1), at(2026, 1, 3), at(2026, 1, 5)]
+        );
+        assert_eq!(
+            expand("FREQ=DAILY;UNTIL=20260103T190000", at(2026, 1, 1)),
+            [at(2026, 1, 1), at(2026, 1, 2), at(2026, 1, 3)]
+        );
+        assert_eq!(
+            expand("FREQ=WEEKLY;BYDAY=MO,TH;COUNT=4", at(2026, 1, 1)),
+            [
+                at(2026, 1, 1),
+                at(2026, 1, 5),
+                at(2026, 1, 8),
+                at(2026, 1, 12)
+            ]
+        );
+        assert_eq!(
+            expand("FREQ=WEEKLY;INTERVAL=2;COUNT=3", at(2026, 1, 1)),
+            [at(2026, 1, 1), at(2026, 1, 15), at(2026, 1, 29)]
+        );
+    }
+
+    #[test]
+    fn expands_monthly_and_yearly_rules() {
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=2TH;COUNT=3", at(2026, 1, 8)),
+            [at(2026, 1, 8), at(2026, 2, 12), at(2026, 3, 12)]
+        );
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20260430", at(2026, 1, 30)),
+            [
+                at(2026, 1, 30),
+                at(2026, 2, 27),
+                at(2026, 3, 27),
+                at(2026, 4, 24)
+            ]
+        );
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYMONTHDAY=-1;COUNT=3", at(2026, 1, 31)),
+            [at(2026, 1, 31), at(2026, 2, 28), at(2026, 3, 31)]
+        );
+        // Months without the start's day are skipped
+        assert_eq!(
+            expand("FREQ=MONTHLY;COUNT=3", at(2026, 1, 31)),
+            [at(2026, 1, 31), at(2026, 3, 31), at(2026, 5, 31)]
+        );
+        assert_eq!(
+            expand("FREQ=YEARLY;COUNT=3", at(2024, 2, 29)),
+            [at(2024, 2, 29), at(2028, 2, 29), at(2032, 2, 29)]
+        );
+    }
+
+    #[test]
+    fn limits_occurrences_to_the_window() {
+        let rule = RRule::parse("FREQ=WEEKLY").unwrap();
+        assert_eq!(
+            rule.occurrences(at(2026, 1, 1), at(2026, 2, 1), at(2040, 12, 31), 2),
+            [at(2026, 2, 5), at(2026, 2, 12)]
+        );
+
+        // Occurrences before the window still count towards COUNT
+        let rule = RRule::parse("FREQ=WEEKLY;COUNT=3").unwrap();
+        assert_eq!(
+            rule.occurrences(at(2026, 1, 1), at(2026, 1, 10), at(2040, 12, 31), 100),
+            [at(2026, 1, 15)]
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use crate::db::{GUEST_COLUMNS, Guest, Invitation, Party};
+use crate::invitation::format_date_time_in;
+use chrono_tz::Tz;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// Organizers write `{{variable}}` placeholders in invitation content and notification texts.
+// They are filled in on the server for each guest, so every channel (invitation page, email,
+// push) shows the same text. Supported syntax:
+//   {{party_name}}                      the value, or the placeholder itself if unknown
+//   {{salutation|Hi}}                   the value, or "Hi" if it is empty
+//   {{#if location}}…{{else}}…{{/if}}   the first part if the value is not empty
+
+/// Named values for the template variables
+#[derive(Debug, Default, Clone)]
+pub struct Variables(HashMap<&'static str, String>);
+
+impl Variables {
+    pub fn set(&mut self, name: &'static str, value: impl Into<String>) -> &mut Self {
+        self.0.insert(name, value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    fn is_set(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|value| !value.is_empty())
+    }
+
+    /// `{{party_name}}`, `{{party_date}}`, `{{party_time}}`, `{{location}}`, `{{respond_until}}`
+    /// and `{{author_name}}`; `{{date}}` and `{{time}}` are kept for older invitations.
+    /// Dates are shown in `time_zone`.
+    pub fn for_party(party: &Party, author_name: &str, language: &str, time_zone: Tz) -> Self {
+        let (date, time) = format_date_time_in(&party.date, language, party.zone(), time_zone);
+        let (respond_until, _) =
+            format_date_time_in(&party.respond_until, language, party.zone(), time_zone);
+        let mut variables = Variables::default();
+        variables
+            .set("party_name", party.name.as_str())
+            .set("party_date", date.as_str())
+            .set("party_time", time.as_str())
+            .set("date", date)
+            .set("time", time)
+            .set("location", party.location.as_str())
+            .set("respond_until", respond_until)
+            .set("author_name", author_name);
+        variables
+    }
+
+    /// `{{salutation}}`, `{{first}}`, `{{last}}` and `{{name}}` (first and last name)
+    pub fn set_guest(&mut self, salutation: &str, first: &str, last: &str) -> &mut Self {
+        self.set("salutation", salutation)
+            .set("first", first)
+            .set("last", last)
+            .set("name", format!("{} {}", first, last).trim().to_string())
+    }
+}
+
+/// The address Party Hub is reachable at
+pub fn base_url() -> String {
+    std::env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string())
+}
+
+/// The address guests open their invitation (or a household's shared link) at
+pub fn invitation_url(invitation_id: &str) -> String {
+    format!("{}/{}", base_url(), invitation_id)
+}
+
+/// All variables for a guest's invitation, formatted for the guest's notification language,
+/// which is returned as well
+pub fn load(conn: &Connection, invitation_id: &str) -> rusqlite::Result<(Variables, String)> {
+    let invitation = Invitation::load(conn, invitation_id)?;
+    let party = Party::load(conn, &invitation.party_id)?;
+    let guest = conn.query_row(
+        &format!("SELECT {} FROM guests WHERE id = ?1", GUEST_COLUMNS),
+        [&invitation.guest_id],
+        Guest::from_row,
+    )?;
+    let author_name: String = conn.query_row(
+        "SELECT name FROM authors WHERE id = ?1",
+        [&party.author],
+        |row| row.get(0),
+    )?;
+
+    let language = guest.notification_language(&party.language).to_string();
+    let mut variables = Variables::for_party(&party, &author_name, &language, party.zone());
+    variables
+        .set_guest(&guest.salutation, &guest.first, &guest.last)
+        .set("invitation_url", invitation_url(invitation_id));
+    Ok((variables, language))
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Tag { inner: &'a str, raw: &'a str },
+}
+
+enum Node<'a> {
+    Text(&'a str),
+    Variable {
+        name: &'a str,
+        fallback: Option<&'a str>,
+        raw: &'a str,
+    },
+    If {
+        name: &'a str,
+        then: Vec<Node<'a>>,
+        otherwise: Vec<Node<'a>>,
+    },
+}
+
+fn tokenize(template: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + length + 2;
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+        tokens.push(Token::Tag {
+            inner: rest[start + 2..end - 2].trim(),
+            raw: &rest[start..end],
+        });
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    tokens
+}
+
+/// Parses nodes up to the end of the template or, inside a conditional, up to its
+/// `{{else}}` or `{{/if}}`, which is returned as well. An unclosed conditional runs to the end.
+fn parse<'a>(
+    tokens: &mut std::slice::Iter<'_, Token<'a>>,
+    in_conditional: bool,
+) -> (Vec<Node<'a>>, Option<&'a str>) {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        match *token {
+            Token::Text(text) => nodes.push(Node::Text(text)),
+            Token::Tag { inner, .. } if in_conditional && (inner == "else" || inner == "/if") => {
+                return (nodes, Some(inner));
+            }
+            Token::Tag { inner, raw } => {
+                if let Some(name) = inner.strip_prefix("#if ") {
+                    let (then, end) = parse(tokens, true);
+                    let otherwise = if end == Some("else") {
+                        parse(tokens, true).0
+                    } else {
+                        Vec::new()
+                    };
+                    nodes.push(Node::If {
+                        name: name.trim(),
+                        then,
+                        otherwise,
+                    });
+                } else if let Some((name, fallback)) = inner.split_once('|') {
+                    nodes.push(Node::Variable {
+                        name: name.trim(),
+                        fallback: Some(fallback.trim()),
+                        raw,
+                    });
+                } else {
+                    nodes.push(Node::Variable {
+                        name: inner,
+                        fallback: None,
+                        raw,
+                    });
+                }
+            }
+        }
+    }
+    (nodes, None)
+}
+
+fn render_nodes(
+    nodes: &[Node],
+    variables: &Variables,
+    escape: &dyn Fn(&str) -> String,
+    output: &mut String,
+) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable {
+                name,
+                fallback,
+                raw,
+            } => match (variables.get(name), fallback) {
+                (Some(value), _) if !value.is_empty() => output.push_str(&escape(value)),
+                (_, Some(fallback)) => output.push_str(fallback),
+                (Some(_), None) => {}
+                // Unknown names are left alone, so text that merely looks like a variable survives
+                (None, None) => output.push_str(raw),
+            },
+            Node::If {
+                name,
+                then,
+                otherwise,
+            } => {
+                let branch = if variables.is_set(name) {
+                    then
+                } else {
+                    otherwise
+                };
+                render_nodes(branch, variables, escape, output);
+            }
+        }
+    }
+}
+
+/// Fills in the variables, passing each value through `escape` (fallbacks and the rest of
+/// the template are the organizer's own text and stay as they are)
+pub fn render_with(
+    template: &str,
+    variables: &Variables,
+    escape: &dyn Fn(&str) -> String,
+) -> String {
+    let tokens = tokenize(template);
+    let (nodes, _) = parse(&mut tokens.iter(), false);
+    let mut output = String::with_capacity(template.len());
+    render_nodes(&nodes, variables, escape, &mut output);
+    output
+}
+
+/// Fills in the variables for plain text such as emails and push notifications
+pub fn render(template: &str, variables: &Variables) -> String {
+    render_with(template, variables, &|value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Variables {
+        let mut variables = Variables::default();
+        variables
+            .set("party_name", "Summer party")
+            .set("location", "Garden")
+            .set("salutation", "")
+            .set("first", "Bo");
+        variables
+    }
+
+    #[test]
+    fn fills_in_values() {
+        assert_eq!(
+            render("Welcome to {{party_name}}, {{ first }}!", &variables()),
+            "Welcome to Summer party, Bo!"
+        );
+        // Known but empty values leave nothing behind
+        assert_eq!(render("[{{salutation}}]", &variables()), "[]");
+    }
+
+    #[test]
+    fn leaves_unknown_placeholders_alone() {
+        assert_eq!(
+            render("{{unknown}} and {{ spaced out }}", &variables()),
+            "{{unknown}} and {{ spaced out }}"
+        );
+        // Unclosed braces are plain text
+        assert_eq!(
+            render("{{first}} {{party_name", &variables()),
+            "Bo {{party_name"
+        );
+        assert_eq!(render("}} {{", &variables()), "}} {{");
+    }
+
+    #[test]
+    fn uses_fallbacks_for_empty_values() {
+        assert_eq!(render("{{salutation|Hi}} {{first}}", &variables()), "Hi Bo");
+        assert_eq!(render("{{first|friend}}", &variables()), "Bo");
+        // Unknown variables take the fallback as well, which may be empty
+        assert_eq!(
+            render("{{nickname | dear guest }}", &variables()),
+            "dear guest"
+        );
+        assert_eq!(render("a{{nickname|}}b", &variables()), "ab");
+    }
+
+    #[test]
+    fn picks_conditional_branches() {
+        let template = "{{#if location}}At {{location}}{{else}}Place to be announced{{/if}}.";
+        assert_eq!(render(template, &variables()), "At Garden.");
+        let mut without_location = variables();
+        without_location.set("location", "");
+        assert_eq!(
+            render(template, &without_location),
+            "Place to be announced."
+        );
+        assert_eq!(render("{{#if nickname}}Hey{{/if}}!", &variables()), "!");
+    }
+
+    #[test]
+    fn nests_conditionals() {
+        let template = "{{#if first}}{{#if salutation}}{{salutation}} {{else}}Hi {{/if}}{{first}}{{else}}Hello{{/if}}";
+        assert_eq!(render(template, &variables()), "Hi Bo");
+        let mut with_salutation = variables();
+        with_salutation.set("salutation", "Dear");
+        assert_eq!(render(template, &with_salutation), "Dear Bo");
+        let mut without_name = variables();
+        without_name.set("first", "");
+        assert_eq!(render(template, &without_name), "Hello");
+    }
+
+    #[test]
+    fn unclosed_conditionals_run_to_the_end() {
+        assert_eq!(
+            render("A{{#if location}} at {{location}}", &variables()),
+            "A at Garden"
+        );
+        assert_eq!(render("A{{#if nickname}} hidden", &variables()), "A");
+        assert_eq!(
+            render("{{#if nickname}}x{{else}} shown", &variables()),
+            " shown"
+        );
+    }
+
+    #[test]
+    fn keeps_stray_closing_tags() {
+        assert_eq!(
+            render("{{first}}{{/if}} and {{else}}", &variables()),
+            "Bo{{/if}} and {{else}}"
+        );
+        // A second `{{else}}` inside a conditional ends it
+        assert_eq!(
+            render("{{#if first}}a{{else}}b{{else}}c{{/if}}", &variables()),
+            "ac{{/if}}"
+        );
+    }
+
+    #[test]
+    fn escapes_only_values() {
+        let escape = |value: &str| value.to_uppercase();
+        assert_eq!(
+            render_with(
+                "{{first}} {{salutation|hi}} {{unknown}}",
+                &variables(),
+                &escape
+            ),
+            "BO hi {{unknown}}"
+        );
+    }
+}
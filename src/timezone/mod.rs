@@ -0,0 +1,266 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz, TzOffset};
+use std::sync::LazyLock;
+
+// Party dates are stored as local wall-clock times ("2026-12-01T19:00") together with the
+// IANA time zone of the party (e.g. "Europe/Berlin"). Parties without one, including all
+// parties from before time zones existed, use the server's default zone: the one set with the
+// `TIME_ZONE` environment variable, else the server's local zone, as before (UTC if unknown).
+
+/// The server's local zone, looked up once. Servers often name UTC "Etc/UTC", which is
+/// taken as UTC itself, so its times are written as such.
+static LOCAL_TIME_ZONE: LazyLock<Option<Tz>> =
+    LazyLock::new(|| match iana_time_zone::get_timezone().ok()?.as_str() {
+        "Etc/UTC" | "Etc/UCT" | "Etc/Universal" | "Etc/Zulu" | "UCT" | "Universal" | "Zulu" => {
+            Some(Tz::UTC)
+        }
+        name => parse(name),
+    });
+
+/// The zone for parties that don't name one
+pub fn default_time_zone() -> Tz {
+    std::env::var("TIME_ZONE")
+        .ok()
+        .and_then(|name| parse(&name))
+        .or(*LOCAL_TIME_ZONE)
+        .unwrap_or(Tz::UTC)
+}
+
+/// An IANA time zone name such as "Europe/Berlin"
+pub fn parse(name: &str) -> Option<Tz> {
+    name.trim().parse().ok()
+}
+
+/// The zone `name`, or the default zone if it is empty or unknown
+pub fn or_default(name: &str) -> Tz {
+    parse(name).unwrap_or_else(default_time_zone)
+}
+
+/// The current wall-clock time in `time_zone`
+pub fn now_in(time_zone: Tz) -> NaiveDateTime {
+    Utc::now().with_timezone(&time_zone).naive_local()
+}
+
+/// The instant a wall-clock time in `time_zone` refers to. Times that occur twice when the clocks
+/// go back are taken the first time; times skipped when they go forward are moved past the gap.
+pub fn to_utc(local: NaiveDateTime, time_zone: Tz) -> DateTime<Utc> {
+    let resolve = |local: NaiveDateTime| match time_zone.from_local_datetime(&local) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => {
+            Some(time.with_timezone(&Utc))
+        }
+        LocalResult::None => None,
+    };
+    resolve(local)
+        // An hour later is past the gap, which is the same instant as the skipped time taken
+        // with the offset from before the clocks went forward (as RFC 5545 asks)
+        .or_else(|| resolve(local + Duration::hours(1)))
+        .unwrap_or_else(|| local.and_utc())
+}
+
+/// A wall-clock time in `from` as the wall-clock time in `to`
+pub fn convert(local: NaiveDateTime, from: Tz, to: Tz) -> NaiveDateTime {
+    to_utc(local, from).with_timezone(&to).naive_local()
+}
+
+/// The zone's abbreviation at a wall-clock time ("CET", "EDT"), or its UTC offset if it has none
+pub fn abbreviation(local: NaiveDateTime, time_zone: Tz) -> String {
+    offset_name(&time_zone.offset_from_utc_datetime(&to_utc(local, time_zone).naive_utc()))
+}
+
+fn offset_name(offset: &TzOffset) -> String {
+    match offset.abbreviation() {
+        Some(abbreviation) if !abbreviation.starts_with(['+', '-']) => abbreviation.to_string(),
+        _ => format!("UTC{}", format_offset(offset.fix().local_minus_utc())),
+    }
+}
+
+/// An offset in seconds as `+hhmm` (or `+hhmmss`), as used by iCalendar
+fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if seconds == 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    } else {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    }
+}
+
+/// The first instant after `from` at which the zone's offset (or abbreviation) changes,
+/// if there is one before `until`
+fn next_transition(
+    time_zone: Tz,
+    from: NaiveDateTime,
+    until: NaiveDateTime,
+) -> Option<NaiveDateTime> {
+    let offset_at = |utc: NaiveDateTime| {
+        let offset = time_zone.offset_from_utc_datetime(&utc);
+        (offset.fix(), offset.abbreviation().map(str::to_string))
+    };
+    let initial = offset_at(from);
+
+    // Offsets change at most a few times a year, so stepping by a day finds every change
+    let mut before = from;
+    loop {
+        let after = (before + Duration::days(1)).min(until);
+        if offset_at(after) != initial {
+            // Narrow the day down to the second the change happens at
+            let (mut low, mut high) = (before, after);
+            while high - low > Duration::seconds(1) {
+                let middle = low + (high - low) / 2;
+                if offset_at(middle) == initial {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            return Some(high);
+        }
+        if after >= until {
+            return None;
+        }
+        before = after;
+    }
+}
+
+/// A `VTIMEZONE` component describing `time_zone` for events between `start` and `end`
+/// (wall-clock times in that zone). Every change of offset in that period is listed on its own,
+/// so calendar apps don't need to know the zone to show the events at the right time.
+/// UTC needs none, since its times are written as such (see `ics_property`).
+pub fn vtimezone(time_zone: Tz, start: NaiveDateTime, end: NaiveDateTime) -> String {
+    if time_zone == Tz::UTC {
+        return String::new();
+    }
+    // A year of margin covers events that are moved a little without a new calendar
+    let from = to_utc(start, time_zone).naive_utc() - Duration::days(366);
+    let until = to_utc(end, time_zone).naive_utc() + Duration::days(366);
+
+    let observance = |utc: NaiveDateTime, offset_from: i32| {
+        let offset = time_zone.offset_from_utc_datetime(&utc);
+        let offset_to = offset.fix().local_minus_utc();
+        let kind = if offset.dst_offset().is_zero() {
+            "STANDARD"
+        } else {
+            "DAYLIGHT"
+        };
+        let onset = utc + Duration::seconds(offset_from.into());
+        format!(
+            "BEGIN:{kind}\r\nDTSTART:{}\r\nTZOFFSETFROM:{}\r\nTZOFFSETTO:{}\r\nTZNAME:{}\r\nEND:{kind}\r\n",
+            onset.format("%Y%m%dT%H%M%S"),
+            format_offset(offset_from),
+            format_offset(offset_to),
+            offset_name(&offset),
+        )
+    };
+
+    let mut component = format!("BEGIN:VTIMEZONE\r\nTZID:{}\r\n", time_zone.name());
+    let initial_offset = time_zone
+        .offset_from_utc_datetime(&from)
+        .fix()
+        .local_minus_utc();
+    component.push_str(&observance(from, initial_offset));
+    let mut previous = from;
+    while let Some(transition) = next_transition(time_zone, previous, until) {
+        let offset_from = time_zone
+            .offset_from_utc_datetime(&(transition - Duration::seconds(1)))
+            .fix()
+            .local_minus_utc();
+        component.push_str(&observance(transition, offset_from));
+        previous = transition;
+    }
+    component.push_str("END:VTIMEZONE\r\n");
+    component
+}
+
+/// An iCalendar date-time property such as `DTSTART`, for a wall-clock time in `time_zone`.
+/// UTC times are written as such; others refer to the zone's `VTIMEZONE`.
+pub fn ics_property(name: &str, local: NaiveDateTime, time_zone: Tz) -> String {
+    if time_zone == Tz::UTC {
+        format!("{}:{}Z", name, local.format("%Y%m%dT%H%M%S"))
+    } else {
+        format!(
+            "{};TZID={}:{}",
+            name,
+            time_zone.name(),
+            local.format("%Y%m%dT%H%M%S")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn vtimezone_lists_every_change_around_the_events() {
+        let berlin = chrono_tz::Europe::Berlin;
+        let component = vtimezone(berlin, at(2026, 7, 1, 19), at(2026, 7, 1, 22));
+
+        assert!(component.starts_with("BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\n"));
+        assert!(component.ends_with("END:VTIMEZONE\r\n"));
+        assert!(component.contains(
+            "BEGIN:DAYLIGHT\r\nDTSTART:20260329T020000\r\nTZOFFSETFROM:+0100\r\nTZOFFSETTO:+0200\r\nTZNAME:CEST\r\nEND:DAYLIGHT\r\n"
+        ));
+        assert!(component.contains(
+            "BEGIN:STANDARD\r\nDTSTART:20261025T030000\r\nTZOFFSETFROM:+0200\r\nTZOFFSETTO:+0100\r\nTZNAME:CET\r\nEND:STANDARD\r\n"
+        ));
+        // A year on either side: the summer it starts in, and the changes until summer 2027
+        assert_eq!(component.matches("BEGIN:DAYLIGHT").count(), 3);
+        assert_eq!(component.matches("BEGIN:STANDARD").count(), 2);
+    }
+
+    #[test]
+    fn vtimezone_without_changes() {
+        let component = vtimezone(
+            chrono_tz::Asia::Tokyo,
+            at(2026, 7, 1, 19),
+            at(2026, 7, 1, 22),
+        );
+        assert_eq!(component.matches("BEGIN:STANDARD").count(), 1);
+        assert!(component.contains("TZOFFSETFROM:+0900\r\nTZOFFSETTO:+0900\r\nTZNAME:JST\r\n"));
+        assert!(!component.contains("DAYLIGHT"));
+
+        assert_eq!(
+            vtimezone(Tz::UTC, at(2026, 7, 1, 19), at(2026, 7, 1, 22)),
+            ""
+        );
+    }
+
+    #[test]
+    fn converts_wall_clock_times() {
+        let berlin = chrono_tz::Europe::Berlin;
+        assert_eq!(
+            to_utc(at(2026, 7, 1, 19), berlin),
+            at(2026, 7, 1, 17).and_utc()
+        );
+        // 02:30 doesn't exist on the day the clocks go forward and is moved past the gap
+        let skipped = at(2026, 3, 29, 2) + Duration::minutes(30);
+        assert_eq!(
+            to_utc(skipped, berlin),
+            at(2026, 3, 29, 1).and_utc() + Duration::minutes(30)
+        );
+        // 02:30 happens twice on the day the clocks go back and is taken the first time
+        let repeated = at(2026, 10, 25, 2) + Duration::minutes(30);
+        assert_eq!(
+            to_utc(repeated, berlin),
+            at(2026, 10, 25, 0).and_utc() + Duration::minutes(30)
+        );
+        assert_eq!(
+            convert(at(2026, 7, 1, 19), berlin, chrono_tz::America::New_York),
+            at(2026, 7, 1, 13)
+        );
+        assert_eq!(abbreviation(at(2026, 1, 1, 12), berlin), "CET");
+        assert_eq!(
+            ics_property("DTSTART", at(2026, 1, 1, 12), berlin),
+            "DTSTART;TZID=Europe/Berlin:20260101T120000"
+        );
+    }
+}
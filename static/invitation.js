@@ -12,7 +12,7 @@ function saveInvitationToLocalStorage(invitationId, data) {
             name: data.party_name || 'Unnamed Party',
             date: data.party_date || '',
             author: data.author_name || 'Unknown',
-            invitee: data.guest_name || 'Unknown'
+            invitee: (data.household && data.household.name) || data.guest_name || 'Unknown'
         };
 
         // Check if invitation already exists
@@ -33,12 +33,35 @@ function saveInvitationToLocalStorage(invitationId, data) {
     }
 }
 
+function formatFileSize(bytes) {
+    if (bytes < 1024) return `${bytes} B`;
+    if (bytes < 1024 * 1024) return `${Math.round(bytes / 1024)} KB`;
+    return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+}
+
 // ===== MODEL =====
 class InvitationModel {
     constructor() {
         this.answers = new Map();
         this.listeners = new Set();
         this.invitationData = null;
+        // Plus-ones the guest brings, `active` while the attendance answer allows companions
+        this.companions = { max: 0, askNames: false, count: 0, names: [], active: false };
+    }
+
+    setCompanions(count, names) {
+        this.companions.count = count;
+        this.companions.names = names;
+        this.notifyListeners('companionsChanged', { count, names });
+    }
+
+    // Companions to submit; nobody comes along with a guest who doesn't come
+    getCompanionsForSave() {
+        if (!this.companions.active) return { companions: 0, companion_names: [] };
+        return {
+            companions: this.companions.count,
+            companion_names: this.companions.names.slice(0, this.companions.count)
+        };
     }
 
     setAnswer(blockId, value) {
@@ -100,6 +123,7 @@ class InvitationModel {
 class InvitationView {
     constructor() {
         this.invitation_section = document.querySelector("section#invitation");
+        this.companions = { max: 0, askNames: false, count: 0, names: [], active: false, onChange: () => { } };
         this.templates = {
             error: document.querySelector("template#error"),
             multiple_choice: document.querySelector("template#multiple-choice"),
@@ -111,8 +135,20 @@ class InvitationView {
             text_input: document.querySelector("template#text-input"),
             number_input: document.querySelector("template#number-input"),
             calendar: document.querySelector("template#calendar"),
+            image_block: document.querySelector("template#image-block"),
+            file_block: document.querySelector("template#file-block"),
+            file_download: document.querySelector("template#file-download"),
+            item_signup: document.querySelector("template#item-signup"),
+            item_signup_item: document.querySelector("template#item-signup-item"),
+            date_poll: document.querySelector("template#date-poll"),
+            date_poll_row: document.querySelector("template#date-poll-row"),
+            date_poll_results: document.querySelector("template#date-poll-results"),
             public_stats: document.querySelector("template#public-stats"),
             response_item: document.querySelector("template#response-item"),
+            incomplete_notice: document.querySelector("template#incomplete-notice"),
+            waitlist_notice: document.querySelector("template#waitlist-notice"),
+            household_switcher: document.querySelector("template#household-switcher"),
+            companion_name_placeholder: document.querySelector("template#companion-name-placeholder"),
             status_saving: document.querySelector("template#status-saving"),
             status_saved: document.querySelector("template#status-saved"),
             status_save: document.querySelector("template#status-save"),
@@ -122,52 +158,111 @@ class InvitationView {
         };
     }
 
-    personalizeContent(content, guestData) {
-        if (typeof content === 'string') {
-            let personalized = content;
-            if (guestData) {
-                personalized = personalized.replace(/\{\{salutation\}\}/g, guestData.salutation || '');
-                personalized = personalized.replace(/\{\{first\}\}/g, guestData.first || '');
-                personalized = personalized.replace(/\{\{last\}\}/g, guestData.last || '');
-                personalized = personalized.replace(/\{\{name\}\}/g, guestData.name || '');
-                personalized = personalized.replace(/\{\{date\}\}/g, guestData.date || '');
-                personalized = personalized.replace(/\{\{time\}\}/g, guestData.time || '');
+    showWaitlistPosition(position) {
+        this.invitation_section.querySelector('.waitlist-notice')?.remove();
+        if (!position) return;
+        const notice = this.templates.waitlist_notice.content.cloneNode(true);
+        notice.querySelector('.waitlist-position').textContent = position;
+        this.invitation_section.prepend(notice);
+    }
+
+    showHouseholdSwitcher(household, currentInvitationId, onSwitch) {
+        this.invitation_section.querySelector('.household-switcher')?.remove();
+        if (!household || household.members.length < 2) return;
+        const switcher = this.templates.household_switcher.content.cloneNode(true);
+        const current = household.members.find(member => member.invitation_id === currentInvitationId);
+        switcher.querySelector('.household-member-name').textContent = current ? current.name : '';
+        switcher.querySelector('.household-name').textContent = household.name;
+        const membersDiv = switcher.querySelector('.household-members');
+        household.members.forEach(member => {
+            const button = document.createElement('button');
+            button.textContent = member.name;
+            if (member.invitation_id === currentInvitationId) {
+                button.classList.add('active');
+                button.disabled = true;
             }
-            return personalized;
-        }
-        return content;
+            button.addEventListener('click', () => onSwitch(member.invitation_id));
+            membersDiv.appendChild(button);
+        });
+        this.invitation_section.prepend(switcher);
     }
 
-    render(invitation_blocks, invitation_block_answers, other_guests_answers, guestData, isOrganizer, onInputChange) {
+    render(invitation_blocks, invitation_block_answers, other_guests_answers, guestData, isOrganizer, missingRequired, onInputChange) {
         if (invitation_blocks.length < 1) return;
         this.invitation_section.innerHTML = "";
+        if (missingRequired.length > 0) {
+            this.invitation_section.appendChild(this.templates.incomplete_notice.content.cloneNode(true));
+        }
         invitation_blocks.forEach((block, i) => {
             const blockId = block.id || i.toString(); // Use block ID if available, fallback to index
             const answer = invitation_block_answers.hasOwnProperty(blockId) ? invitation_block_answers[blockId] : null;
             const div = document.createElement("div");
             div.classList.add("block");
-            div.appendChild(this.createBlock(block.template, block.content, answer, blockId, other_guests_answers, guestData, isOrganizer, onInputChange));
+            div.dataset.blockId = blockId;
+            div.appendChild(this.createBlock(block, answer, blockId, other_guests_answers, guestData, isOrganizer, onInputChange));
+            if (block.required) {
+                const label = div.querySelector('label');
+                if (label) label.textContent += ' *';
+            }
             this.invitation_section.appendChild(div);
         });
+        this.applyConditions(invitation_blocks, invitation_block_answers);
+    }
+
+    // Hides blocks whose condition isn't met. Conditions refer to blocks above,
+    // so a block that depends on a hidden block is hidden as well.
+    applyConditions(invitation_blocks, answers) {
+        const shown = new Set();
+        invitation_blocks.forEach((block, i) => {
+            const blockId = block.id || i.toString();
+            const condition = block.condition;
+            const visible = !condition || (shown.has(condition.block_id) && this.conditionMet(condition, answers[condition.block_id]));
+            if (visible) shown.add(blockId);
+            const div = this.invitation_section.querySelector(`.block[data-block-id="${CSS.escape(blockId)}"]`);
+            if (div) div.style.display = visible ? '' : 'none';
+        });
     }
 
-    createBlock(template, content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
-        if (['h1', 'h2', 'h3', 'p', 'code'].includes(template)) {
-            const el = document.createElement(template);
-            el.textContent = this.personalizeContent(content, guestData);
+    // Multiple choice answers hold one flag per option, the others the chosen index
+    conditionMet(condition, answer) {
+        if (Array.isArray(answer)) return condition.options.some(index => answer[index] === true);
+        return typeof answer === 'number' && condition.options.includes(answer);
+    }
+
+    createBlock(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
+        if (['heading', 'paragraph', 'code'].includes(block.type)) {
+            const tag = block.type === 'heading' ? `h${block.level}` : block.type === 'paragraph' ? 'p' : 'code';
+            // Markdown in paragraphs and headings arrives rendered and sanitized by the server.
+            // A rendered paragraph brings its own <p> elements (and maybe lists), so it goes into a div.
+            const rendered = typeof block.html === 'string';
+            const el = document.createElement(rendered && block.type === 'paragraph' ? 'div' : tag);
+            if (rendered) {
+                if (block.type === 'paragraph') el.classList.add('markdown');
+                el.innerHTML = block.html;
+            } else {
+                el.textContent = block.text;
+            }
             return el;
         } else {
-            switch (template) {
+            switch (block.type) {
+                case 'image':
+                    return this.createImage(block);
+                case 'file':
+                    return this.createFile(block);
                 case 'multiple_choice':
-                    return this.createMultipleChoice(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
+                    return this.createMultipleChoice(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
                 case 'single_choice':
-                    return this.createSingleChoice(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
+                    return this.createSingleChoice(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
                 case 'attendance':
-                    return this.createAttendance(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
+                    return this.createAttendance(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
                 case 'text_input':
-                    return this.createTextInput(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
+                    return this.createTextInput(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
                 case 'number_input':
-                    return this.createNumberInput(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
+                    return this.createNumberInput(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange);
+                case 'item_signup':
+                    return this.createItemSignup(block, answer_data, blockId, other_guests_answers, guestData, onInputChange);
+                case 'date_poll':
+                    return this.createDatePoll(block, answer_data, blockId, guestData, onInputChange);
                 case 'calendar':
                     return this.createCalendar();
                 default:
@@ -176,27 +271,21 @@ class InvitationView {
         }
     }
 
-    createMultipleChoice(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
+    createMultipleChoice(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
         const mc = this.templates.multiple_choice.content.cloneNode(true);
-        let mcContent;
-        try {
-            mcContent = typeof content === 'string' ? JSON.parse(content) : content;
-        } catch {
-            mcContent = { label: content, options: ["Yes", "No"] };
-        }
 
-        mc.querySelector("label").textContent = this.personalizeContent(mcContent.label, guestData);
+        mc.querySelector("label").textContent = block.label;
         const ul = mc.querySelector('ul');
 
         const currentAnswer = answer_data || [];
-        const isPublic = mcContent.public === true;
+        const isPublic = block.visibility === 'public';
 
         // Calculate stats if this block is public or user is organizer
         let optionCounts = [];
         let optionGuestNames = []; // Track which guests selected each option
         if ((isPublic || isOrganizer) && other_guests_answers) {
-            optionCounts = new Array(mcContent.options.length).fill(0);
-            optionGuestNames = new Array(mcContent.options.length).fill().map(() => []);
+            optionCounts = new Array(block.options.length).fill(0);
+            optionGuestNames = new Array(block.options.length).fill().map(() => []);
 
             // Include other guests' answers
             other_guests_answers.forEach(guestAnswers => {
@@ -226,7 +315,7 @@ class InvitationView {
             }
         }
 
-        mcContent.options.forEach((o, i) => {
+        block.options.forEach((o, i) => {
             const mci = this.templates.multiple_choice_item.content.cloneNode(true);
             const li = mci.querySelector('li');
             const checkbox = li.querySelector('input');
@@ -260,8 +349,8 @@ class InvitationView {
             const updateStatsDisplay = () => {
                 if ((isPublic || isOrganizer) && optionCounts.length > 0) {
                     // Recalculate counts including current selections
-                    const updatedCounts = new Array(mcContent.options.length).fill(0);
-                    const updatedGuestNames = new Array(mcContent.options.length).fill().map(() => []);
+                    const updatedCounts = new Array(block.options.length).fill(0);
+                    const updatedGuestNames = new Array(block.options.length).fill().map(() => []);
 
                     // Include other guests' answers
                     other_guests_answers.forEach(guestAnswers => {
@@ -290,7 +379,7 @@ class InvitationView {
                     });
 
                     // Update all span texts with new counts and guest names
-                    mcContent.options.forEach((o, i) => {
+                    block.options.forEach((o, i) => {
                         const currentSpan = ul.querySelectorAll('span')[i];
                         if (currentSpan) {
                             const count = updatedCounts[i] || 0;
@@ -319,28 +408,22 @@ class InvitationView {
         return mc;
     }
 
-    createSingleChoice(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
+    createSingleChoice(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
         const sc = this.templates.single_choice.content.cloneNode(true);
-        let scContent;
-        try {
-            scContent = typeof content === 'string' ? JSON.parse(content) : content;
-        } catch {
-            scContent = { label: content, options: ["Yes", "No"] };
-        }
 
-        sc.querySelector("label").textContent = this.personalizeContent(scContent.label, guestData);
+        sc.querySelector("label").textContent = block.label;
         const ul = sc.querySelector('ul');
 
         const currentAnswer = answer_data !== undefined && answer_data !== null ? answer_data : -1; // Single choice uses index, -1 means no selection
-        const isPublic = scContent.public === true;
+        const isPublic = block.visibility === 'public';
         const radioGroupName = `single_choice_${blockId}`;
 
         // Calculate stats if this block is public or user is organizer
         let optionCounts = [];
         let optionGuestNames = []; // Track which guests selected each option
         if ((isPublic || isOrganizer) && other_guests_answers) {
-            optionCounts = new Array(scContent.options.length).fill(0);
-            optionGuestNames = new Array(scContent.options.length).fill().map(() => []);
+            optionCounts = new Array(block.options.length).fill(0);
+            optionGuestNames = new Array(block.options.length).fill().map(() => []);
 
             // Include other guests' answers
             other_guests_answers.forEach(guestAnswers => {
@@ -362,7 +445,7 @@ class InvitationView {
             }
         }
 
-        scContent.options.forEach((o, i) => {
+        block.options.forEach((o, i) => {
             const sci = this.templates.single_choice_item.content.cloneNode(true);
             const li = sci.querySelector('li');
             const radio = li.querySelector('input');
@@ -399,8 +482,8 @@ class InvitationView {
             const updateStatsDisplay = () => {
                 if ((isPublic || isOrganizer) && optionCounts.length > 0) {
                     // Recalculate counts including current selection
-                    const updatedCounts = new Array(scContent.options.length).fill(0);
-                    const updatedGuestNames = new Array(scContent.options.length).fill().map(() => []);
+                    const updatedCounts = new Array(block.options.length).fill(0);
+                    const updatedGuestNames = new Array(block.options.length).fill().map(() => []);
 
                     // Include other guests' answers
                     other_guests_answers.forEach(guestAnswers => {
@@ -426,7 +509,7 @@ class InvitationView {
                     }
 
                     // Update all span texts with new counts and guest names
-                    scContent.options.forEach((o, i) => {
+                    block.options.forEach((o, i) => {
                         const currentSpan = ul.querySelectorAll('span')[i];
                         if (currentSpan) {
                             const count = updatedCounts[i] || 0;
@@ -455,55 +538,117 @@ class InvitationView {
         return sc;
     }
 
-    createAttendance(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
+    createAttendance(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
         const at = this.templates.attendance.content.cloneNode(true);
-        let atContent;
-        try {
-            atContent = typeof content === 'string' ? JSON.parse(content) : content;
-        } catch {
-            atContent = { label: content, options: ["Yes", "Maybe", "No"] };
-        }
-
-        // If no options specified, use default attendance options
-        if (!atContent.options || atContent.options.length === 0) {
-            atContent.options = ["Yes", "Maybe", "No"];
-        }
 
-        at.querySelector("label").textContent = this.personalizeContent(atContent.label, guestData);
+        at.querySelector("label").textContent = block.label;
         const ul = at.querySelector('ul');
 
         const currentAnswer = answer_data !== undefined && answer_data !== null ? answer_data : -1;
-        const isPublic = atContent.public === true;
+        const isPublic = block.visibility === 'public';
         const radioGroupName = `attendance_${blockId}`;
+        const companions = this.companions;
 
-        // Calculate stats if this block is public or user is organizer
-        let optionCounts = [];
-        let optionGuestNames = [];
-        if ((isPublic || isOrganizer) && other_guests_answers) {
-            optionCounts = new Array(atContent.options.length).fill(0);
-            optionGuestNames = new Array(atContent.options.length).fill().map(() => []);
+        // Each answer counts the guest plus their companions
+        const countHeads = (selectedIndex) => {
+            const counts = new Array(block.options.length).fill(0);
+            const guestNames = new Array(block.options.length).fill().map(() => []);
 
             // Include other guests' answers
             other_guests_answers.forEach(guestAnswers => {
                 const blockAnswerData = guestAnswers[blockId];
                 if (blockAnswerData) {
                     const blockAnswer = blockAnswerData.answer;
-                    const guestName = blockAnswerData.guest_name;
-
-                    if (typeof blockAnswer === 'number' && blockAnswer >= 0 && blockAnswer < optionCounts.length) {
-                        optionCounts[blockAnswer]++;
-                        optionGuestNames[blockAnswer].push(guestName);
+                    const guestCompanions = blockAnswerData.companions || 0;
+                    const guestName = guestCompanions > 0
+                        ? `${blockAnswerData.guest_name} (+${guestCompanions})`
+                        : blockAnswerData.guest_name;
+
+                    if (typeof blockAnswer === 'number' && blockAnswer >= 0 && blockAnswer < counts.length) {
+                        counts[blockAnswer] += 1 + guestCompanions;
+                        guestNames[blockAnswer].push(guestName);
                     }
                 }
             });
 
             // Include current user's answer
-            if (typeof currentAnswer === 'number' && currentAnswer >= 0 && currentAnswer < optionCounts.length) {
-                optionCounts[currentAnswer]++;
+            if (selectedIndex >= 0 && selectedIndex < counts.length) {
+                counts[selectedIndex] += 1 + companions.count;
             }
+            return { counts, guestNames };
+        };
+
+        const showStats = (isPublic || isOrganizer) && other_guests_answers;
+
+        const updateStatsDisplay = () => {
+            if (!showStats) return;
+            const selectedRadio = ul.querySelector('input[type="radio"]:checked');
+            const selectedIndex = selectedRadio ? parseInt(selectedRadio.value) : -1;
+            const { counts, guestNames } = countHeads(selectedIndex);
+
+            block.options.forEach((o, i) => {
+                const currentSpan = ul.querySelectorAll('span')[i];
+                if (currentSpan) {
+                    const count = counts[i] || 0;
+                    const names = guestNames[i] || [];
+                    if (names.length > 0) {
+                        currentSpan.textContent = `${o} (${count}) - ${names.join(', ')}`;
+                    } else {
+                        currentSpan.textContent = `${o} (${count})`;
+                    }
+                }
+            });
+        };
+
+        // Plus-ones are asked for when the guest is coming or might come
+        const companionsDiv = at.querySelector('.companions');
+        const companionCount = companionsDiv.querySelector('.companion-count');
+        const companionNames = companionsDiv.querySelector('.companion-names');
+        const namePlaceholder = this.templates.companion_name_placeholder.content.textContent;
+
+        const updateCompanions = () => {
+            companions.onChange(companions.count, companions.names.slice(0, companions.count));
+            updateStatsDisplay();
+        };
+
+        const renderCompanionNames = () => {
+            companionNames.innerHTML = '';
+            if (!companions.askNames) return;
+            for (let i = 0; i < companions.count; i++) {
+                const nameInput = document.createElement('input');
+                nameInput.type = 'text';
+                nameInput.maxLength = 100;
+                nameInput.placeholder = namePlaceholder;
+                nameInput.value = companions.names[i] || '';
+                nameInput.addEventListener('input', () => {
+                    companions.names[i] = nameInput.value;
+                    updateCompanions();
+                });
+                companionNames.appendChild(nameInput);
+            }
+        };
+
+        const toggleCompanions = () => {
+            const selectedRadio = ul.querySelector('input[type="radio"]:checked');
+            const selectedIndex = selectedRadio ? parseInt(selectedRadio.value) : -1;
+            const bringsCompanions = companions.max > 0 && (selectedIndex === 0 || selectedIndex === 1);
+            companionsDiv.style.display = bringsCompanions ? 'block' : 'none';
+            companions.active = bringsCompanions;
+        };
+
+        if (companions.max > 0) {
+            companionCount.max = companions.max;
+            companionCount.value = companions.count;
+            renderCompanionNames();
+            companionCount.addEventListener('input', () => {
+                const count = parseInt(companionCount.value);
+                companions.count = Number.isNaN(count) ? 0 : Math.min(Math.max(count, 0), companions.max);
+                renderCompanionNames();
+                updateCompanions();
+            });
         }
 
-        atContent.options.forEach((o, i) => {
+        block.options.forEach((o, i) => {
             const ati = this.templates.attendance_item.content.cloneNode(true);
             const li = ati.querySelector('li');
             const radio = li.querySelector('input');
@@ -511,72 +656,19 @@ class InvitationView {
 
             radio.name = radioGroupName;
             radio.value = i;
-
-            // Set option text with public stats and guest names if available
-            if ((isPublic || isOrganizer) && optionCounts.length > 0) {
-                const count = optionCounts[i] || 0;
-                const names = optionGuestNames[i] || [];
-                if (names.length > 0) {
-                    span.textContent = `${o} (${count}) - ${names.join(', ')}`;
-                } else {
-                    span.textContent = `${o} (${count})`;
-                }
-            } else {
-                span.textContent = o;
-            }
-
+            span.textContent = o;
             radio.checked = currentAnswer === i;
 
             const updateAnswer = () => {
                 const selectedRadio = ul.querySelector('input[type="radio"]:checked');
                 const selectedIndex = selectedRadio ? parseInt(selectedRadio.value) : -1;
                 onInputChange(blockId, selectedIndex);
+                toggleCompanions();
 
                 // Update stats in real-time
                 updateStatsDisplay();
             };
 
-            const updateStatsDisplay = () => {
-                if ((isPublic || isOrganizer) && optionCounts.length > 0) {
-                    const updatedCounts = new Array(atContent.options.length).fill(0);
-                    const updatedGuestNames = new Array(atContent.options.length).fill().map(() => []);
-
-                    other_guests_answers.forEach(guestAnswers => {
-                        const blockAnswerData = guestAnswers[blockId];
-                        if (blockAnswerData) {
-                            const blockAnswer = blockAnswerData.answer;
-                            const guestName = blockAnswerData.guest_name;
-
-                            if (typeof blockAnswer === 'number' && blockAnswer >= 0 && blockAnswer < updatedCounts.length) {
-                                updatedCounts[blockAnswer]++;
-                                updatedGuestNames[blockAnswer].push(guestName);
-                            }
-                        }
-                    });
-
-                    const selectedRadio = ul.querySelector('input[type="radio"]:checked');
-                    if (selectedRadio) {
-                        const selectedIndex = parseInt(selectedRadio.value);
-                        if (selectedIndex >= 0 && selectedIndex < updatedCounts.length) {
-                            updatedCounts[selectedIndex]++;
-                        }
-                    }
-
-                    atContent.options.forEach((o, i) => {
-                        const currentSpan = ul.querySelectorAll('span')[i];
-                        if (currentSpan) {
-                            const count = updatedCounts[i] || 0;
-                            const names = updatedGuestNames[i] || [];
-                            if (names.length > 0) {
-                                currentSpan.textContent = `${o} (${count}) - ${names.join(', ')}`;
-                            } else {
-                                currentSpan.textContent = `${o} (${count})`;
-                            }
-                        }
-                    });
-                }
-            };
-
             radio.addEventListener('change', updateAnswer);
 
             li.addEventListener('click', function (e) {
@@ -588,29 +680,20 @@ class InvitationView {
 
             ul.appendChild(li);
         });
+
+        // Set option text with public stats and guest names if available
+        toggleCompanions();
+        updateStatsDisplay();
         return at;
     }
 
-    createTextInput(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
+    createTextInput(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
         const ti = this.templates.text_input.content.cloneNode(true);
         const textInput = ti.querySelector("input");
 
-        let textContent;
-        try {
-            textContent = typeof content === 'string' ? JSON.parse(content) : content;
-            // Handle case where content is an array (from YAML parsing)
-            if (Array.isArray(textContent) && textContent.length > 0) {
-                textContent = textContent[0];
-            }
-        } catch {
-            textContent = { label: content };
-        }
-
-        // Handle both string and object content
-        const label = typeof textContent === 'object' ? textContent.label : textContent;
-        const isPublic = typeof textContent === 'object' && textContent.public === true;
+        const isPublic = block.visibility === 'public';
 
-        ti.querySelector("label").textContent = this.personalizeContent(label, guestData);
+        ti.querySelector("label").textContent = block.label;
         textInput.value = answer_data || '';
 
         textInput.addEventListener('input', () => {
@@ -626,7 +709,7 @@ class InvitationView {
                     const blockAnswer = blockAnswerData.answer;
                     const guestName = blockAnswerData.guest_name;
 
-                    if (blockAnswer && typeof blockAnswer === 'string' && blockAnswer.trim() !== '') {
+                    if (typeof blockAnswer === 'number') {
                         otherAnswers.push({ answer: blockAnswer, guest_name: guestName });
                     }
                 }
@@ -649,30 +732,17 @@ class InvitationView {
         return ti;
     }
 
-    createNumberInput(content, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
+    createNumberInput(block, answer_data, blockId, other_guests_answers, guestData, isOrganizer, onInputChange) {
         const ni = this.templates.number_input.content.cloneNode(true);
         const numberInput = ni.querySelector("input");
 
-        let numberContent;
-        try {
-            numberContent = typeof content === 'string' ? JSON.parse(content) : content;
-            // Handle case where content is an array (from YAML parsing)
-            if (Array.isArray(numberContent) && numberContent.length > 0) {
-                numberContent = numberContent[0];
-            }
-        } catch {
-            numberContent = { label: content };
-        }
-
-        // Handle both string and object content
-        const label = typeof numberContent === 'object' ? numberContent.label : numberContent;
-        const isPublic = typeof numberContent === 'object' && numberContent.public === true;
+        const isPublic = block.visibility === 'public';
 
-        ni.querySelector("label").textContent = this.personalizeContent(label, guestData);
-        numberInput.value = answer_data || '';
+        ni.querySelector("label").textContent = block.label;
+        numberInput.value = answer_data ?? '';
 
         numberInput.addEventListener('input', () => {
-            onInputChange(blockId, numberInput.value);
+            onInputChange(blockId, numberInput.value === '' ? null : Number(numberInput.value));
         });
 
         // Add public stats if enabled or user is organizer
@@ -707,14 +777,193 @@ class InvitationView {
         return ni;
     }
 
+    // Uploads are fetched through the invitation, which grants access to them
+    attachmentUrl(attachmentId) {
+        return `/attachment/${encodeURIComponent(this.invitationId)}/${encodeURIComponent(attachmentId)}`;
+    }
+
+    createImage(block) {
+        const image = this.templates.image_block.content.cloneNode(true);
+        const link = image.querySelector('a');
+        const img = image.querySelector('img');
+        link.href = this.attachmentUrl(block.attachment_id);
+        img.src = `${this.attachmentUrl(block.attachment_id)}/thumbnail`;
+        img.alt = block.alt || '';
+        return image;
+    }
+
+    createFile(block) {
+        const file = this.templates.file_block.content.cloneNode(true);
+        const attachment = (this.attachments || {})[block.attachment_id];
+        file.querySelector('a').href = this.attachmentUrl(block.attachment_id);
+        file.querySelector('.file-label').textContent = block.label || attachment?.file_name || this.templates.file_download.content.textContent;
+        if (attachment) file.querySelector('.file-size').textContent = formatFileSize(attachment.size);
+        return file;
+    }
+
+    createItemSignup(block, answer_data, blockId, other_guests_answers, guestData, onInputChange) {
+        const is = this.templates.item_signup.content.cloneNode(true);
+        is.querySelector("label").textContent = block.label;
+        const ul = is.querySelector('ul');
+
+        // Claimed quantity per item, in the order of the block's items
+        const quantities = block.items.map((_, i) =>
+            Array.isArray(answer_data) && typeof answer_data[i] === 'number' ? answer_data[i] : 0
+        );
+
+        // Other guests' claims arrive with the public answers (or for organizers)
+        const claimedByOthers = block.items.map(() => 0);
+        const claimerNames = block.items.map(() => []);
+        (other_guests_answers || []).forEach(guestAnswers => {
+            const blockAnswerData = guestAnswers[blockId];
+            if (!blockAnswerData || !Array.isArray(blockAnswerData.answer)) return;
+            blockAnswerData.answer.forEach((quantity, i) => {
+                if (typeof quantity === 'number' && quantity > 0 && i < block.items.length) {
+                    claimedByOthers[i] += quantity;
+                    const guestName = blockAnswerData.guest_name;
+                    claimerNames[i].push(quantity > 1 ? `${guestName} (${quantity})` : guestName);
+                }
+            });
+        });
+
+        block.items.forEach((item, i) => {
+            const row = this.templates.item_signup_item.content.cloneNode(true);
+            const li = row.querySelector('li');
+            const checkbox = li.querySelector('.item-claim');
+            const quantityInput = li.querySelector('.item-quantity');
+            const status = li.querySelector('.item-status');
+            li.querySelector('.item-name').textContent = item.name;
+
+            const available = Math.max(item.capacity - claimedByOthers[i], 0);
+            const updateStatus = () => {
+                const names = claimerNames[i].join(', ');
+                status.textContent = `${claimedByOthers[i] + quantities[i]}/${item.capacity}` + (names ? ` - ${names}` : '');
+            };
+            const updateAnswer = (quantity) => {
+                quantities[i] = quantity;
+                onInputChange(blockId, [...quantities]);
+                updateStatus();
+            };
+
+            // Items needed once are ticked, others take a quantity
+            if (item.capacity === 1) {
+                quantityInput.remove();
+                checkbox.checked = quantities[i] > 0;
+                checkbox.disabled = available === 0 && quantities[i] === 0;
+                checkbox.addEventListener('change', () => updateAnswer(checkbox.checked ? 1 : 0));
+                li.addEventListener('click', (e) => {
+                    if (e.target !== checkbox && !checkbox.disabled) {
+                        checkbox.checked = !checkbox.checked;
+                        updateAnswer(checkbox.checked ? 1 : 0);
+                    }
+                });
+            } else {
+                checkbox.remove();
+                quantityInput.max = Math.max(available, quantities[i]);
+                quantityInput.value = quantities[i];
+                quantityInput.disabled = available === 0 && quantities[i] === 0;
+                quantityInput.addEventListener('input', () => {
+                    updateAnswer(Math.max(parseInt(quantityInput.value) || 0, 0));
+                });
+            }
+
+            if (available === 0 && quantities[i] === 0) li.classList.add('taken');
+            updateStatus();
+            ul.appendChild(row);
+        });
+
+        return is;
+    }
+
+    createDatePoll(block, answer_data, blockId, guestData, onInputChange) {
+        const dp = this.templates.date_poll.content.cloneNode(true);
+        dp.querySelector("label").textContent = block.label;
+        const tbody = dp.querySelector('tbody');
+
+        // One choice per slot (0 = yes, 1 = if need be, 2 = no), null for slots left open
+        const choices = block.slots.map((_, i) =>
+            Array.isArray(answer_data) && typeof answer_data[i] === 'number' ? answer_data[i] : null
+        );
+
+        block.slots.forEach((slot, i) => {
+            const row = this.templates.date_poll_row.content.cloneNode(true);
+            row.querySelector('.date-poll-slot').textContent = this.formatSlot(slot);
+            row.querySelectorAll('input[type="radio"]').forEach(radio => {
+                radio.name = `date_poll_${blockId}_${i}`;
+                radio.checked = choices[i] === parseInt(radio.value);
+                radio.addEventListener('change', () => {
+                    choices[i] = parseInt(radio.value);
+                    onInputChange(blockId, [...choices]);
+                });
+            });
+            tbody.appendChild(row);
+        });
+
+        // The server only sends results of polls this guest may see
+        const results = this.datePolls && this.datePolls[blockId];
+        if (results && results.rows.length > 0) {
+            dp.appendChild(this.createDatePollResults(results));
+        }
+
+        return dp;
+    }
+
+    createDatePollResults(results) {
+        const stats = this.templates.date_poll_results.content.cloneNode(true);
+        const headRow = stats.querySelector('thead tr');
+        const footRow = stats.querySelector('tfoot tr');
+        const tbody = stats.querySelector('tbody');
+        const symbols = ['✓', '(✓)', '✗'];
+
+        results.slots.forEach((slot, i) => {
+            const th = document.createElement('th');
+            th.textContent = this.formatSlot(slot.slot);
+            const td = document.createElement('td');
+            td.textContent = `${slot.yes} (${slot.if_need_be})`;
+            if (results.winner === i) {
+                th.classList.add('winner');
+                td.classList.add('winner');
+            }
+            headRow.appendChild(th);
+            footRow.appendChild(td);
+        });
+
+        results.rows.forEach(row => {
+            const tr = document.createElement('tr');
+            const nameCell = document.createElement('td');
+            nameCell.textContent = row.guest_name;
+            tr.appendChild(nameCell);
+            results.slots.forEach((_, i) => {
+                const td = document.createElement('td');
+                const choice = row.answers[i];
+                if (typeof choice === 'number') {
+                    td.textContent = symbols[choice] || '';
+                    td.classList.add(`choice-${choice}`);
+                }
+                if (results.winner === i) td.classList.add('winner');
+                tr.appendChild(td);
+            });
+            tbody.appendChild(tr);
+        });
+
+        return stats;
+    }
+
+    formatSlot(slot) {
+        const date = new Date(slot);
+        if (isNaN(date)) return slot;
+        return date.toLocaleString(document.documentElement.lang || undefined, {
+            weekday: 'short', day: 'numeric', month: 'short', hour: '2-digit', minute: '2-digit'
+        });
+    }
+
     createCalendar() {
         const cal = this.templates.calendar.content.cloneNode(true);
         const downloadBtn = cal.querySelector('.download-calendar-btn');
 
         downloadBtn.addEventListener('click', async () => {
-            // Get the invitation ID from the URL
-            const url = new URL(window.location.href);
-            const invitationId = url.pathname.split('/').pop();
+            // Household members share a link, so use the invitation currently shown
+            const invitationId = this.invitationId;
 
             if (!invitationId) {
                 console.error('No invitation ID found');
@@ -731,9 +980,28 @@ class InvitationView {
             }
         });
 
+        // A subscription keeps all of the guest's invitations up to date in their calendar app
+        const subscribeLink = cal.querySelector('.subscribe-calendar-link');
+        if (this.calendarFeedUrl) {
+            subscribeLink.href = this.calendarFeedUrl.replace(/^https?:/, 'webcal:');
+            subscribeLink.style.display = '';
+        }
+
         return cal;
     }
 
+    showBlockErrors(blockErrors) {
+        this.invitation_section.querySelectorAll('.block-error').forEach(el => el.remove());
+        Object.entries(blockErrors || {}).forEach(([blockId, message]) => {
+            const div = this.invitation_section.querySelector(`.block[data-block-id="${CSS.escape(blockId)}"]`);
+            if (!div) return;
+            const p = document.createElement('p');
+            p.classList.add('block-error');
+            p.textContent = message;
+            div.appendChild(p);
+        });
+    }
+
     showSaveStatus(status, message) {
         const saveButton = document.querySelector("#form-save");
         if (!saveButton) return;
@@ -773,6 +1041,10 @@ class InvitationController {
     constructor() {
         this.model = new InvitationModel();
         this.view = new InvitationView();
+        // The ID from the URL, which may be a household link shared by several invitations
+        const path = window.location.pathname.split('/').filter(Boolean);
+        this.linkId = path.length > 0 ? path[0] : null;
+        this.invitationId = this.linkId;
         this.init();
     }
 
@@ -781,6 +1053,9 @@ class InvitationController {
         this.model.addListener((event, data) => {
             if (event === 'dataLoaded') {
                 this.renderInvitation();
+            } else if (event === 'answerChanged') {
+                const data = this.model.getInvitationData();
+                if (data) this.view.applyConditions(data.invitation_blocks, this.model.getAllAnswers());
             }
         });
 
@@ -794,6 +1069,19 @@ class InvitationController {
     async loadInvitationData() {
         try {
             const data = await this.getDetails();
+            // Household links resolve to one of the members' invitations
+            if (data.invitation_id) this.invitationId = data.invitation_id;
+            this.view.invitationId = this.invitationId;
+            this.view.calendarFeedUrl = data.calendar_feed_url || null;
+            this.view.datePolls = data.date_polls || {};
+            this.view.attachments = data.attachments || {};
+            this.model.answers.clear();
+            Object.assign(this.model.companions, {
+                max: data.max_companions || 0,
+                askNames: data.ask_companion_names || false,
+                count: data.companions || 0,
+                names: data.companion_names || []
+            });
             this.model.setInvitationData(data);
 
             // Initialize model with existing answers
@@ -811,14 +1099,24 @@ class InvitationController {
         const data = this.model.getInvitationData();
         if (!data) return;
 
+        // The view edits the model's companions in place and reports changes back
+        this.view.companions = Object.assign(this.model.companions, {
+            onChange: (count, names) => this.model.setCompanions(count, names)
+        });
         this.view.render(
             data.invitation_blocks,
             data.invitation_block_answers || {},
             data.other_guests_answers || [],
             this.model.getGuestData(), // Pass guest data object for personalization
             data.is_organizer || false, // Pass organizer status to view
+            data.missing_required || [],
             (blockId, value) => this.model.setAnswer(blockId, value)
         );
+        this.view.showWaitlistPosition(data.waitlist_position);
+        this.view.showHouseholdSwitcher(data.household, this.invitationId, (invitationId) => {
+            this.invitationId = invitationId;
+            this.loadInvitationData();
+        });
     }
 
     setupSaveButton() {
@@ -829,16 +1127,17 @@ class InvitationController {
     }
 
     async getDetails() {
-        const path = window.location.pathname.split('/').filter(Boolean);
-        const invitationId = path.length > 0 ? path[0] : null;
+        const invitationId = this.invitationId;
         if (invitationId) {
             try {
-                const response = await fetch(`/invitation/${invitationId}`);
+                // Dates are shown in the guest's own time zone
+                const timeZone = Intl.DateTimeFormat().resolvedOptions().timeZone || '';
+                const response = await fetch(`/invitation/${invitationId}?time_zone=${encodeURIComponent(timeZone)}`);
                 if (!response.ok) throw new Error('Network response was not ok');
                 const data = await response.json();
 
-                // Save invitation to localStorage
-                saveInvitationToLocalStorage(invitationId, data);
+                // Save invitation to localStorage under the link that was opened
+                saveInvitationToLocalStorage(this.linkId, data);
 
                 return data;
             } catch (_error) { }
@@ -847,8 +1146,7 @@ class InvitationController {
     }
 
     async saveAnswers() {
-        const path = window.location.pathname.split('/').filter(Boolean);
-        const invitationId = path.length > 0 ? path[0] : null;
+        const invitationId = this.invitationId;
 
         if (!invitationId) {
             this.view.showSaveStatus('error', this.view.templates.error_no_invitation_id.content.textContent);
@@ -861,23 +1159,25 @@ class InvitationController {
             // Store answers in localStorage and redirect to registration page
             localStorage.setItem('public_party_answers', JSON.stringify({
                 partyId: invitationData.party_id,
-                answers: this.model.getAllAnswers()
+                answers: this.model.getAllAnswers(),
+                ...this.model.getCompanionsForSave()
             }));
 
             // Redirect to registration page
-            window.location.href = '/register';
+            window.location.href = `/register?party=${encodeURIComponent(invitationData.party_id)}`;
             return;
         }
 
         try {
             this.view.showSaveStatus('saving');
+            this.view.showBlockErrors({});
 
             const response = await fetch(`/invitation/${invitationId}`, {
                 method: 'POST',
                 headers: {
                     'Content-Type': 'application/json',
                 },
-                body: JSON.stringify({ answers: this.model.getAllAnswers() })
+                body: JSON.stringify({ answers: this.model.getAllAnswers(), ...this.model.getCompanionsForSave() })
             });
 
             const result = await response.json();
@@ -892,6 +1192,9 @@ class InvitationController {
                     window.location.href = '/register';
                 } else {
                     this.view.showSaveStatus('success');
+                    // A successful save means every required question is answered
+                    this.view.invitation_section.querySelector('.incomplete-notice')?.remove();
+                    this.view.showWaitlistPosition(result.waitlist_position);
                     
                     // Associate guest with device subscription
                     const invitationData = this.model.getInvitationData();
@@ -909,6 +1212,7 @@ class InvitationController {
                     }
                 }
             } else {
+                this.view.showBlockErrors(result.block_errors);
                 throw new Error(result.error || 'Save failed');
             }
         } catch (error) {
@@ -925,4 +1229,11 @@ const userAnswers = model.answers; // Backward compatibility
 // Initialize the application
 document.addEventListener('DOMContentLoaded', () => {
     new InvitationController();
+
+    // The server remembers a language picked with ?lang= for later visits
+    document.querySelector('#language-switcher')?.addEventListener('change', (event) => {
+        const url = new URL(window.location.href);
+        url.searchParams.set('lang', event.target.value);
+        window.location.href = url.toString();
+    });
 });
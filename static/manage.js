@@ -5,6 +5,10 @@ const addPartyBtn = document.getElementById('add-party-btn');
 const toastContainer = document.getElementById('toast-container');
 const guests = document.querySelector("div#guests");
 const addNewGuestBtn = document.getElementById('add-new-guest-btn');
+const households = document.querySelector("div#households");
+const addHouseholdBtn = document.getElementById('add-household-btn');
+const copyPartiesFeedBtn = document.getElementById('copy-parties-feed-btn');
+const resetPartiesFeedBtn = document.getElementById('reset-parties-feed-btn');
 
 // Toast notification utility
 function showToast(message, type = 'info', duration = 4000) {
@@ -34,6 +38,12 @@ const templateError = document.querySelector("template#error");
 const templatePartyLi = document.querySelector("template#party-li");
 const templateEditParty = document.querySelector("template#edit-party");
 const templateGuest = document.querySelector("template#guest");
+const templateWaitlistEntry = document.querySelector("template#waitlist-entry");
+const templateSeriesOccurrence = document.querySelector("template#series-occurrence");
+const templatePollSlot = document.querySelector("template#poll-slot");
+const templateConditionOption = document.querySelector("template#condition-option");
+const templateDatePollResults = document.querySelector("template#date-poll-results");
+const templateDatePollAdoptButton = document.querySelector("template#date-poll-adopt-button");
 const templateInvitationBlock = document.querySelector("template#invitation-block");
 const templateModal = document.querySelector("template#add-guest-modal");
 const templateGuestItem = document.querySelector("template#modal-guest-item");
@@ -41,12 +51,21 @@ const templateToast = document.querySelector("template#toast");
 const templateEmptyState = document.querySelector("template#empty-state");
 const templateGuestLi = document.querySelector("template#guest-li");
 const templateEditGuest = document.querySelector("template#edit-guest");
+const templateNewPartyModal = document.querySelector("template#new-party-modal");
+const templateModalTemplateItem = document.querySelector("template#modal-template-item");
+const templateHouseholdLi = document.querySelector("template#household-li");
+const templateEditHousehold = document.querySelector("template#edit-household");
+const templateHouseholdMember = document.querySelector("template#household-member");
 
 // Global variables for modal and guest data
 let currentPartyId = null;
 let allGuests = [];
 let currentPartyGuests = [];
+let allHouseholds = [];
+let currentPartyHouseholdLinks = new Set();
 let hasRsvpBlock = false;
+let allAttachments = [];
+let blockEditorPartyId = null;
 
 let blockOrder = [];
 
@@ -55,7 +74,7 @@ function initializeBlockEditor(container, blocks) {
     blockOrder = [];
 
     // Check if any block is an attendance block
-    hasRsvpBlock = blocks.some(block => block.template === 'attendance');
+    hasRsvpBlock = blocks.some(block => block.type === 'attendance');
 
     addInsertionPoint(container, 0);
 
@@ -65,7 +84,9 @@ function initializeBlockEditor(container, blocks) {
         blockOrder.push(blockId);
 
         const blockElement = createInvitationBlock(blockId, blockData);
+        const blockDiv = blockElement.querySelector('.invitation-block');
         container.appendChild(blockElement);
+        renderCondition(blockDiv, blockData.condition);
 
         addInsertionPoint(container, blockOrder.length);
     });
@@ -88,43 +109,85 @@ function createInvitationBlock(blockId, blockData = {}) {
     const attendanceOption2 = blockElement.querySelector('.attendance-option-2');
     const attendanceOption3 = blockElement.querySelector('.attendance-option-3');
     const visibilitySelect = blockElement.querySelector('select[name="visibility"]');
-
-    const template = blockData.template || 'p';
-    typeSelect.value = template;
+    const requiredInput = blockElement.querySelector('.block-required');
+    const minInput = blockElement.querySelector('.block-min');
+    const maxInput = blockElement.querySelector('.block-max');
+    const maxLengthInput = blockElement.querySelector('.block-max-length');
+    const minSelectionsInput = blockElement.querySelector('.block-min-selections');
+    const maxSelectionsInput = blockElement.querySelector('.block-max-selections');
+    const pollSlotList = blockElement.querySelector('.poll-slot-list');
+    const signupItemsInput = blockElement.querySelector('.signup-items');
+
+    // The editor offers one entry per heading level, the stored block keeps the level separately
+    const editorType = blockData.type === 'heading' ? `h${blockData.level || 1}`
+        : blockData.type === 'paragraph' ? 'p'
+        : blockData.type || 'p';
+    typeSelect.value = editorType;
 
     // Handle content based on block type
-    if (['single_choice', 'multiple_choice', 'text_input', 'number_input', 'attendance'].includes(template)) {
-        // For question types, parse JSON content
-        try {
-            const questionData = JSON.parse(blockData.content || '{}');
-            contentTextarea.value = questionData.label || '';
-            if (questionData.options && Array.isArray(questionData.options)) {
-                if (template === 'attendance') {
-                    // For attendance, populate three separate inputs
-                    attendanceOption1.value = questionData.options[0] || '';
-                    attendanceOption2.value = questionData.options[1] || '';
-                    attendanceOption3.value = questionData.options[2] || '';
-                } else {
-                    optionsInput.value = questionData.options.join(', ');
-                }
+    if (['single_choice', 'multiple_choice', 'text_input', 'number_input', 'attendance', 'date_poll', 'item_signup'].includes(editorType)) {
+        contentTextarea.value = blockData.label || '';
+        if (Array.isArray(blockData.options)) {
+            if (editorType === 'attendance') {
+                // For attendance, populate three separate inputs
+                attendanceOption1.value = blockData.options[0] || '';
+                attendanceOption2.value = blockData.options[1] || '';
+                attendanceOption3.value = blockData.options[2] || '';
+            } else {
+                optionsInput.value = blockData.options.join(', ');
             }
-            visibilitySelect.value = questionData.public ? 'public' : 'private';
-        } catch (e) {
-            // Fallback for old format or invalid JSON
-            contentTextarea.value = blockData.content || '';
-            if (blockData.options) optionsInput.value = blockData.options;
-            if (blockData.visibility) visibilitySelect.value = blockData.visibility;
         }
+        visibilitySelect.value = blockData.visibility === 'public' ? 'public' : 'private';
+        requiredInput.checked = !!blockData.required;
+        minInput.value = blockData.min ?? '';
+        maxInput.value = blockData.max ?? '';
+        maxLengthInput.value = blockData.max_length ?? '';
+        minSelectionsInput.value = blockData.min_selections ?? '';
+        maxSelectionsInput.value = blockData.max_selections ?? '';
+        (blockData.slots || []).forEach(slot => addPollSlot(pollSlotList, slot));
+        if (Array.isArray(blockData.items)) {
+            signupItemsInput.value = blockData.items
+                .map(item => item.capacity > 1 ? `${item.name} x${item.capacity}` : item.name)
+                .join(', ');
+        }
+    } else if (editorType === 'image') {
+        contentTextarea.value = blockData.alt || '';
+    } else if (editorType === 'file') {
+        contentTextarea.value = blockData.label || '';
     } else {
-        // For non-question blocks, use content directly
-        contentTextarea.value = blockData.content || '';
+        // For content blocks, the text is shown directly
+        contentTextarea.value = blockData.text || '';
     }
 
+    const attachmentSelect = blockElement.querySelector('.attachment-select');
+    fillAttachmentSelect(attachmentSelect, blockData.attachment_id || '');
+    attachmentSelect.addEventListener('change', () => updateAttachmentPreview(blockDiv));
+    blockElement.querySelector('.attachment-file').addEventListener('change', async (e) => {
+        const file = e.target.files[0];
+        e.target.value = '';
+        if (!file) return;
+        const attachment = await uploadAttachment(file);
+        if (!attachment) return;
+        allAttachments.unshift(attachment);
+        document.querySelectorAll('.attachment-select').forEach(select => fillAttachmentSelect(select, select.value));
+        attachmentSelect.value = attachment.id;
+        updateAttachmentPreview(blockDiv);
+    });
+
     typeSelect.addEventListener('change', (e) => {
         updateBlockVisibility(blockDiv, e.target.value);
         updateAttendanceAvailability();
     });
 
+    blockElement.querySelector('.add-poll-slot').addEventListener('click', () => addPollSlot(pollSlotList, ''));
+
+    // The questions above may have changed since the condition was rendered
+    const conditionSelect = blockElement.querySelector('.condition-block');
+    conditionSelect.addEventListener('focus', () => renderCondition(blockDiv, readCondition(blockDiv)));
+    conditionSelect.addEventListener('change', () => {
+        renderCondition(blockDiv, conditionSelect.value ? { block_id: conditionSelect.value, options: [0] } : null);
+    });
+
     const deleteBtn = blockElement.querySelector('.block-delete');
     deleteBtn.addEventListener('click', () => deleteBlock(blockId));
 
@@ -149,15 +212,148 @@ function updateBlockVisibility(blockDiv, blockType) {
     const isChoiceQuestion = ['single_choice', 'multiple_choice'].includes(blockType);
     optionsInput.style.display = isChoiceQuestion ? 'block' : 'none';
 
-    const isQuestion = ['single_choice', 'multiple_choice', 'text_input', 'number_input', 'attendance'].includes(blockType);
+    const isQuestion = ['single_choice', 'multiple_choice', 'text_input', 'number_input', 'attendance', 'date_poll', 'item_signup'].includes(blockType);
     visibilitySelect.style.display = isQuestion ? 'block' : 'none';
+
+    // Only show the constraints that apply to this question type
+    const constraintsDiv = blockDiv.querySelector('.question-constraints');
+    if (constraintsDiv) {
+        constraintsDiv.style.display = isQuestion ? 'block' : 'none';
+        constraintsDiv.querySelectorAll('.constraint-number').forEach(el => {
+            el.style.display = blockType === 'number_input' ? 'inline-block' : 'none';
+        });
+        constraintsDiv.querySelectorAll('.constraint-text').forEach(el => {
+            el.style.display = blockType === 'text_input' ? 'inline-block' : 'none';
+        });
+        constraintsDiv.querySelectorAll('.constraint-selections').forEach(el => {
+            el.style.display = blockType === 'multiple_choice' ? 'inline-block' : 'none';
+        });
+    }
     
+    const attachmentPicker = blockDiv.querySelector('.attachment-picker');
+    if (attachmentPicker) {
+        attachmentPicker.style.display = ['image', 'file'].includes(blockType) ? 'block' : 'none';
+        updateAttachmentPreview(blockDiv);
+    }
+
+    const signupItemsInput = blockDiv.querySelector('.signup-items');
+    if (signupItemsInput) {
+        signupItemsInput.style.display = blockType === 'item_signup' ? 'block' : 'none';
+    }
+
+    // Date polls list their proposed dates, a new poll starts with one empty slot
+    const pollSlotsDiv = blockDiv.querySelector('.poll-slots');
+    if (pollSlotsDiv) {
+        pollSlotsDiv.style.display = blockType === 'date_poll' ? 'block' : 'none';
+        const pollSlotList = pollSlotsDiv.querySelector('.poll-slot-list');
+        if (blockType === 'date_poll' && pollSlotList.children.length === 0) {
+            addPollSlot(pollSlotList, '');
+        }
+    }
+
     // Hide textarea for calendar block (it doesn't need content)
     if (contentTextarea) {
         contentTextarea.style.display = blockType === 'calendar' ? 'none' : 'block';
     }
 }
 
+// "Drinks x3" asks for three drinks, items without a count are needed once
+function parseSignupItems(raw) {
+    return raw.split(',')
+        .map(entry => entry.trim())
+        .filter(entry => entry)
+        .map(entry => {
+            const match = entry.match(/^(.*?)\s*[x×]\s*(\d+)$/i);
+            return match && match[1]
+                ? { name: match[1], capacity: parseInt(match[2]) }
+                : { name: entry, capacity: 1 };
+        });
+}
+
+function fillAttachmentSelect(select, selectedId) {
+    select.querySelectorAll('option:not([value=""])').forEach(option => option.remove());
+    allAttachments.forEach(attachment => select.add(new Option(attachment.file_name, attachment.id)));
+    select.value = selectedId;
+}
+
+// Images show a thumbnail of the chosen upload while editing
+function updateAttachmentPreview(blockDiv) {
+    const preview = blockDiv.querySelector('.attachment-preview');
+    const attachmentId = blockDiv.querySelector('.attachment-select').value;
+    const attachment = allAttachments.find(attachment => attachment.id === attachmentId);
+    const isImage = blockDiv.querySelector('.block-type-select').value === 'image'
+        && attachment && attachment.content_type.startsWith('image/');
+    if (isImage) {
+        preview.src = `/attachment/${encodeURIComponent(blockEditorPartyId)}/${encodeURIComponent(attachmentId)}/thumbnail`;
+    } else {
+        preview.removeAttribute('src');
+    }
+    preview.style.display = isImage ? 'block' : 'none';
+}
+
+async function uploadAttachment(file) {
+    const formData = new FormData();
+    formData.append('file', file);
+    try {
+        const response = await fetch('/attachment/upload', { method: 'POST', body: formData });
+        const result = await response.json();
+        if (!response.ok) throw new Error(result.error || 'Upload failed');
+        showToast('File uploaded', 'success');
+        return result;
+    } catch (error) {
+        console.error('Error uploading file:', error);
+        showToast(error.message, 'error');
+        return null;
+    }
+}
+
+// Conditions can only refer to choice questions above the block
+function renderCondition(blockDiv, condition) {
+    const select = blockDiv.querySelector('.condition-block');
+    const optionsDiv = blockDiv.querySelector('.condition-options');
+    const targets = [];
+    for (const other of blockDiv.parentNode.querySelectorAll('.invitation-block')) {
+        if (other === blockDiv) break;
+        const data = getBlockContent(other);
+        if (['single_choice', 'multiple_choice', 'attendance'].includes(data.type)) {
+            targets.push({ id: other.getAttribute('data-block-id'), label: data.label, options: data.options });
+        }
+    }
+
+    select.querySelectorAll('option:not([value=""])').forEach(option => option.remove());
+    targets.forEach(target => select.add(new Option(target.label || target.id, target.id)));
+
+    const target = targets.find(target => target.id === condition?.block_id);
+    select.value = target ? target.id : '';
+    blockDiv.querySelector('.condition-hint').style.display = target ? 'inline' : 'none';
+
+    optionsDiv.innerHTML = '';
+    (target?.options || []).forEach((option, index) => {
+        const optionElement = templateConditionOption.content.cloneNode(true);
+        const checkbox = optionElement.querySelector('input');
+        checkbox.value = index;
+        checkbox.checked = condition.options.includes(index);
+        optionElement.querySelector('span').textContent = option;
+        optionsDiv.appendChild(optionElement);
+    });
+}
+
+function readCondition(blockDiv) {
+    const blockId = blockDiv.querySelector('.condition-block')?.value;
+    if (!blockId) return undefined;
+    const options = Array.from(blockDiv.querySelectorAll('.condition-options input:checked'))
+        .map(input => parseInt(input.value));
+    return { block_id: blockId, options };
+}
+
+function addPollSlot(list, value) {
+    const slotElement = templatePollSlot.content.cloneNode(true);
+    const slotDiv = slotElement.querySelector('.poll-slot');
+    slotElement.querySelector('.poll-slot-input').value = value;
+    slotElement.querySelector('.remove-poll-slot').addEventListener('click', () => slotDiv.remove());
+    list.appendChild(slotElement);
+}
+
 function updateAttendanceAvailability() {
     // Check if any block currently has attendance type
     const hasAttendance = Array.from(document.querySelectorAll('.block-type-select')).some(
@@ -239,7 +435,9 @@ function rebuildBlockEditor(container) {
     blockOrder.forEach((blockId, index) => {
         const blockData = existingBlocks[blockId] || {};
         const blockElement = createInvitationBlock(blockId, blockData);
+        const blockDiv = blockElement.querySelector('.invitation-block');
         container.appendChild(blockElement);
+        renderCondition(blockDiv, blockData.condition);
         addInsertionPoint(container, index + 1);
     });
     
@@ -247,6 +445,13 @@ function rebuildBlockEditor(container) {
 }
 
 function getBlockData(blockElement) {
+    const blockData = getBlockContent(blockElement);
+    const condition = readCondition(blockElement);
+    if (condition) blockData.condition = condition;
+    return blockData;
+}
+
+function getBlockContent(blockElement) {
     const typeSelect = blockElement.querySelector('.block-type-select');
     const contentTextarea = blockElement.querySelector('textarea');
     const optionsInput = blockElement.querySelector('input[type="text"]');
@@ -254,36 +459,77 @@ function getBlockData(blockElement) {
     const attendanceOption2 = blockElement.querySelector('.attendance-option-2');
     const attendanceOption3 = blockElement.querySelector('.attendance-option-3');
     const visibilitySelect = blockElement.querySelector('select[name="visibility"]');
+    const requiredInput = blockElement.querySelector('.block-required');
 
-    const template = typeSelect?.value || 'p';
+    const editorType = typeSelect?.value || 'p';
     const rawContent = contentTextarea?.value || '';
     const rawOptions = optionsInput?.value || '';
     const visibility = visibilitySelect?.value || 'private';
 
-    if (['single_choice', 'multiple_choice', 'text_input', 'number_input', 'attendance'].includes(template)) {
+    if (['single_choice', 'multiple_choice', 'text_input', 'number_input', 'attendance', 'date_poll', 'item_signup'].includes(editorType)) {
         const questionData = {
+            type: editorType,
             label: rawContent,
-            public: visibility === 'public'
+            visibility: visibility,
+            required: requiredInput ? requiredInput.checked : false
+        };
+
+        // Empty constraint inputs mean "no limit"
+        const readNumber = (selector, parse) => {
+            const value = blockElement.querySelector(selector)?.value.trim();
+            return value ? parse(value) : undefined;
         };
+        if (editorType === 'number_input') {
+            questionData.min = readNumber('.block-min', parseFloat);
+            questionData.max = readNumber('.block-max', parseFloat);
+        } else if (editorType === 'text_input') {
+            questionData.max_length = readNumber('.block-max-length', v => parseInt(v));
+        } else if (editorType === 'date_poll') {
+            questionData.slots = Array.from(blockElement.querySelectorAll('.poll-slot-input'))
+                .map(input => input.value)
+                .filter(slot => slot);
+        } else if (editorType === 'item_signup') {
+            questionData.items = parseSignupItems(blockElement.querySelector('.signup-items')?.value || '');
+        } else if (editorType === 'multiple_choice') {
+            questionData.min_selections = readNumber('.block-min-selections', v => parseInt(v));
+            questionData.max_selections = readNumber('.block-max-selections', v => parseInt(v));
+        }
 
-        if (template === 'attendance') {
+        if (editorType === 'attendance') {
             // For attendance, get values from three separate inputs
             const opt1 = attendanceOption1?.value.trim() || 'Yes';
             const opt2 = attendanceOption2?.value.trim() || 'Maybe';
             const opt3 = attendanceOption3?.value.trim() || 'No';
             questionData.options = [opt1, opt2, opt3];
-        } else if (['single_choice', 'multiple_choice'].includes(template) && rawOptions) {
+        } else if (['single_choice', 'multiple_choice'].includes(editorType)) {
             questionData.options = rawOptions.split(',').map(opt => opt.trim()).filter(opt => opt);
         }
 
+        return questionData;
+    } else if (['h1', 'h2', 'h3'].includes(editorType)) {
+        return {
+            type: 'heading',
+            level: parseInt(editorType.substring(1)),
+            text: rawContent
+        };
+    } else if (editorType === 'calendar') {
+        return { type: 'calendar' };
+    } else if (editorType === 'image') {
         return {
-            template: template,
-            content: JSON.stringify(questionData)
+            type: 'image',
+            attachment_id: blockElement.querySelector('.attachment-select')?.value || '',
+            alt: rawContent
+        };
+    } else if (editorType === 'file') {
+        return {
+            type: 'file',
+            attachment_id: blockElement.querySelector('.attachment-select')?.value || '',
+            label: rawContent
         };
     } else {
         return {
-            template: template,
-            content: rawContent
+            type: editorType === 'code' ? 'code' : 'paragraph',
+            text: rawContent
         };
     }
 }
@@ -362,6 +608,12 @@ async function renderParty(partyId) {
         const publicLinkContainer = p.querySelector("#public-link-container");
         const frozenInput = p.querySelector("input#party-frozen-input");
         const publicInput = p.querySelector("input#party-public-input");
+        const waitlistInput = p.querySelector("input#party-waitlist-input");
+        const maxCompanionsInput = p.querySelector("input#party-max-companions-input");
+        const askCompanionNamesInput = p.querySelector("input#party-ask-companion-names-input");
+        const languageInput = p.querySelector("select#party-language-input");
+        const timeZoneInput = p.querySelector("input#party-time-zone-input");
+        const timeZoneOptions = p.querySelector("datalist#time-zone-options");
 
         nameInput.value = partyDetails.name;
         dateInput.value = partyDetails.date || '';
@@ -371,6 +623,24 @@ async function renderParty(partyId) {
         maxGuestsInput.value = partyDetails.max_guests || 0;
         frozenInput.checked = partyDetails.frozen || false;
         publicInput.checked = partyDetails.public || false;
+        waitlistInput.checked = partyDetails.waitlist || false;
+        maxCompanionsInput.value = partyDetails.max_companions || 0;
+        askCompanionNamesInput.checked = partyDetails.ask_companion_names || false;
+        languageInput.value = partyDetails.language || '';
+
+        // Parties without a time zone are in the server's default zone
+        timeZoneInput.value = partyDetails.time_zone || partyDetails.default_time_zone || '';
+        (Intl.supportedValuesOf ? Intl.supportedValuesOf('timeZone') : []).forEach(zone => {
+            const option = document.createElement('option');
+            option.value = zone;
+            timeZoneOptions.appendChild(option);
+        });
+
+        // Headcount includes companions, so it can differ from the number of guests
+        if (partyDetails.headcount !== null && partyDetails.headcount !== undefined) {
+            p.querySelector("#party-headcount-value").textContent = partyDetails.headcount;
+            p.querySelector("#party-headcount").style.display = 'inline';
+        }
 
         // Show/hide public link button based on public checkbox
         const togglePublicLink = () => {
@@ -411,6 +681,8 @@ async function renderParty(partyId) {
 
         p.querySelector("#save-party-btn").addEventListener('click', () => saveParty(partyId));
         p.querySelector("#delete-party-btn").addEventListener('click', () => deleteParty(partyId));
+        p.querySelector("#clone-party-btn").addEventListener('click', () => cloneParty(partyId));
+        p.querySelector("#save-template-btn").addEventListener('click', () => saveAsTemplate(partyId));
         p.querySelector("#add-guest-btn").addEventListener('click', () => showAddGuestModal(partyId));
         p.querySelector("#add-block-btn").addEventListener('click', () => addInvitationBlock());
 
@@ -439,6 +711,52 @@ async function renderParty(partyId) {
                 guestNameElement.appendChild(badge);
             }
 
+            // Household members share one invitation link, clicking the badge copies it
+            if (guest.household_name) {
+                const badge = document.createElement('span');
+                badge.style.cssText = 'margin-left: 8px; padding: 2px 6px; background-color: #764ba2; color: white; border-radius: 3px; font-size: 0.7em; font-weight: normal;';
+                badge.textContent = guest.household_name;
+                if (guest.household_invitation_id) {
+                    badge.style.cursor = 'pointer';
+                    badge.title = 'Copy household invitation link';
+                    badge.addEventListener('click', async (e) => {
+                        e.preventDefault();
+                        e.stopPropagation();
+                        const householdUrl = `${window.location.origin}/${guest.household_invitation_id}`;
+                        try {
+                            await navigator.clipboard.writeText(householdUrl);
+                            showToast('Household invitation link copied to clipboard!', 'success');
+                        } catch (err) {
+                            console.error('Failed to copy to clipboard:', err);
+                            showToast('Failed to copy household invitation link', 'error');
+                        }
+                    });
+                }
+                guestNameElement.appendChild(badge);
+            }
+
+            // Show how many companions the guest brings
+            if (guest.companions > 0) {
+                const badge = document.createElement('span');
+                badge.style.cssText = 'margin-left: 8px; padding: 2px 6px; background-color: #2196F3; color: white; border-radius: 3px; font-size: 0.7em; font-weight: normal;';
+                badge.textContent = `+${guest.companions}`;
+                badge.title = (guest.companion_names || []).filter(name => name).join('\n');
+                guestNameElement.appendChild(badge);
+            }
+
+            // Flag guests who still have to answer required questions
+            if (guest.missing_required && guest.missing_required.length > 0) {
+                const labels = guest.missing_required.map(blockId => {
+                    const block = partyDetails.invitation_blocks.find(b => b.id === blockId);
+                    return block?.label || blockId;
+                });
+                const badge = document.createElement('span');
+                badge.style.cssText = 'margin-left: 8px; padding: 2px 6px; background-color: #ff9800; color: white; border-radius: 3px; font-size: 0.7em; font-weight: normal;';
+                badge.textContent = `! ${guest.missing_required.length}`;
+                badge.title = labels.join('\n');
+                guestNameElement.appendChild(badge);
+            }
+
             const organizerButton = guestElement.querySelector("button#guest-organizer");
             const chevronUp = organizerButton.querySelector('#guest-promote');
             const chevronDown = organizerButton.querySelector('#guest-demote');
@@ -508,11 +826,20 @@ async function renderParty(partyId) {
             guestsContainer.appendChild(guestElement);
         });
 
+        renderWaitlist(p, partyId, partyDetails);
+        renderRecurrence(p, partyId, partyDetails);
+        renderDatePolls(p, partyId, partyDetails);
+
         const invitationBlocksContainer = p.querySelector("div#invitation-blocks");
         const invitationBlocks = Array.isArray(partyDetails.invitation_blocks)
             ? partyDetails.invitation_blocks
             : [];
 
+        // Image and file blocks pick from the organizer's uploads
+        const attachmentsResponse = await fetch('/attachment/list');
+        allAttachments = attachmentsResponse.ok ? await attachmentsResponse.json() : [];
+        blockEditorPartyId = partyId;
+
         initializeBlockEditor(invitationBlocksContainer, invitationBlocks);
 
         main.appendChild(p);
@@ -523,6 +850,366 @@ async function renderParty(partyId) {
     }
 }
 
+function renderWaitlist(p, partyId, partyDetails) {
+    const entries = partyDetails.waitlist_entries || [];
+    p.querySelector("#waitlist-section").style.display = partyDetails.waitlist || entries.length > 0 ? 'block' : 'none';
+    p.querySelector("#waitlist-empty").style.display = entries.length > 0 ? 'none' : 'block';
+
+    const waitlistContainer = p.querySelector("div#waitlist");
+    const order = entries.map(entry => entry.invitation_id);
+
+    const move = async (index, offset) => {
+        const newOrder = [...order];
+        [newOrder[index], newOrder[index + offset]] = [newOrder[index + offset], newOrder[index]];
+
+        const response = await fetch(`/party/${partyId}/waitlist`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin',
+            body: JSON.stringify({ invitation_ids: newOrder })
+        });
+
+        if (response.ok) {
+            renderParty(partyId);
+        } else {
+            const error = await response.json();
+            console.error('Failed to reorder waitlist:', error);
+            showToast('Failed to reorder waitlist: ' + (error.error || 'Unknown error'), 'error');
+        }
+    };
+
+    entries.forEach((entry, index) => {
+        const entryElement = templateWaitlistEntry.content.cloneNode(true);
+        entryElement.querySelector("#waitlist-position").textContent = `${entry.position}.`;
+        const entryName = entry.name || 'Unnamed Guest';
+        entryElement.querySelector("#waitlist-name").textContent = entry.companions > 0 ? `${entryName} (+${entry.companions})` : entryName;
+
+        const upButton = entryElement.querySelector("button#waitlist-up");
+        const downButton = entryElement.querySelector("button#waitlist-down");
+        upButton.disabled = index === 0;
+        downButton.disabled = index === entries.length - 1;
+        upButton.addEventListener('click', () => move(index, -1));
+        downButton.addEventListener('click', () => move(index, 1));
+
+        waitlistContainer.appendChild(entryElement);
+    });
+}
+
+function formatSlot(slot) {
+    const date = new Date(slot);
+    if (isNaN(date)) return slot;
+    return date.toLocaleString(document.documentElement.lang || undefined, {
+        weekday: 'short', day: 'numeric', month: 'short', hour: '2-digit', minute: '2-digit'
+    });
+}
+
+function renderDatePolls(p, partyId, partyDetails) {
+    const polls = partyDetails.date_polls || {};
+    const pollBlocks = partyDetails.invitation_blocks.filter(block => block.type === 'date_poll' && polls[block.id]);
+    p.querySelector("#date-polls-section").style.display = pollBlocks.length > 0 ? 'block' : 'none';
+
+    const pollsContainer = p.querySelector("div#date-polls");
+    const symbols = ['✓', '(✓)', '✗'];
+    pollBlocks.forEach(block => {
+        const results = polls[block.id];
+        const resultsElement = templateDatePollResults.content.cloneNode(true);
+        resultsElement.querySelector(".date-poll-label").textContent = block.label;
+        const headRow = resultsElement.querySelector("thead tr");
+        const totalsRow = resultsElement.querySelector("tr.date-poll-totals");
+        const adoptRow = resultsElement.querySelector("tr.date-poll-adopt");
+        const tbody = resultsElement.querySelector("tbody");
+
+        results.slots.forEach((slot, i) => {
+            const th = document.createElement('th');
+            th.textContent = formatSlot(slot.slot);
+            const total = document.createElement('td');
+            total.textContent = `${slot.yes} (${slot.if_need_be})`;
+            const adopt = document.createElement('td');
+            const adoptButton = templateDatePollAdoptButton.content.cloneNode(true);
+            adoptButton.querySelector("button").addEventListener('click', () => adoptSlot(partyId, block.id, i, slot.slot));
+            adopt.appendChild(adoptButton);
+            if (results.winner === i) {
+                [th, total, adopt].forEach(cell => cell.classList.add('winner'));
+            }
+            if (partyDetails.date === slot.slot) {
+                th.classList.add('adopted');
+            }
+            headRow.appendChild(th);
+            totalsRow.appendChild(total);
+            adoptRow.appendChild(adopt);
+        });
+
+        results.rows.forEach(row => {
+            const tr = document.createElement('tr');
+            const nameCell = document.createElement('td');
+            nameCell.textContent = row.guest_name || 'Unnamed Guest';
+            tr.appendChild(nameCell);
+            results.slots.forEach((_, i) => {
+                const td = document.createElement('td');
+                const choice = row.answers[i];
+                if (typeof choice === 'number') {
+                    td.textContent = symbols[choice] || '';
+                    td.classList.add(`choice-${choice}`);
+                }
+                if (results.winner === i) td.classList.add('winner');
+                tr.appendChild(td);
+            });
+            tbody.appendChild(tr);
+        });
+
+        pollsContainer.appendChild(resultsElement);
+    });
+}
+
+async function adoptSlot(partyId, blockId, slotIndex, slot) {
+    const confirmed = confirm(`Set the party date to ${formatSlot(slot)}? All guests will be notified.`);
+    if (!confirmed) return;
+
+    try {
+        const response = await fetch(`/party/${partyId}/adopt_slot`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin',
+            body: JSON.stringify({ block_id: blockId, slot: slotIndex })
+        });
+
+        if (response.ok) {
+            showToast('Party date set and guests notified!', 'success');
+            renderParties();
+            renderParty(partyId);
+        } else {
+            const error = await response.json();
+            console.error('Failed to set party date:', error);
+            showToast('Failed to set party date: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error setting party date:', error);
+        showToast('Error setting party date. Please try again.', 'error');
+    }
+}
+
+// Weekday codes as used by RRULE BYDAY, indexed like Date.getDay()
+const RRULE_WEEKDAYS = ['SU', 'MO', 'TU', 'WE', 'TH', 'FR', 'SA'];
+
+function recurrenceRule(preset, date, custom) {
+    switch (preset) {
+        case 'weekly':
+            return 'FREQ=WEEKLY';
+        case 'biweekly':
+            return 'FREQ=WEEKLY;INTERVAL=2';
+        case 'monthly-day':
+            return `FREQ=MONTHLY;BYMONTHDAY=${date.getDate()}`;
+        case 'monthly-weekday': {
+            // The fifth weekday of a month doesn't always exist, so it becomes the last one
+            const ordinal = Math.ceil(date.getDate() / 7);
+            return `FREQ=MONTHLY;BYDAY=${ordinal > 4 ? -1 : ordinal}${RRULE_WEEKDAYS[date.getDay()]}`;
+        }
+        default:
+            return custom.trim();
+    }
+}
+
+function renderRecurrence(p, partyId, partyDetails) {
+    const series = partyDetails.series;
+    p.querySelector("#recurrence-setup").style.display = series ? 'none' : 'block';
+    p.querySelector("#series-info").style.display = series ? 'block' : 'none';
+
+    if (!series) {
+        const presetSelect = p.querySelector("select#recurrence-preset");
+        const customContainer = p.querySelector("#recurrence-custom");
+        const customInput = p.querySelector("input#recurrence-custom-input");
+        presetSelect.addEventListener('change', () => {
+            customContainer.style.display = presetSelect.value === 'custom' ? 'block' : 'none';
+        });
+        p.querySelector("#make-recurring-btn").addEventListener('click', () => {
+            if (!partyDetails.date) {
+                showToast('Please save a date and time first', 'warning');
+                return;
+            }
+            const rrule = recurrenceRule(presetSelect.value, new Date(partyDetails.date), customInput.value);
+            makeRecurring(partyId, rrule);
+        });
+        return;
+    }
+
+    p.querySelector("#series-rule").textContent = series.rrule;
+    p.querySelector("#edit-series-rule-btn").addEventListener('click', () => changeSeriesRule(partyId, series));
+    p.querySelector("#series-guests-btn").addEventListener('click', () => useGuestsForSeries(partyId, series.id));
+    p.querySelector("#end-series-btn").addEventListener('click', () => endSeries(partyId, series.id));
+    p.querySelector("#copy-series-feed-btn").addEventListener('click', async (e) => {
+        e.preventDefault();
+        const feedUrl = `${window.location.origin}/series/${series.id}/ics`;
+        try {
+            await navigator.clipboard.writeText(feedUrl);
+            showToast('Calendar feed link copied to clipboard!', 'success');
+        } catch (err) {
+            console.error('Failed to copy to clipboard:', err);
+            showToast('Failed to copy calendar feed link', 'error');
+        }
+    });
+
+    // Loading the series also creates occurrences that moved into the planning window
+    const occurrencesContainer = p.querySelector("div#series-occurrences");
+    fetch(`/series/${series.id}`)
+        .then(response => {
+            if (!response.ok) throw new Error('Failed to load series');
+            return response.json();
+        })
+        .then(details => {
+            details.occurrences.forEach(occurrence => {
+                const occurrenceElement = templateSeriesOccurrence.content.cloneNode(true);
+                const button = occurrenceElement.querySelector("button");
+                occurrenceElement.querySelector("#occurrence-date").textContent =
+                    new Date(occurrence.date).toLocaleString([], { dateStyle: 'medium', timeStyle: 'short' });
+                if (occurrence.overrides.length > 0) {
+                    const badge = occurrenceElement.querySelector("#occurrence-customized");
+                    badge.style.display = 'inline';
+                    badge.title = occurrence.overrides.join(', ');
+                }
+                if (occurrence.party_id === partyId) {
+                    button.classList.add('current');
+                } else {
+                    button.addEventListener('click', () => renderParty(occurrence.party_id));
+                }
+                occurrencesContainer.appendChild(occurrenceElement);
+            });
+        })
+        .catch(error => {
+            console.error('Error loading series:', error);
+            showToast('Error loading series occurrences', 'error');
+        });
+}
+
+async function makeRecurring(partyId, rrule) {
+    if (!rrule) {
+        showToast('Please enter a recurrence rule', 'warning');
+        return;
+    }
+
+    try {
+        const response = await fetch(`/series/from_party/${partyId}`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin',
+            body: JSON.stringify({ rrule })
+        });
+
+        if (response.ok) {
+            const result = await response.json();
+            showToast(`Series created with ${result.created} more occurrences!`, 'success');
+            renderParties();
+            renderParty(partyId);
+        } else {
+            const error = await response.json();
+            console.error('Failed to create series:', error);
+            showToast('Failed to create series: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error creating series:', error);
+        showToast('Error creating series. Please try again.', 'error');
+    }
+}
+
+async function changeSeriesRule(partyId, series) {
+    const rrule = prompt('Recurrence rule (upcoming occurrences the new rule skips are deleted):', series.rrule);
+    if (rrule === null || rrule.trim() === series.rrule) return;
+
+    try {
+        const response = await fetch(`/series/${series.id}/rule`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin',
+            body: JSON.stringify({ rrule: rrule.trim() })
+        });
+
+        if (response.ok) {
+            showToast('Series rule updated successfully!', 'success');
+            renderParties();
+            renderParty(partyId);
+        } else {
+            const error = await response.json();
+            console.error('Failed to update series rule:', error);
+            showToast('Failed to update series rule: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error updating series rule:', error);
+        showToast('Error updating series rule. Please try again.', 'error');
+    }
+}
+
+async function useGuestsForSeries(partyId, seriesId) {
+    const confirmed = confirm('Use the guests of this occurrence for the whole series? Missing guests are invited to all upcoming occurrences.');
+    if (!confirmed) return;
+
+    try {
+        const response = await fetch(`/series/${seriesId}/guests`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin',
+            body: JSON.stringify({ party_id: partyId })
+        });
+
+        if (response.ok) {
+            const result = await response.json();
+            showToast(`Series guest list updated, ${result.invited} guests newly invited!`, 'success');
+        } else {
+            const error = await response.json();
+            console.error('Failed to update series guests:', error);
+            showToast('Failed to update series guests: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error updating series guests:', error);
+        showToast('Error updating series guests. Please try again.', 'error');
+    }
+}
+
+async function endSeries(partyId, seriesId) {
+    const confirmed = confirm('End this series? Its occurrences stay as independent parties.');
+    if (!confirmed) return;
+    const deleteUpcoming = confirm('Also delete all upcoming occurrences, including their guests and answers? Press "Cancel" to keep them.');
+
+    try {
+        const response = await fetch(`/series/${seriesId}/delete`, {
+            method: 'DELETE',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin',
+            body: JSON.stringify({ delete_upcoming: deleteUpcoming })
+        });
+
+        if (response.ok) {
+            showToast('Series ended successfully!', 'success');
+            renderParties();
+            // This occurrence may have been one of the deleted ones
+            const partyResponse = await fetch(`/party/${partyId}`);
+            if (partyResponse.ok) {
+                renderParty(partyId);
+            } else {
+                main.innerHTML = "";
+                main.appendChild(templateEmptyState.content.cloneNode(true));
+            }
+        } else {
+            const error = await response.json();
+            console.error('Failed to end series:', error);
+            showToast('Failed to end series: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error ending series:', error);
+        showToast('Error ending series. Please try again.', 'error');
+    }
+}
+
 async function renderParties() {
     try {
         const response = await fetch('/party');
@@ -547,15 +1234,20 @@ async function renderParties() {
 
 function setupCollapsibleSections() {
     const partiesSection = document.querySelector('aside section:first-child');
+    const householdsSection = document.getElementById('households')?.closest('section');
     const guestsSection = document.querySelector('aside section:last-child');
 
     const partiesCollapseBtn = document.getElementById('parties-collapse');
     const partiesExpandBtn = document.getElementById('parties-expand');
+    const householdsCollapseBtn = document.getElementById('households-collapse');
+    const householdsExpandBtn = document.getElementById('households-expand');
     const guestsCollapseBtn = document.getElementById('guests-collapse');
     const guestsExpandBtn = document.getElementById('guests-expand');
 
     partiesCollapseBtn?.addEventListener('click', () => partiesSection.classList.add('collapsed'));
     partiesExpandBtn?.addEventListener('click', () => partiesSection.classList.remove('collapsed'));
+    householdsCollapseBtn?.addEventListener('click', () => householdsSection.classList.add('collapsed'));
+    householdsExpandBtn?.addEventListener('click', () => householdsSection.classList.remove('collapsed'));
     guestsCollapseBtn?.addEventListener('click', () => guestsSection.classList.add('collapsed'));
     guestsExpandBtn?.addEventListener('click', () => guestsSection.classList.remove('collapsed'));
 }
@@ -572,11 +1264,19 @@ async function showAddGuestModal(partyId) {
         if (!partyResponse.ok) throw new Error('Failed to fetch party details');
         const partyDetails = await partyResponse.json();
         currentPartyGuests = partyDetails.guests.map(guest => guest.id);
+        currentPartyHouseholdLinks = new Set(partyDetails.guests
+            .filter(guest => guest.household_invitation_id)
+            .map(guest => guest.household_id));
+
+        const householdsResponse = await fetch('/guest/households');
+        if (!householdsResponse.ok) throw new Error('Failed to fetch households');
+        allHouseholds = await householdsResponse.json();
 
         const modal = createAddGuestModal();
         document.body.appendChild(modal);
 
         populateGuestList();
+        populateHouseholdList();
         setupGuestSearch();
 
     } catch (error) {
@@ -615,6 +1315,8 @@ function closeAddGuestModal() {
     currentPartyId = null;
     allGuests = [];
     currentPartyGuests = [];
+    allHouseholds = [];
+    currentPartyHouseholdLinks = new Set();
 }
 
 function populateGuestList(filteredGuests = null) {
@@ -652,6 +1354,33 @@ function populateGuestList(filteredGuests = null) {
     });
 }
 
+function populateHouseholdList() {
+    const householdsDiv = document.querySelector("#modal-households");
+    const householdList = document.querySelector("#modal-household-list");
+    if (!householdsDiv || !householdList) return;
+
+    // Only offer households that still have uninvited members or no shared link yet
+    const availableHouseholds = allHouseholds.filter(household =>
+        household.members.some(member => !currentPartyGuests.includes(member.id)) ||
+        (household.members.length > 0 && !currentPartyHouseholdLinks.has(household.id))
+    );
+
+    householdList.innerHTML = "";
+    householdsDiv.style.display = availableHouseholds.length > 0 ? 'block' : 'none';
+
+    availableHouseholds.forEach(household => {
+        const householdItem = templateGuestItem.content.cloneNode(true);
+        const memberNames = household.members.map(member => `${member.first} ${member.last}`.trim());
+
+        householdItem.querySelector(".modal-guest-item").removeAttribute('data-guest-id');
+        householdItem.querySelector(".guest-name").textContent = household.name;
+        householdItem.querySelector(".guest-name").title = memberNames.join('\n');
+        householdItem.querySelector(".btn-add-guest").addEventListener('click', () => addHouseholdToParty(household.id));
+
+        householdList.appendChild(householdItem);
+    });
+}
+
 function setupGuestSearch() {
     const searchInput = document.querySelector("#guest-search");
     if (!searchInput) return;
@@ -693,6 +1422,28 @@ async function addGuestToParty(guestId, guestName) {
     }
 }
 
+async function addHouseholdToParty(householdId) {
+    try {
+        const response = await fetch(`/party/${currentPartyId}/add_household/${householdId}`, {
+            method: 'POST',
+            credentials: 'same-origin'
+        });
+
+        if (response.ok) {
+            const partyId = currentPartyId;
+            closeAddGuestModal();
+            renderParty(partyId);
+        } else {
+            const error = await response.json();
+            console.error('Failed to add household:', error);
+            showToast('Failed to add household: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error adding household:', error);
+        showToast('Error adding household. Please try again.', 'error');
+    }
+}
+
 async function saveParty(partyId) {
     try {
         const nameInput = document.querySelector("input#party-name-input");
@@ -703,7 +1454,13 @@ async function saveParty(partyId) {
         const maxGuestsInput = document.querySelector("input#party-max-guests-input");
         const frozenInput = document.querySelector("input#party-frozen-input");
         const publicInput = document.querySelector("input#party-public-input");
+        const waitlistInput = document.querySelector("input#party-waitlist-input");
+        const maxCompanionsInput = document.querySelector("input#party-max-companions-input");
+        const askCompanionNamesInput = document.querySelector("input#party-ask-companion-names-input");
+        const languageInput = document.querySelector("select#party-language-input");
+        const timeZoneInput = document.querySelector("input#party-time-zone-input");
         const changelogInput = document.querySelector("textarea#party-changelog-input");
+        const applyToSeriesInput = document.querySelector("input#apply-to-series-input");
 
         if (!nameInput) {
             showToast('Error: Could not find party name input', 'error');
@@ -738,7 +1495,13 @@ async function saveParty(partyId) {
             max_guests: maxGuestsInput ? parseInt(maxGuestsInput.value) || 0 : 0,
             frozen: frozenInput ? frozenInput.checked : false,
             public: publicInput ? publicInput.checked : false,
-            changelog: changelogInput ? changelogInput.value.trim() : ''
+            waitlist: waitlistInput ? waitlistInput.checked : false,
+            max_companions: maxCompanionsInput ? parseInt(maxCompanionsInput.value) || 0 : 0,
+            ask_companion_names: askCompanionNamesInput ? askCompanionNamesInput.checked : false,
+            language: languageInput ? languageInput.value : '',
+            time_zone: timeZoneInput ? timeZoneInput.value.trim() : '',
+            changelog: changelogInput ? changelogInput.value.trim() : '',
+            apply_to_series: applyToSeriesInput ? applyToSeriesInput.checked : false
         };
 
         const response = await fetch(`/party/${partyId}/update`, {
@@ -757,6 +1520,10 @@ async function saveParty(partyId) {
                 changelogInput.value = '';
             }
             renderParties();
+            // Overrides of series occurrences changed, so the occurrence list has to be refreshed
+            if (document.querySelector("#series-info")?.style.display === 'block') {
+                renderParty(partyId);
+            }
         } else {
             const error = await response.json();
             console.error('Failed to save party:', error);
@@ -800,14 +1567,144 @@ async function deleteParty(partyId) {
     }
 }
 
-async function createNewParty() {
+async function cloneParty(partyId) {
+    const includeGuests = confirm('Also invite the same guests to the copy? They get fresh invitations without any answers.');
+
+    try {
+        const response = await fetch(`/party/${partyId}/clone`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin',
+            body: JSON.stringify({ include_guests: includeGuests })
+        });
+
+        if (response.ok) {
+            const result = await response.json();
+            showToast('Party cloned successfully!', 'success');
+            renderParties();
+            if (result.party_id) {
+                renderParty(result.party_id);
+            }
+        } else {
+            const error = await response.json();
+            console.error('Failed to clone party:', error);
+            showToast('Failed to clone party: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error cloning party:', error);
+        showToast('Error cloning party. Please try again.', 'error');
+    }
+}
+
+async function saveAsTemplate(partyId) {
+    const nameInput = document.querySelector("input#party-name-input");
+    const templateName = prompt('Template name:', nameInput ? nameInput.value : '');
+    if (templateName === null) return;
+    if (!templateName.trim()) {
+        showToast('Please enter a template name', 'warning');
+        return;
+    }
+
+    try {
+        // Templates are made from the saved blocks, so unsaved edits are not included
+        const response = await fetch(`/party/${partyId}/save_template`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin',
+            body: JSON.stringify({ name: templateName.trim() })
+        });
+
+        if (response.ok) {
+            showToast('Template saved successfully!', 'success');
+        } else {
+            const error = await response.json();
+            console.error('Failed to save template:', error);
+            showToast('Failed to save template: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error saving template:', error);
+        showToast('Error saving template. Please try again.', 'error');
+    }
+}
+
+async function showNewPartyModal() {
+    try {
+        const response = await fetch('/party/templates');
+        if (!response.ok) throw new Error('Failed to fetch templates');
+        const templates = await response.json();
+
+        // Without templates there is nothing to choose from
+        if (templates.length === 0) {
+            createNewParty();
+            return;
+        }
+
+        const modal = templateNewPartyModal.content.cloneNode(true);
+        const modalOverlay = modal.querySelector(".modal-overlay");
+        modalOverlay.classList.add('show');
+
+        const closeModal = () => modalOverlay.remove();
+        modal.querySelector(".modal-close").addEventListener('click', closeModal);
+        modal.querySelector(".btn-cancel").addEventListener('click', closeModal);
+        modalOverlay.addEventListener('click', (e) => {
+            if (e.target === modalOverlay) closeModal();
+        });
+
+        modal.querySelector("#blank-party-btn").addEventListener('click', () => {
+            closeModal();
+            createNewParty();
+        });
+
+        const templateList = modal.querySelector("#modal-template-list");
+        templates.forEach(template => {
+            const item = templateModalTemplateItem.content.cloneNode(true);
+            const itemDiv = item.querySelector(".modal-guest-item");
+            item.querySelector(".template-name").textContent = template.name;
+            item.querySelector(".template-block-count").textContent = template.block_count;
+            item.querySelector(".btn-use-template").addEventListener('click', () => {
+                closeModal();
+                createNewParty(template.id);
+            });
+            item.querySelector(".btn-delete-template").addEventListener('click', async () => {
+                if (!confirm(`Are you sure you want to delete the template "${template.name}"? Parties created from it are not affected.`)) {
+                    return;
+                }
+                const deleteResponse = await fetch(`/party/templates/${template.id}/delete`, {
+                    method: 'DELETE',
+                    credentials: 'same-origin'
+                });
+                if (deleteResponse.ok) {
+                    itemDiv.remove();
+                    showToast('Template deleted successfully!', 'success');
+                } else {
+                    const error = await deleteResponse.json();
+                    console.error('Failed to delete template:', error);
+                    showToast('Failed to delete template: ' + (error.error || 'Unknown error'), 'error');
+                }
+            });
+            templateList.appendChild(item);
+        });
+
+        document.body.appendChild(modal);
+    } catch (error) {
+        console.error('Error loading templates:', error);
+        createNewParty();
+    }
+}
+
+async function createNewParty(templateId = null) {
     try {
         const response = await fetch('/party/new', {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
             },
-            credentials: 'same-origin'
+            credentials: 'same-origin',
+            body: JSON.stringify(templateId ? { template_id: templateId } : {})
         });
 
         if (response.ok) {
@@ -864,12 +1761,14 @@ async function renderGuest(guestId) {
         const firstInput = g.querySelector("input#guest-edit-first");
         const lastInput = g.querySelector("input#guest-edit-last");
         const emailInput = g.querySelector("input#guest-edit-email");
+        const languageSelect = g.querySelector("select#guest-edit-language");
         const noteTextarea = g.querySelector("textarea#guest-edit-note");
         
         salutationInput.value = guestDetails.salutation || '';
         firstInput.value = guestDetails.first || '';
         lastInput.value = guestDetails.last || '';
         emailInput.value = guestDetails.email || '';
+        languageSelect.value = guestDetails.language || '';
         noteTextarea.value = guestDetails.note || '';
 
         // Show selfcreated badge if applicable
@@ -895,6 +1794,7 @@ async function saveGuest(guestId) {
         const firstInput = document.querySelector("input#guest-edit-first");
         const lastInput = document.querySelector("input#guest-edit-last");
         const emailInput = document.querySelector("input#guest-edit-email");
+        const languageSelect = document.querySelector("select#guest-edit-language");
         const noteTextarea = document.querySelector("textarea#guest-edit-note");
         
         if (!firstInput || !lastInput) {
@@ -916,6 +1816,7 @@ async function saveGuest(guestId) {
             first: firstName,
             last: lastName,
             email: emailInput ? emailInput.value.trim() : '',
+            language: languageSelect ? languageSelect.value : '',
             note: noteTextarea ? noteTextarea.value.trim() : ''
         };
 
@@ -1003,6 +1904,174 @@ async function createNewGuest() {
     }
 }
 
+async function renderHouseholds() {
+    try {
+        const response = await fetch('/guest/households');
+        if (!response.ok) throw new Error('Failed to load households');
+        const myHouseholds = await response.json();
+
+        households.innerHTML = "";
+
+        myHouseholds.forEach(household => {
+            const hl = templateHouseholdLi.content.cloneNode(true);
+            const nameBtn = hl.querySelector("button#household-sidebar-name");
+            nameBtn.textContent = household.name || 'Unnamed Household';
+            nameBtn.addEventListener("click", () => renderHousehold(household.id));
+            households.appendChild(hl);
+        });
+    } catch (error) {
+        console.error('Error loading households:', error);
+        households.innerHTML = "";
+        households.appendChild(templateError.content.cloneNode(true));
+    }
+}
+
+async function renderHousehold(householdId) {
+    try {
+        const [householdResponse, guestsResponse, householdsResponse] = await Promise.all([
+            fetch(`/guest/households/${householdId}`),
+            fetch('/guest'),
+            fetch('/guest/households')
+        ]);
+        if (!householdResponse.ok || !guestsResponse.ok || !householdsResponse.ok) {
+            throw new Error('Failed to load household');
+        }
+        const householdDetails = await householdResponse.json();
+        const myGuests = await guestsResponse.json();
+        const householdNames = new Map((await householdsResponse.json()).map(household => [household.id, household.name]));
+
+        main.innerHTML = "";
+        const h = templateEditHousehold.content.cloneNode(true);
+
+        h.querySelector("input#household-edit-name").value = householdDetails.name || '';
+
+        // Every guest can be picked, members of other households are moved over on save
+        const membersContainer = h.querySelector("div#household-members");
+        myGuests.forEach(guest => {
+            const memberElement = templateHouseholdMember.content.cloneNode(true);
+            const checkbox = memberElement.querySelector("input");
+            checkbox.value = guest.id;
+            checkbox.checked = guest.household_id === householdId;
+
+            let displayName = `${guest.first} ${guest.last}`.trim() || 'Unnamed Guest';
+            if (guest.household_id && guest.household_id !== householdId) {
+                displayName += ` (${householdNames.get(guest.household_id) || '?'})`;
+            }
+            memberElement.querySelector(".household-member-name").textContent = displayName;
+            membersContainer.appendChild(memberElement);
+        });
+
+        h.querySelector("#save-household-btn").addEventListener('click', () => saveHousehold(householdId));
+        h.querySelector("#delete-household-btn").addEventListener('click', () => deleteHousehold(householdId));
+
+        main.appendChild(h);
+    } catch (error) {
+        console.error('Error rendering household:', error);
+        main.innerHTML = "";
+        main.appendChild(templateError.content.cloneNode(true));
+    }
+}
+
+async function saveHousehold(householdId) {
+    try {
+        const nameInput = document.querySelector("input#household-edit-name");
+        if (!nameInput) {
+            showToast('Error: Could not find household name input', 'error');
+            return;
+        }
+
+        const householdName = nameInput.value.trim();
+        if (!householdName) {
+            showToast('Please enter a household name', 'warning');
+            nameInput.focus();
+            return;
+        }
+
+        const memberIds = Array.from(document.querySelectorAll("div#household-members input:checked"))
+            .map(checkbox => checkbox.value);
+
+        const response = await fetch(`/guest/households/${householdId}/update`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin',
+            body: JSON.stringify({ name: householdName, member_ids: memberIds })
+        });
+
+        if (response.ok) {
+            showToast('Household saved successfully!', 'success');
+            renderHouseholds();
+            renderHousehold(householdId);
+        } else {
+            const error = await response.json();
+            console.error('Failed to save household:', error);
+            showToast('Failed to save household: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error saving household:', error);
+        showToast('Error saving household. Please try again.', 'error');
+    }
+}
+
+async function deleteHousehold(householdId) {
+    const nameInput = document.querySelector("input#household-edit-name");
+    const householdName = nameInput ? nameInput.value.trim() || 'this household' : 'this household';
+    const confirmed = confirm(`Are you sure you want to delete "${householdName}"? Its members stay in your guests and keep their invitations.`);
+
+    if (!confirmed) {
+        return;
+    }
+
+    try {
+        const response = await fetch(`/guest/households/${householdId}/delete`, {
+            method: 'DELETE',
+            credentials: 'same-origin'
+        });
+
+        if (response.ok) {
+            showToast('Household deleted successfully!', 'success');
+            main.innerHTML = "";
+            main.appendChild(templateEmptyState.content.cloneNode(true));
+            renderHouseholds();
+        } else {
+            const error = await response.json();
+            console.error('Failed to delete household:', error);
+            showToast('Failed to delete household: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error deleting household:', error);
+        showToast('Error deleting household. Please try again.', 'error');
+    }
+}
+
+async function createNewHousehold() {
+    try {
+        const response = await fetch('/guest/households/new', {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            credentials: 'same-origin'
+        });
+
+        if (response.ok) {
+            const result = await response.json();
+            renderHouseholds();
+            if (result.household_id) {
+                renderHousehold(result.household_id);
+            }
+        } else {
+            const error = await response.json();
+            console.error('Failed to create household:', error);
+            showToast('Failed to create household: ' + (error.error || 'Unknown error'), 'error');
+        }
+    } catch (error) {
+        console.error('Error creating household:', error);
+        showToast('Error creating household. Please try again.', 'error');
+    }
+}
+
 function generateBlockId() {
     // random UUID-like string without external libraries
     return 'block_' + 'xxxxxxxx-xxxx-4xxx-yxxx-xxxxxxxxxxxx'.replace(/[xy]/g, function (c) {
@@ -1013,13 +2082,47 @@ function generateBlockId() {
 }
 
 if (addPartyBtn) {
-    addPartyBtn.addEventListener('click', createNewParty);
+    addPartyBtn.addEventListener('click', showNewPartyModal);
 }
 
 if (addNewGuestBtn) {
     addNewGuestBtn.addEventListener('click', createNewGuest);
 }
 
+if (addHouseholdBtn) {
+    addHouseholdBtn.addEventListener('click', createNewHousehold);
+}
+
+// The feed of all parties, for calendar apps to subscribe to
+async function copyPartiesFeed(reset) {
+    try {
+        const response = reset
+            ? await fetch('/party/calendar_feed/reset', { method: 'POST' })
+            : await fetch('/party/calendar_feed');
+        if (!response.ok) throw new Error('Failed to load calendar feed');
+        const { url } = await response.json();
+        await navigator.clipboard.writeText(url);
+        showToast(reset
+            ? 'New calendar feed link copied to clipboard! The old link no longer works.'
+            : 'Calendar feed link copied to clipboard!', 'success');
+    } catch (err) {
+        console.error('Failed to copy calendar feed link:', err);
+        showToast('Failed to copy calendar feed link', 'error');
+    }
+}
+
+if (copyPartiesFeedBtn) {
+    copyPartiesFeedBtn.addEventListener('click', () => copyPartiesFeed(false));
+}
+
+if (resetPartiesFeedBtn) {
+    resetPartiesFeedBtn.addEventListener('click', () => {
+        if (confirm('Create a new calendar feed link? Calendars subscribed to the old link stop receiving updates.')) {
+            copyPartiesFeed(true);
+        }
+    });
+}
+
 // Initialize collapsible sections when the page loads
 setupCollapsibleSections();
 
@@ -1027,5 +2130,6 @@ setupCollapsibleSections();
 main.appendChild(templateEmptyState.content.cloneNode(true));
 
 renderParties();
+renderHouseholds();
 renderGuests();
 
@@ -4,7 +4,7 @@ if (!storedData) {
     window.location.href = '/';
 }
 
-const { partyId, answers } = JSON.parse(storedData);
+const { partyId, answers, companions, companion_names } = JSON.parse(storedData);
 
 document.getElementById('registration-form').addEventListener('submit', async (e) => {
     e.preventDefault();
@@ -53,7 +53,7 @@ document.getElementById('registration-form').addEventListener('submit', async (e
             headers: {
                 'Content-Type': 'application/json'
             },
-            body: JSON.stringify({ answers })
+            body: JSON.stringify({ answers, companions, companion_names })
         });
         
         // Always clear stored data and redirect to invitation, even if save fails